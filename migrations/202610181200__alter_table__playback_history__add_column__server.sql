@@ -0,0 +1,7 @@
+ALTER TABLE "playback_history_events" ADD COLUMN "server" TEXT NOT NULL DEFAULT 'default';
+
+ALTER TABLE "playback_history_metadata" ADD COLUMN "server" TEXT NOT NULL DEFAULT 'default';
+
+CREATE INDEX "playback_history_server_play_id_idx" ON "playback_history_events" ("server", "play_id");
+CREATE INDEX "playback_history_server_recorded_at_idx" ON "playback_history_events" ("server", "recorded_at");
+CREATE INDEX "playback_history_metadata_server_play_id_idx" ON "playback_history_metadata" ("server", "play_id");
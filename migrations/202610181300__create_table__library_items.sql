@@ -0,0 +1,49 @@
+CREATE TABLE "library_items" (
+    "id"           INTEGER PRIMARY KEY,
+    "server"       TEXT NOT NULL,
+    "kind"         TEXT NOT NULL,
+    "uri"          TEXT NOT NULL,
+    "name"         TEXT NOT NULL,
+    "title"        TEXT NOT NULL,
+    "artist"       TEXT NOT NULL,
+    "album"        TEXT NOT NULL,
+    "album_artist" TEXT NOT NULL,
+    "composer"     TEXT NOT NULL,
+    "genre"        TEXT NOT NULL,
+    "date"         TEXT NOT NULL,
+    "format"       TEXT,
+    "duration"     REAL NOT NULL,
+    "updated_at"   TEXT
+) STRICT;
+
+CREATE UNIQUE INDEX "library_items_server_uri_idx" ON "library_items" ("server", "uri");
+
+CREATE VIRTUAL TABLE "library_items_fts" USING fts5 (
+    "name",
+    "title",
+    "artist",
+    "album",
+    "album_artist",
+    "composer",
+    "genre",
+    "uri",
+    content = 'library_items',
+    content_rowid = 'id',
+    tokenize = 'unicode61 remove_diacritics 2',
+    prefix = '2 3'
+);
+
+CREATE TRIGGER "library_items_fts_insert" AFTER INSERT ON "library_items" BEGIN
+    INSERT INTO "library_items_fts" ("rowid", "name", "title", "artist", "album", "album_artist", "composer", "genre", "uri")
+    VALUES (new."id", new."name", new."title", new."artist", new."album", new."album_artist", new."composer", new."genre", new."uri");
+END;
+
+CREATE TRIGGER "library_items_fts_delete" AFTER DELETE ON "library_items" BEGIN
+    INSERT INTO "library_items_fts" ("library_items_fts", "rowid", "name", "title", "artist", "album", "album_artist", "composer", "genre", "uri")
+    VALUES ('delete', old."id", old."name", old."title", old."artist", old."album", old."album_artist", old."composer", old."genre", old."uri");
+END;
+
+CREATE TABLE "library_syncs" (
+    "server"    TEXT PRIMARY KEY,
+    "synced_at" TEXT NOT NULL
+) STRICT;
@@ -0,0 +1,12 @@
+CREATE TABLE "cover_cache" (
+    "id"            INTEGER PRIMARY KEY,
+    "server"        TEXT NOT NULL,
+    "kind"          TEXT NOT NULL,
+    "key"           TEXT NOT NULL,
+    "last_modified" TEXT NOT NULL,
+    "data"          BLOB NOT NULL,
+    "stale"         INTEGER NOT NULL DEFAULT 0,
+    "cached_at"     TEXT NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX "cover_cache_server_kind_key_idx" ON "cover_cache" ("server", "kind", "key");
@@ -0,0 +1,4 @@
+-- Cached covers don't know what they are, it's cheaper to fetch them again.
+DELETE FROM "cover_cache";
+
+ALTER TABLE "cover_cache" ADD COLUMN "content_type" TEXT NOT NULL DEFAULT 'application/octet-stream';
@@ -0,0 +1,19 @@
+-- Cached covers have no placeholders, fetching them again computes those.
+DELETE FROM "cover_cache";
+
+ALTER TABLE "cover_cache" ADD COLUMN "blurhash" TEXT;
+ALTER TABLE "cover_cache" ADD COLUMN "dominant_color" TEXT;
+
+CREATE TABLE "cover_thumbnails" (
+    "id"                 INTEGER PRIMARY KEY,
+    "server"             TEXT NOT NULL,
+    "kind"               TEXT NOT NULL,
+    "key"                TEXT NOT NULL,
+    "size"               INTEGER NOT NULL,
+    "format"             TEXT NOT NULL,
+    "source_modified_at" INTEGER NOT NULL,
+    "data"               BLOB NOT NULL,
+    "created_at"         TEXT NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX "cover_thumbnails_server_kind_key_size_format_idx" ON "cover_thumbnails" ("server", "kind", "key", "size", "format");
@@ -0,0 +1,9 @@
+CREATE TABLE "cover_strategies" (
+    "id"         INTEGER PRIMARY KEY,
+    "server"     TEXT NOT NULL,
+    "directory"  TEXT NOT NULL,
+    "strategy"   TEXT NOT NULL,
+    "updated_at" TEXT NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX "cover_strategies_server_directory_idx" ON "cover_strategies" ("server", "directory");
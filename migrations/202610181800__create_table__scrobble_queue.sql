@@ -0,0 +1,15 @@
+CREATE TABLE "scrobble_queue" (
+    "id"           INTEGER PRIMARY KEY,
+    "server"       TEXT NOT NULL,
+    "target"       TEXT NOT NULL,
+    "play_id"      INTEGER NOT NULL,
+    "artist"       TEXT NOT NULL,
+    "title"        TEXT NOT NULL,
+    "album"        TEXT,
+    "album_artist" TEXT,
+    "duration"     REAL NOT NULL,
+    "listened_at"  TEXT NOT NULL,
+    "queued_at"    TEXT NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX "scrobble_queue_server_target_play_id_idx" ON "scrobble_queue" ("server", "target", "play_id");
@@ -0,0 +1,6 @@
+CREATE TABLE "playback_history_checkpoints" (
+    "server"          TEXT PRIMARY KEY,
+    "play_id"         INTEGER NOT NULL,
+    "elapsed"         REAL    NOT NULL,
+    "checkpointed_at" TEXT    NOT NULL
+) STRICT;
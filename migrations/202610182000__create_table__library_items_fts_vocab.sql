@@ -0,0 +1 @@
+CREATE VIRTUAL TABLE "library_items_fts_vocab" USING fts5vocab ("library_items_fts", 'row');
@@ -0,0 +1,7 @@
+-- Items indexed so far lack their parent and every tag but the searchable ones, so indexing starts over.
+DELETE FROM "library_items";
+DELETE FROM "library_syncs";
+
+ALTER TABLE "library_items" ADD COLUMN "parent" TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX "library_items_server_parent_idx" ON "library_items" ("server", "parent");
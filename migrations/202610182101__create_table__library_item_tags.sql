@@ -0,0 +1,10 @@
+CREATE TABLE "library_item_tags" (
+    "id"     INTEGER PRIMARY KEY,
+    "server" TEXT NOT NULL,
+    "uri"    TEXT NOT NULL,
+    "key"    TEXT NOT NULL,
+    "value"  TEXT NOT NULL,
+    FOREIGN KEY ("server", "uri") REFERENCES "library_items" ("server", "uri") ON DELETE CASCADE
+) STRICT;
+
+CREATE INDEX "library_item_tags_server_uri_idx" ON "library_item_tags" ("server", "uri");
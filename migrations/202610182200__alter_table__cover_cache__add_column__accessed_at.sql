@@ -0,0 +1,24 @@
+-- Cached covers don't know how big they are or when they were served last, it's cheaper to fetch them again.
+DROP TABLE "cover_thumbnails";
+DELETE FROM "cover_cache";
+
+ALTER TABLE "cover_cache" ADD COLUMN "size" INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE "cover_cache" ADD COLUMN "accessed_at" TEXT NOT NULL DEFAULT '';
+
+CREATE INDEX "cover_cache_accessed_at_idx" ON "cover_cache" ("accessed_at");
+
+-- Thumbnails go along with the cover they were made from.
+CREATE TABLE "cover_thumbnails" (
+    "id"                 INTEGER PRIMARY KEY,
+    "server"             TEXT NOT NULL,
+    "kind"               TEXT NOT NULL,
+    "key"                TEXT NOT NULL,
+    "size"               INTEGER NOT NULL,
+    "format"             TEXT NOT NULL,
+    "source_modified_at" INTEGER NOT NULL,
+    "data"               BLOB NOT NULL,
+    "created_at"         TEXT NOT NULL,
+    FOREIGN KEY ("server", "kind", "key") REFERENCES "cover_cache" ("server", "kind", "key") ON DELETE CASCADE
+) STRICT;
+
+CREATE UNIQUE INDEX "cover_thumbnails_server_kind_key_size_format_idx" ON "cover_thumbnails" ("server", "kind", "key", "size", "format");
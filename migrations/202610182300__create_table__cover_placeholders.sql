@@ -0,0 +1,20 @@
+-- Placeholders are kept apart from the covers they were made from, so they outlive their eviction.
+CREATE TABLE "cover_placeholders" (
+    "id"             INTEGER PRIMARY KEY,
+    "server"         TEXT NOT NULL,
+    "kind"           TEXT NOT NULL,
+    "key"            TEXT NOT NULL,
+    "blurhash"       TEXT NOT NULL,
+    "dominant_color" TEXT NOT NULL,
+    "created_at"     TEXT NOT NULL
+) STRICT;
+
+CREATE UNIQUE INDEX "cover_placeholders_server_kind_key_idx" ON "cover_placeholders" ("server", "kind", "key");
+
+INSERT INTO "cover_placeholders" ("server", "kind", "key", "blurhash", "dominant_color", "created_at")
+SELECT "server", "kind", "key", "blurhash", "dominant_color", "cached_at"
+FROM "cover_cache"
+WHERE "blurhash" IS NOT NULL AND "dominant_color" IS NOT NULL;
+
+ALTER TABLE "cover_cache" DROP COLUMN "blurhash";
+ALTER TABLE "cover_cache" DROP COLUMN "dominant_color";
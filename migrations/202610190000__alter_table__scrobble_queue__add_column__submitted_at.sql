@@ -0,0 +1,4 @@
+-- The last submitted scrobble is kept around, it's where catching up starts from after a restart.
+ALTER TABLE "scrobble_queue" ADD COLUMN "submitted_at" TEXT;
+
+CREATE INDEX "scrobble_queue_server_listened_at_idx" ON "scrobble_queue" ("server", "listened_at");
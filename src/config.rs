@@ -1,10 +1,14 @@
+use std::collections::HashSet;
 use std::env;
 use std::fmt::Formatter;
 use std::ops::Add;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use serde::de::MapAccess;
+use serde::de::SeqAccess;
 use serde::de::Visitor;
+use serde::de::value::MapAccessDeserializer;
 use serde::Deserialize;
 use serde::Deserializer;
 use tokio::fs;
@@ -12,11 +16,13 @@ use tokio::io;
 use tracing::Level;
 
 use crate::args::Args;
+use crate::mpd;
 
 #[derive(Deserialize)]
 #[serde(default, deny_unknown_fields)]
 #[cfg_attr(test, derive(Debug, Eq, PartialEq))]
 pub struct Mpd {
+    pub name: String,
     pub host: String,
     pub port: u32,
     pub password: Option<String>,
@@ -25,6 +31,7 @@ pub struct Mpd {
 impl Default for Mpd {
     fn default() -> Self {
         Mpd {
+            name: Mpd::DEFAULT_NAME.to_owned(),
             host: "localhost".to_owned(),
             port: 6600,
             password: None,
@@ -32,6 +39,91 @@ impl Default for Mpd {
     }
 }
 
+impl Mpd {
+    pub const DEFAULT_NAME: &'static str = "default";
+
+    // MPD_HOST and MPD_PORT take precedence over the config, like they do for other MPD clients.
+    fn apply_env(&mut self, host: Option<String>, port: Option<String>) {
+        if let Some(host) = host {
+            self.host = host;
+        }
+
+        if let Some(port) = port.and_then(|port| port.parse().ok()) {
+            self.port = port;
+        }
+    }
+
+    // Follows the MPD_HOST convention where the host may be
+    // prefixed with a password, i.e. 'password@host'.
+    fn split_host(&self) -> (Option<&str>, &str) {
+        match self.host.split_once('@') {
+            Some((password, host)) if !password.is_empty() => (Some(password), host),
+            _ => (None, self.host.as_str()),
+        }
+    }
+
+    pub fn address(&self) -> mpd::Address {
+        let (_, host) = self.split_host();
+
+        if host.starts_with('/') {
+            mpd::Address::Unix { path: PathBuf::from(host) }
+        } else {
+            mpd::Address::Tcp { host: host.to_owned(), port: self.port }
+        }
+    }
+
+    pub fn password(&self) -> Option<String> {
+        let (password, _) = self.split_host();
+
+        self.password.as_deref()
+            .or(password)
+            .map(ToOwned::to_owned)
+    }
+}
+
+// Accepts either a single [mpd] table or a list of [[mpd]] tables.
+fn deserialize_mpd<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Mpd>, D::Error> {
+    struct MpdVisitor;
+
+    impl<'de> Visitor<'de> for MpdVisitor {
+        type Value = Vec<Mpd>;
+
+        fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+            f.write_str("mpd table or array of mpd tables")
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<Self::Value, A::Error> where A: MapAccess<'de> {
+            Ok(vec![Mpd::deserialize(MapAccessDeserializer::new(map))?])
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error> where A: SeqAccess<'de> {
+            let mut result = Vec::new();
+
+            while let Some(mpd) = seq.next_element()? {
+                result.push(mpd);
+            }
+
+            Ok(result)
+        }
+    }
+
+    let result = deserializer.deserialize_any(MpdVisitor)?;
+
+    if result.is_empty() {
+        return Err(serde::de::Error::custom("expected at least one mpd server"));
+    }
+
+    let mut names = HashSet::new();
+
+    for mpd in &result {
+        if !names.insert(mpd.name.as_str()) {
+            return Err(serde::de::Error::custom(format!("duplicate mpd server name '{}'", mpd.name)));
+        }
+    }
+
+    Ok(result)
+}
+
 #[derive(Deserialize)]
 #[serde(default, deny_unknown_fields)]
 #[cfg_attr(test, derive(Debug, Eq, PartialEq))]
@@ -106,14 +198,133 @@ impl Default for Database {
     }
 }
 
-#[derive(Default, Deserialize)]
+#[derive(Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct Recents {
+    // How far back files count as recently added when the client does not say.
+    pub days: u32,
+}
+
+impl Default for Recents {
+    fn default() -> Self {
+        Recents {
+            days: 7,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(default, deny_unknown_fields)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub struct Covers {
+    // How many MiB of covers are cached, the least recently served ones are evicted past that.
+    pub cache_size: u64,
+}
+
+impl Default for Covers {
+    fn default() -> Self {
+        Covers {
+            cache_size: 512,
+        }
+    }
+}
+
+impl Covers {
+    pub fn cache_size_bytes(&self) -> u64 {
+        self.cache_size.saturating_mul(1024 * 1024)
+    }
+}
+
+fn default_listenbrainz_name() -> String {
+    "listenbrainz".to_owned()
+}
+
+fn default_listenbrainz_url() -> String {
+    "https://api.listenbrainz.org".to_owned()
+}
+
+fn default_lastfm_name() -> String {
+    "lastfm".to_owned()
+}
+
+fn default_lastfm_url() -> String {
+    "https://ws.audioscrobbler.com/2.0/".to_owned()
+}
+
+// Last.fm compatible services only differ by their url, so that's configurable too.
+#[derive(Deserialize, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase", deny_unknown_fields)]
+#[cfg_attr(test, derive(Debug, Eq, PartialEq))]
+pub enum Scrobbler {
+    ListenBrainz {
+        #[serde(default = "default_listenbrainz_name")]
+        name: String,
+        #[serde(default = "default_listenbrainz_url")]
+        url: String,
+        token: String,
+    },
+    LastFm {
+        #[serde(default = "default_lastfm_name")]
+        name: String,
+        #[serde(default = "default_lastfm_url")]
+        url: String,
+        api_key: String,
+        api_secret: String,
+        session_key: String,
+    },
+}
+
+impl Scrobbler {
+    pub fn name(&self) -> &str {
+        match self {
+            Scrobbler::ListenBrainz { name, .. } | Scrobbler::LastFm { name, .. } => name,
+        }
+    }
+}
+
+// Scrobbles waiting to be submitted are kept by name, so it has to be unique.
+fn deserialize_scrobblers<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Scrobbler>, D::Error> {
+    let result = Vec::<Scrobbler>::deserialize(deserializer)?;
+
+    let mut names = HashSet::new();
+
+    for scrobbler in &result {
+        if !names.insert(scrobbler.name()) {
+            return Err(serde::de::Error::custom(format!("duplicate scrobbler name '{}'", scrobbler.name())));
+        }
+    }
+
+    Ok(result)
+}
+
+#[derive(Deserialize)]
 #[serde(default, deny_unknown_fields)]
 #[cfg_attr(test, derive(Debug, Eq, PartialEq))]
 pub struct Config {
-    pub mpd: Mpd,
+    #[serde(deserialize_with = "deserialize_mpd")]
+    pub mpd: Vec<Mpd>,
     pub server: Server,
     pub logging: Logging,
     pub database: Database,
+    pub recents: Recents,
+    pub covers: Covers,
+    #[serde(deserialize_with = "deserialize_scrobblers")]
+    pub scrobblers: Vec<Scrobbler>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            mpd: vec![Mpd::default()],
+            server: Server::default(),
+            logging: Logging::default(),
+            database: Database::default(),
+            recents: Recents::default(),
+            covers: Covers::default(),
+            scrobblers: vec![],
+        }
+    }
 }
 
 fn path() -> Result<PathBuf, String> {
@@ -128,7 +339,24 @@ fn path() -> Result<PathBuf, String> {
     Ok(PathBuf::from(result))
 }
 
+impl Config {
+    // Only the default server is meant by the environment when several are configured.
+    fn apply_env(&mut self, host: Option<String>, port: Option<String>) {
+        if let Some(mpd) = self.mpd.iter_mut().find(|mpd| mpd.name == Mpd::DEFAULT_NAME) {
+            mpd.apply_env(host, port);
+        }
+    }
+}
+
 pub async fn read(args: &Args) -> Result<Config, String> {
+    let mut result = parse(args).await?;
+
+    result.apply_env(env::var("MPD_HOST").ok(), env::var("MPD_PORT").ok());
+
+    Ok(result)
+}
+
+async fn parse(args: &Args) -> Result<Config, String> {
     let path = match &args.config {
         Some(path) => PathBuf::from(path),
         _ => path()?,
@@ -167,6 +395,12 @@ mod tests {
 
         [logging]
         level = "info"
+
+        [recents]
+        days = 7
+
+        [covers]
+        cache_size = 512
     "#;
 
     const CUSTOM_CONFIG1: &str = r#"
@@ -193,6 +427,60 @@ mod tests {
 
         [logging]
         level = "trace"
+
+        [recents]
+        days = 30
+
+        [covers]
+        cache_size = 64
+    "#;
+
+    const CUSTOM_CONFIG4: &str = r#"
+        [mpd]
+        host = "/run/mpd/socket"
+    "#;
+
+    const CUSTOM_CONFIG5: &str = r#"
+        [[mpd]]
+        name = "living-room"
+        host = "10.0.0.1"
+
+        [[mpd]]
+        name = "kitchen"
+        host = "10.0.0.2"
+        port = 6601
+    "#;
+
+    const CUSTOM_CONFIG6: &str = r#"
+        [[scrobblers]]
+        kind = "listenbrainz"
+        token = "qwerty"
+
+        [[scrobblers]]
+        kind = "lastfm"
+        name = "libre.fm"
+        url = "https://libre.fm/2.0/"
+        api_key = "key"
+        api_secret = "secret"
+        session_key = "session"
+    "#;
+
+    const DUPLICATE_SCROBBLER_NAME_CONFIG: &str = r#"
+        [[scrobblers]]
+        kind = "listenbrainz"
+        token = "qwerty"
+
+        [[scrobblers]]
+        kind = "listenbrainz"
+        token = "asdf"
+    "#;
+
+    const DUPLICATE_NAME_CONFIG: &str = r#"
+        [[mpd]]
+        host = "10.0.0.1"
+
+        [[mpd]]
+        host = "10.0.0.2"
     "#;
 
     #[test]
@@ -214,11 +502,12 @@ mod tests {
         let result = toml::from_str::<Config>(CUSTOM_CONFIG1).unwrap();
 
         assert_eq!(result, Config {
-            mpd: Mpd {
+            mpd: vec![Mpd {
                 host: "10.0.0.1".to_owned(),
                 port: 6601,
                 password: Some("qwerty".to_owned()),
-            },
+                ..Mpd::default()
+            }],
             logging: Logging {
                 level: Level::TRACE,
             },
@@ -231,10 +520,10 @@ mod tests {
         let result = toml::from_str::<Config>(CUSTOM_CONFIG2).unwrap();
 
         assert_eq!(result, Config {
-            mpd: Mpd {
+            mpd: vec![Mpd {
                 host: "10.0.0.1".to_owned(),
                 ..Mpd::default()
-            },
+            }],
             database: Database {
                 path: PathBuf::from("/home/user/.data/mpdweb/db")
             },
@@ -254,7 +543,149 @@ mod tests {
             logging: Logging {
                 level: Level::TRACE,
             },
+            recents: Recents {
+                days: 30,
+            },
+            covers: Covers {
+                cache_size: 64,
+            },
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn should_get_tcp_address() {
+        let mpd = Mpd {
+            host: "10.0.0.1".to_owned(),
+            port: 6601,
+            password: None,
+            ..Mpd::default()
+        };
+
+        assert_eq!(mpd.address(), mpd::Address::Tcp { host: "10.0.0.1".to_owned(), port: 6601 });
+        assert_eq!(mpd.password(), None);
+    }
+
+    #[test]
+    fn should_get_unix_address() {
+        let mpd = Mpd {
+            host: "/run/mpd/socket".to_owned(),
+            ..Mpd::default()
+        };
+
+        assert_eq!(mpd.address(), mpd::Address::Unix { path: PathBuf::from("/run/mpd/socket") });
+    }
+
+    #[test]
+    fn should_get_password_from_host() {
+        let mpd = Mpd {
+            host: "qwerty@/run/mpd/socket".to_owned(),
+            ..Mpd::default()
+        };
+
+        assert_eq!(mpd.address(), mpd::Address::Unix { path: PathBuf::from("/run/mpd/socket") });
+        assert_eq!(mpd.password(), Some("qwerty".to_owned()));
+
+        let mpd = Mpd {
+            host: "qwerty@10.0.0.1".to_owned(),
+            port: 6601,
+            password: Some("asdf".to_owned()),
+            ..Mpd::default()
+        };
+
+        assert_eq!(mpd.address(), mpd::Address::Tcp { host: "10.0.0.1".to_owned(), port: 6601 });
+        assert_eq!(mpd.password(), Some("asdf".to_owned()));
+    }
+
+    #[test]
+    fn should_parse_socket_config() {
+        let result = toml::from_str::<Config>(CUSTOM_CONFIG4).unwrap();
+
+        assert_eq!(result, Config {
+            mpd: vec![Mpd {
+                host: "/run/mpd/socket".to_owned(),
+                ..Mpd::default()
+            }],
             ..Config::default()
         });
     }
+
+    #[test]
+    fn should_parse_multiple_servers_config() {
+        let result = toml::from_str::<Config>(CUSTOM_CONFIG5).unwrap();
+
+        assert_eq!(result, Config {
+            mpd: vec![
+                Mpd {
+                    name: "living-room".to_owned(),
+                    host: "10.0.0.1".to_owned(),
+                    ..Mpd::default()
+                },
+                Mpd {
+                    name: "kitchen".to_owned(),
+                    host: "10.0.0.2".to_owned(),
+                    port: 6601,
+                    ..Mpd::default()
+                },
+            ],
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn should_apply_env_to_default_server() {
+        let mut result = toml::from_str::<Config>(CUSTOM_CONFIG1).unwrap();
+
+        result.apply_env(Some("/run/mpd/socket".to_owned()), Some("6602".to_owned()));
+
+        assert_eq!(result.mpd, vec![Mpd {
+            host: "/run/mpd/socket".to_owned(),
+            port: 6602,
+            password: Some("qwerty".to_owned()),
+            ..Mpd::default()
+        }]);
+
+        let mut result = toml::from_str::<Config>(CUSTOM_CONFIG5).unwrap();
+
+        result.apply_env(Some("/run/mpd/socket".to_owned()), None);
+
+        assert_eq!(result, toml::from_str::<Config>(CUSTOM_CONFIG5).unwrap());
+    }
+
+    #[test]
+    fn should_reject_duplicate_server_names() {
+        let result = toml::from_str::<Config>(DUPLICATE_NAME_CONFIG);
+
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn should_parse_scrobblers_config() {
+        let result = toml::from_str::<Config>(CUSTOM_CONFIG6).unwrap();
+
+        assert_eq!(result, Config {
+            scrobblers: vec![
+                Scrobbler::ListenBrainz {
+                    name: "listenbrainz".to_owned(),
+                    url: "https://api.listenbrainz.org".to_owned(),
+                    token: "qwerty".to_owned(),
+                },
+                Scrobbler::LastFm {
+                    name: "libre.fm".to_owned(),
+                    url: "https://libre.fm/2.0/".to_owned(),
+                    api_key: "key".to_owned(),
+                    api_secret: "secret".to_owned(),
+                    session_key: "session".to_owned(),
+                },
+            ],
+            ..Config::default()
+        });
+    }
+
+    #[test]
+    fn should_reject_duplicate_scrobbler_names() {
+        let result = toml::from_str::<Config>(DUPLICATE_SCROBBLER_NAME_CONFIG);
+
+        assert!(result.is_err());
+    }
 }
@@ -0,0 +1,10 @@
+pub use error::Error;
+pub use handle::Cover;
+pub use handle::CoverArtKind;
+pub use handle::Handle;
+pub use crate::persist::CoverPlaceholder;
+pub use crate::persist::CoverThumbnailFormat;
+
+mod error;
+mod handle;
+mod imaging;
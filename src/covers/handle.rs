@@ -0,0 +1,597 @@
+use std::collections::HashMap;
+
+use bytes::Bytes;
+use time::format_description::well_known::Rfc3339;
+use time::OffsetDateTime;
+
+use crate::covers::error::Error;
+use crate::covers::imaging;
+use crate::mpd;
+use crate::mpd::CoverArt;
+use crate::mpd::DbItem;
+use crate::persist;
+use crate::persist::CoverKind;
+use crate::persist::CoverPlaceholder;
+use crate::persist::CoverStrategy;
+use crate::persist::CoverThumbnailFormat;
+
+#[derive(Clone)]
+pub struct Handle {
+    inner: persist::Handle,
+    server: String,
+    cache_size: u64,
+}
+
+impl Handle {
+    pub fn new(persistence_handle: persist::Handle, server: String, cache_size: u64) -> Self {
+        Handle { inner: persistence_handle, server, cache_size }
+    }
+}
+
+pub struct Cover {
+    pub data: Bytes,
+    pub content_type: String,
+    pub last_modified: Option<OffsetDateTime>,
+}
+
+impl From<persist::Cover> for Cover {
+    fn from(cover: persist::Cover) -> Self {
+        Cover {
+            last_modified: OffsetDateTime::parse(&cover.last_modified, &Rfc3339).ok(),
+            content_type: cover.content_type,
+            data: Bytes::from(cover.data),
+        }
+    }
+}
+
+// What kind of cover is asked for, auto tries every strategy until one of them has a cover.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CoverArtKind {
+    File,
+    Embedded,
+    Auto,
+}
+
+const STRATEGIES: [CoverStrategy; 3] = [
+    CoverStrategy::Embedded,
+    CoverStrategy::Directory,
+    CoverStrategy::ParentDirectory,
+];
+
+const PLACEHOLDER_STRATEGIES: [CoverStrategy; 3] = [
+    CoverStrategy::Directory,
+    CoverStrategy::ParentDirectory,
+    CoverStrategy::Embedded,
+];
+
+// What MPD is asked for to get a cover of a song and where it's cached.
+#[derive(Debug, PartialEq)]
+struct Source<'a> {
+    kind: CoverKind,
+    key: &'a str,
+    uri: &'a str,
+    art_kind: mpd::CoverArtKind,
+}
+
+// MPD looks for cover files in the directory of the given uri, so passing it
+// the directory of a song makes it look one level up. Songs that are at most
+// one level deep have no parent worth looking into.
+fn parent_cover_art_uri(uri: &str) -> Option<&str> {
+    let (directory, _) = uri.rsplit_once('/')?;
+
+    directory.contains('/').then_some(directory)
+}
+
+fn directory(uri: &str) -> &str {
+    uri.rsplit_once('/')
+        .map_or("", |(directory, _)| directory)
+}
+
+// MPD looks for cover files in the directory of the song,
+// so all of its songs share a single cache entry.
+fn source(uri: &str, strategy: CoverStrategy) -> Option<Source<'_>> {
+    let source = match strategy {
+        CoverStrategy::Embedded => Source {
+            kind: CoverKind::Embedded,
+            key: uri,
+            uri,
+            art_kind: mpd::CoverArtKind::Embedded,
+        },
+        CoverStrategy::Directory => Source {
+            kind: CoverKind::File,
+            key: directory(uri),
+            uri,
+            art_kind: mpd::CoverArtKind::File,
+        },
+        CoverStrategy::ParentDirectory => {
+            let parent = parent_cover_art_uri(uri)?;
+
+            Source {
+                kind: CoverKind::File,
+                key: directory(parent),
+                uri: parent,
+                art_kind: mpd::CoverArtKind::File,
+            }
+        },
+    };
+
+    Some(source)
+}
+
+// The strategy that worked for an album before is tried first.
+fn strategies(kind: CoverArtKind, preferred: Option<CoverStrategy>) -> Vec<CoverStrategy> {
+    match kind {
+        CoverArtKind::File => vec![CoverStrategy::Directory],
+        CoverArtKind::Embedded => vec![CoverStrategy::Embedded],
+        CoverArtKind::Auto => {
+            preferred.into_iter()
+                .chain(STRATEGIES.into_iter().filter(|strategy| Some(*strategy) != preferred))
+                .collect()
+        },
+    }
+}
+
+async fn process<T, F>(data: Bytes, f: F) -> Result<T, String>
+    where T: Send + 'static,
+          F: FnOnce(&[u8]) -> Result<T, String> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || f(&data)).await
+        .map_err(|err| format!("failed to process cover: {err}"))?
+}
+
+async fn last_modified(handle: &mpd::Handle, uri: &str) -> mpd::Result<Option<String>> {
+    let items = handle.db()
+        .get(uri.to_owned(), Some(vec![]))
+        .await?;
+
+    let result = items.into_iter()
+        .find_map(|item| match item {
+            DbItem::File { uri: file, updated_at, .. } if file == uri => Some(updated_at),
+            _ => None,
+        });
+
+    Ok(result)
+}
+
+// Embedded covers change along with their song. Cover files don't have to touch any of the songs
+// next to them, so those go by their directory which MPD only reports when listing its parent.
+async fn source_last_modified(handle: &mpd::Handle, uri: &str, source: &Source<'_>) -> mpd::Result<Option<String>> {
+    match source.kind {
+        CoverKind::Embedded => last_modified(handle, uri).await,
+        CoverKind::File if source.key.is_empty() => Ok(None),
+        CoverKind::File => {
+            let items = handle.db()
+                .get(directory(source.key).to_owned(), Some(vec![]))
+                .await?;
+
+            let result = items.into_iter()
+                .find_map(|item| match item {
+                    DbItem::Directory { uri, updated_at } if uri == source.key => updated_at,
+                    _ => None,
+                });
+
+            Ok(result)
+        },
+    }
+}
+
+impl Handle {
+    pub async fn get(&self, handle: &mpd::Handle, uri: String, kind: CoverArtKind) -> Result<Cover, Error> {
+        let preferred = self.preferred(&uri, kind).await?;
+
+        let (cover, _) = self.resolve(handle, &uri, kind, preferred).await?;
+
+        Ok(cover)
+    }
+
+    async fn preferred(&self, uri: &str, kind: CoverArtKind) -> Result<Option<CoverStrategy>, Error> {
+        let result = match kind {
+            CoverArtKind::Auto => self.inner.cover(&self.server).get_strategy(directory(uri)).await?,
+            CoverArtKind::File | CoverArtKind::Embedded => None,
+        };
+
+        Ok(result)
+    }
+
+    async fn resolve<'a>(
+        &self,
+        handle: &mpd::Handle,
+        uri: &'a str,
+        kind: CoverArtKind,
+        preferred: Option<CoverStrategy>,
+    ) -> Result<(Cover, Source<'a>), Error> {
+        let covers = self.inner.cover(&self.server);
+
+        let album = directory(uri);
+
+        for strategy in strategies(kind, preferred) {
+            let Some(source) = source(uri, strategy) else {
+                continue;
+            };
+
+            match self.fetch(handle, uri, &source).await {
+                Err(Error::Mpd(mpd::Error::NotFound(msg))) if matches!(kind, CoverArtKind::Auto) => {
+                    tracing::debug!(uri, ?strategy, "no cover found: {msg}");
+                },
+                Err(err) => {
+                    return Err(err);
+                },
+                Ok(cover) => {
+                    if preferred != Some(strategy) && matches!(kind, CoverArtKind::Auto) {
+                        covers.put_strategy(album, strategy).await?;
+                    }
+
+                    return Ok((cover, source));
+                },
+            }
+        }
+
+        Err(mpd::Error::NotFound(format!("no cover art found for file at uri '{uri}'")).into())
+    }
+
+    // Cached covers are served as is until the index notices their source changed,
+    // after that they're only downloaded again if it has actually been modified.
+    async fn fetch(&self, handle: &mpd::Handle, uri: &str, source: &Source<'_>) -> Result<Cover, Error> {
+        let covers = self.inner.cover(&self.server);
+
+        let cached = match covers.get(source.kind, source.key).await? {
+            Some(cover) if !cover.stale => {
+                covers.refresh(source.kind, source.key).await?;
+
+                return Ok(cover.into());
+            },
+            cached => cached,
+        };
+
+        let Some(last_modified) = source_last_modified(handle, uri, source).await? else {
+            // Nothing to validate against, so nothing to cache either.
+            let CoverArt { data, content_type } = handle.db()
+                .cover_art(source.uri.to_owned(), source.art_kind)
+                .await?;
+
+            return Ok(Cover { data, content_type, last_modified: None });
+        };
+
+        if let Some(cover) = cached.filter(|cover| cover.last_modified == last_modified) {
+            covers.refresh(source.kind, source.key).await?;
+
+            return Ok(cover.into());
+        }
+
+        let CoverArt { data, content_type } = handle.db()
+            .cover_art(source.uri.to_owned(), source.art_kind)
+            .await?;
+
+        let cover = persist::Cover {
+            last_modified,
+            content_type,
+            data: data.to_vec(),
+            stale: false,
+        };
+
+        let result = Cover {
+            data,
+            content_type: cover.content_type.clone(),
+            last_modified: OffsetDateTime::parse(&cover.last_modified, &Rfc3339).ok(),
+        };
+
+        covers.put(source.kind, source.key, cover, self.cache_size).await?;
+
+        self.put_placeholder(source, result.data.clone()).await?;
+
+        Ok(result)
+    }
+
+    // Covers that can't be decoded are still served, only without a placeholder.
+    async fn put_placeholder(&self, source: &Source<'_>, data: Bytes) -> Result<(), Error> {
+        let placeholder = match process(data, imaging::placeholder).await {
+            Ok(placeholder) => placeholder,
+            Err(err) => {
+                tracing::debug!(key = source.key, "no placeholder for cover: {err}");
+
+                return Ok(());
+            },
+        };
+
+        self.inner.cover(&self.server)
+            .put_placeholder(source.kind, source.key, placeholder)
+            .await?;
+
+        Ok(())
+    }
+
+    // Thumbnails are kept for as long as the cover they were made from stays the same.
+    // Covers that can't be decoded are served as they are instead.
+    pub async fn get_thumbnail(
+        &self,
+        handle: &mpd::Handle,
+        uri: String,
+        kind: CoverArtKind,
+        size: u32,
+        format: CoverThumbnailFormat,
+    ) -> Result<Cover, Error> {
+        let covers = self.inner.cover(&self.server);
+
+        let preferred = self.preferred(&uri, kind).await?;
+
+        // The first strategy is the one that's served whenever its cover is cached,
+        // so its thumbnail is good to go without loading the cover itself.
+        let first = strategies(kind, preferred).into_iter()
+            .find_map(|strategy| source(&uri, strategy));
+
+        if let Some(source) = first {
+            if let Some(thumbnail) = self.cached_thumbnail(&source, size, format).await? {
+                covers.refresh(source.kind, source.key).await?;
+
+                return Ok(thumbnail);
+            }
+        }
+
+        let (cover, source) = self.resolve(handle, &uri, kind, preferred).await?;
+
+        if let Some(thumbnail) = self.cached_thumbnail(&source, size, format).await? {
+            return Ok(thumbnail);
+        }
+
+        let data = match process(cover.data.clone(), move |data| imaging::thumbnail(data, size, format)).await {
+            Ok(data) => data,
+            Err(err) => {
+                tracing::debug!(uri, "no thumbnail for cover: {err}");
+
+                return Ok(cover);
+            },
+        };
+
+        if let Some(source_modified_at) = cover.last_modified.map(OffsetDateTime::unix_timestamp) {
+            covers.put_thumbnail(
+                source.kind,
+                source.key,
+                size,
+                format,
+                persist::CoverThumbnail { source_modified_at, data: data.clone() },
+            ).await?;
+        }
+
+        Ok(Cover {
+            data: Bytes::from(data),
+            content_type: imaging::content_type(format).to_owned(),
+            last_modified: cover.last_modified,
+        })
+    }
+
+    async fn cached_thumbnail(
+        &self,
+        source: &Source<'_>,
+        size: u32,
+        format: CoverThumbnailFormat,
+    ) -> Result<Option<Cover>, Error> {
+        let cached = self.inner.cover(&self.server)
+            .get_thumbnail(source.kind, source.key, size, format)
+            .await?;
+
+        let Some((last_modified, thumbnail)) = cached else {
+            return Ok(None);
+        };
+
+        let last_modified = OffsetDateTime::parse(&last_modified, &Rfc3339).ok();
+
+        if last_modified.map(OffsetDateTime::unix_timestamp) != Some(thumbnail.source_modified_at) {
+            return Ok(None);
+        }
+
+        Ok(Some(Cover {
+            data: Bytes::from(thumbnail.data),
+            content_type: imaging::content_type(format).to_owned(),
+            last_modified,
+        }))
+    }
+
+    // Listings show the cover of an album for all of its songs, so the placeholder of one of
+    // them is made ahead of time. Songs without a cover are skipped.
+    pub async fn precompute_placeholder(&self, handle: &mpd::Handle, uri: &str) -> Result<(), Error> {
+        let preferred = self.preferred(uri, CoverArtKind::Auto).await?;
+
+        let (cover, source) = match self.resolve(handle, uri, CoverArtKind::Auto, preferred).await {
+            Ok(result) => result,
+            Err(Error::Mpd(mpd::Error::NotFound(_))) => return Ok(()),
+            Err(err) => return Err(err),
+        };
+
+        let placeholders = self.inner.cover(&self.server)
+            .get_all_placeholders(&[(source.kind, source.key)])
+            .await?;
+
+        if placeholders.is_empty() {
+            self.put_placeholder(&source, cover.data).await?;
+        }
+
+        Ok(())
+    }
+
+    // Placeholders are made whenever a cover is cached or an album is indexed.
+    // Folder covers are preferred for files since those are shared across an album.
+    pub async fn placeholders(&self, items: &[DbItem]) -> Result<HashMap<String, CoverPlaceholder>, Error> {
+        let keys = |item: &DbItem| -> Vec<(CoverKind, String)> {
+            match item {
+                DbItem::File { uri, .. } => {
+                    PLACEHOLDER_STRATEGIES.into_iter()
+                        .filter_map(|strategy| source(uri, strategy))
+                        .map(|source| (source.kind, source.key.to_owned()))
+                        .collect()
+                },
+                DbItem::Directory { uri, .. } => vec![(CoverKind::File, uri.clone())],
+                DbItem::Playlist { .. } => vec![],
+            }
+        };
+
+        let all_keys = items.iter()
+            .flat_map(keys)
+            .collect::<Vec<_>>();
+
+        let placeholders = self.inner.cover(&self.server)
+            .get_all_placeholders(&all_keys.iter().map(|(kind, key)| (*kind, key.as_str())).collect::<Vec<_>>())
+            .await?;
+
+        let result = items.iter()
+            .filter_map(|item| {
+                let placeholder = keys(item).into_iter()
+                    .find_map(|key| placeholders.get(&key))?;
+
+                Some((item.uri().to_owned(), placeholder.clone()))
+            })
+            .collect();
+
+        Ok(result)
+    }
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_share_cover_files_within_directory() {
+        let key = |uri, strategy| source(uri, strategy).map(|source| (source.kind, source.key));
+
+        assert_eq!(key("alfa/beta/one.flac", CoverStrategy::Directory), Some((CoverKind::File, "alfa/beta")));
+        assert_eq!(key("one.flac", CoverStrategy::Directory), Some((CoverKind::File, "")));
+        assert_eq!(key("alfa/beta/one.flac", CoverStrategy::Embedded), Some((CoverKind::Embedded, "alfa/beta/one.flac")));
+    }
+
+    #[test]
+    fn should_look_for_cover_files_one_level_up() {
+        assert_eq!(parent_cover_art_uri("alfa/cd1/one.flac"), Some("alfa/cd1"));
+        assert_eq!(parent_cover_art_uri("alfa/one.flac"), None);
+        assert_eq!(parent_cover_art_uri("one.flac"), None);
+    }
+
+    #[test]
+    fn should_look_up_cover_files_of_parent_directory() {
+        assert_eq!(
+            source("alfa/cd1/one.flac", CoverStrategy::ParentDirectory),
+            Some(Source { kind: CoverKind::File, key: "alfa", uri: "alfa/cd1", art_kind: mpd::CoverArtKind::File }),
+        );
+        assert_eq!(source("alfa/one.flac", CoverStrategy::ParentDirectory), None);
+    }
+
+    #[test]
+    fn should_try_preferred_strategy_first() {
+        assert_eq!(
+            strategies(CoverArtKind::Auto, Some(CoverStrategy::Directory)),
+            vec![CoverStrategy::Directory, CoverStrategy::Embedded, CoverStrategy::ParentDirectory],
+        );
+        assert_eq!(strategies(CoverArtKind::Auto, None), STRATEGIES.to_vec());
+        assert_eq!(strategies(CoverArtKind::File, Some(CoverStrategy::Embedded)), vec![CoverStrategy::Directory]);
+    }
+
+    fn cached(size: usize) -> persist::Cover {
+        persist::Cover {
+            last_modified: "2023-01-01T00:00:00Z".to_owned(),
+            content_type: "image/png".to_owned(),
+            data: vec![0; size],
+            stale: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn should_evict_least_recently_served_covers() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+
+        let covers = persistence_handle.cover("default");
+
+        covers.put(CoverKind::File, "alfa", cached(10), 25).await.unwrap();
+        covers.put(CoverKind::File, "beta", cached(10), 25).await.unwrap();
+        covers.refresh(CoverKind::File, "alfa").await.unwrap();
+        covers.put(CoverKind::File, "gamma", cached(10), 25).await.unwrap();
+
+        assert!(covers.get(CoverKind::File, "alfa").await.unwrap().is_some());
+        assert!(covers.get(CoverKind::File, "beta").await.unwrap().is_none());
+        assert!(covers.get(CoverKind::File, "gamma").await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn should_follow_library_changes() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+
+        let covers = persistence_handle.cover("default");
+
+        for (kind, key) in [(CoverKind::File, "alfa"), (CoverKind::File, "alfa/cd1"), (CoverKind::File, "beta")] {
+            covers.put(kind, key, cached(10), u64::MAX).await.unwrap();
+        }
+
+        let beta = persist::LibraryItem {
+            kind: persist::LibraryItemKind::Directory,
+            uri: "beta".to_owned(),
+            tags: mpd::DbTags::default(),
+            format: None,
+            duration: time::Duration::ZERO,
+            updated_at: Some("2023-01-02T00:00:00Z".to_owned()),
+        };
+
+        persistence_handle.library_item("default")
+            .update_all(vec![beta], vec!["alfa".to_owned()])
+            .await
+            .unwrap();
+
+        assert!(covers.get(CoverKind::File, "alfa").await.unwrap().is_none());
+        assert!(covers.get(CoverKind::File, "alfa/cd1").await.unwrap().is_none());
+        assert!(covers.get(CoverKind::File, "beta").await.unwrap().unwrap().stale);
+    }
+
+    #[tokio::test]
+    async fn should_find_thumbnail_without_loading_cover() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+
+        let handle = Handle::new(persistence_handle.clone(), "default".to_owned(), u64::MAX);
+
+        let covers = persistence_handle.cover("default");
+
+        let source = source("alfa/one.flac", CoverStrategy::Directory).unwrap();
+
+        covers.put(source.kind, source.key, cached(10), u64::MAX).await.unwrap();
+
+        let thumbnail = persist::CoverThumbnail {
+            source_modified_at: OffsetDateTime::parse("2023-01-01T00:00:00Z", &Rfc3339).unwrap().unix_timestamp(),
+            data: vec![1; 5],
+        };
+
+        covers.put_thumbnail(source.kind, source.key, 64, CoverThumbnailFormat::Webp, thumbnail).await.unwrap();
+
+        let result = handle.cached_thumbnail(&source, 64, CoverThumbnailFormat::Webp).await.unwrap().unwrap();
+
+        assert_eq!(result.data.len(), 5);
+        assert_eq!(result.content_type, "image/webp");
+        assert!(handle.cached_thumbnail(&source, 128, CoverThumbnailFormat::Webp).await.unwrap().is_none());
+
+        let alfa = persist::LibraryItem {
+            kind: persist::LibraryItemKind::Directory,
+            uri: "alfa".to_owned(),
+            tags: mpd::DbTags::default(),
+            format: None,
+            duration: time::Duration::ZERO,
+            updated_at: Some("2023-01-02T00:00:00Z".to_owned()),
+        };
+
+        persistence_handle.library_item("default").update_all(vec![alfa], vec![]).await.unwrap();
+
+        assert!(handle.cached_thumbnail(&source, 64, CoverThumbnailFormat::Webp).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn should_keep_placeholders_of_evicted_covers() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+
+        let covers = persistence_handle.cover("default");
+
+        let placeholder = CoverPlaceholder { blurhash: "LEHV6nWB2yk8".to_owned(), dominant_color: "#fa0a0a".to_owned() };
+
+        covers.put(CoverKind::File, "alfa", cached(10), 15).await.unwrap();
+        covers.put_placeholder(CoverKind::File, "alfa", placeholder).await.unwrap();
+        covers.put(CoverKind::File, "beta", cached(10), 15).await.unwrap();
+
+        assert!(covers.get(CoverKind::File, "alfa").await.unwrap().is_none());
+        assert!(covers.get_all_placeholders(&[(CoverKind::File, "alfa")]).await.unwrap().contains_key(&(CoverKind::File, "alfa".to_owned())));
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use image::imageops::FilterType;
+use image::RgbaImage;
+
+use crate::persist::CoverPlaceholder;
+use crate::persist::CoverThumbnailFormat;
+
+const JPEG_QUALITY: u8 = 85;
+
+const WEBP_QUALITY: f32 = 80.0;
+
+const PLACEHOLDER_SIZE: u32 = 32;
+
+const BLURHASH_COMPONENTS_X: u32 = 4;
+const BLURHASH_COMPONENTS_Y: u32 = 3;
+
+pub fn content_type(format: CoverThumbnailFormat) -> &'static str {
+    match format {
+        CoverThumbnailFormat::Jpeg => "image/jpeg",
+        CoverThumbnailFormat::Webp => "image/webp",
+    }
+}
+
+fn decode(data: &[u8]) -> Result<DynamicImage, String> {
+    image::load_from_memory(data)
+        .map_err(|err| format!("failed to decode cover: {err}"))
+}
+
+// Fits the cover into a square of the given size, smaller covers are left as they are.
+pub fn thumbnail(data: &[u8], size: u32, format: CoverThumbnailFormat) -> Result<Vec<u8>, String> {
+    let image = decode(data)?;
+
+    let image = if image.width() > size || image.height() > size {
+        image.resize(size, size, FilterType::Triangle)
+    } else {
+        image
+    };
+
+    match format {
+        CoverThumbnailFormat::Jpeg => {
+            let mut result = Vec::new();
+
+            image.to_rgb8()
+                .write_with_encoder(JpegEncoder::new_with_quality(&mut result, JPEG_QUALITY))
+                .map_err(|err| format!("failed to encode thumbnail: {err}"))?;
+
+            Ok(result)
+        },
+        // The encoder that comes with image only does lossless, which makes thumbnails bigger than the JPEG ones.
+        CoverThumbnailFormat::Webp => {
+            let image = image.to_rgba8();
+
+            let result = webp::Encoder::from_rgba(image.as_raw(), image.width(), image.height())
+                .encode_simple(false, WEBP_QUALITY)
+                .map_err(|err| format!("failed to encode thumbnail: {err:?}"))?;
+
+            Ok(result.to_vec())
+        },
+    }
+}
+
+// Pixels are bucketed by the high bits of each channel, the color
+// is the average of the fullest bucket rather than of the whole image.
+fn dominant_color(image: &RgbaImage) -> String {
+    let mut buckets: BTreeMap<[u8; 3], (u32, [u32; 3])> = BTreeMap::new();
+
+    for pixel in image.pixels() {
+        let [r, g, b, a] = pixel.0;
+
+        // Transparent pixels don't show.
+        if a < u8::MAX / 2 {
+            continue;
+        }
+
+        let (count, sums) = buckets.entry([r >> 4, g >> 4, b >> 4]).or_default();
+
+        *count += 1;
+
+        for (sum, value) in sums.iter_mut().zip([r, g, b]) {
+            *sum += u32::from(value);
+        }
+    }
+
+    let Some((count, [r, g, b])) = buckets.into_values().max_by_key(|(count, _)| *count) else {
+        return "#000000".to_owned();
+    };
+
+    format!("#{:02x}{:02x}{:02x}", r / count, g / count, b / count)
+}
+
+pub fn placeholder(data: &[u8]) -> Result<CoverPlaceholder, String> {
+    let image = decode(data)?
+        .thumbnail(PLACEHOLDER_SIZE, PLACEHOLDER_SIZE)
+        .to_rgba8();
+
+    let blurhash = blurhash::encode(
+        BLURHASH_COMPONENTS_X,
+        BLURHASH_COMPONENTS_Y,
+        image.width(),
+        image.height(),
+        image.as_raw(),
+    ).map_err(|err| format!("failed to compute blurhash: {err}"))?;
+
+    Ok(CoverPlaceholder { blurhash, dominant_color: dominant_color(&image) })
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use image::ImageFormat;
+    use image::Rgba;
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbaImage::from_fn(width, height, |x, _| {
+            if x < width / 4 { Rgba([0, 0, 255, 255]) } else { Rgba([250, 10, 10, 255]) }
+        });
+
+        let mut result = Cursor::new(Vec::new());
+
+        image.write_to(&mut result, ImageFormat::Png).unwrap();
+
+        result.into_inner()
+    }
+
+    #[test]
+    fn should_fit_thumbnail_into_size() {
+        let thumbnail = image::load_from_memory(&thumbnail(&png(300, 150), 64, CoverThumbnailFormat::Jpeg).unwrap()).unwrap();
+
+        assert_eq!((thumbnail.width(), thumbnail.height()), (64, 32));
+    }
+
+    #[test]
+    fn should_not_upscale_thumbnail() {
+        let thumbnail = image::load_from_memory(&thumbnail(&png(20, 10), 64, CoverThumbnailFormat::Webp).unwrap()).unwrap();
+
+        assert_eq!((thumbnail.width(), thumbnail.height()), (20, 10));
+    }
+
+    #[test]
+    fn should_compute_placeholder() {
+        let placeholder = placeholder(&png(64, 64)).unwrap();
+
+        assert_eq!(placeholder.dominant_color, "#fa0a0a");
+        assert!(!placeholder.blurhash.is_empty());
+    }
+
+    #[test]
+    fn should_encode_lossy_webp() {
+        let thumbnail = thumbnail(&png(300, 300), 64, CoverThumbnailFormat::Webp).unwrap();
+
+        assert_eq!(&thumbnail[12..16], b"VP8 ");
+    }
+}
@@ -1,5 +1,13 @@
 pub use handle::Handle;
 pub use handle::HistoryEntry;
+pub use handle::HistoryStats;
+pub use crate::persist::PlaybackHistoryAlbum;
+pub use crate::persist::PlaybackHistoryArtist;
+pub use crate::persist::PlaybackHistoryTally;
+pub use crate::persist::PlaybackHistoryTotals;
+pub use crate::persist::PlaybackHistoryTrack;
 
 mod handle;
+pub mod session;
+mod stats;
 pub mod keeper;
@@ -2,23 +2,32 @@ use std::collections::HashMap;
 
 use time::Duration;
 use time::OffsetDateTime;
+use time::UtcOffset;
+use time::Weekday;
 
 use crate::convert::IntoOption;
 use crate::convert::IntoResult;
+use crate::history::session;
+use crate::history::session::Session;
+use crate::history::stats::Aggregator;
 use crate::mpd::DbTags;
 use crate::persist;
 use crate::persist::PlaybackHistoryEvent;
 use crate::persist::PlaybackHistoryMetadata;
 use crate::persist::PlaybackHistoryPlayId;
+use crate::persist::PlaybackHistoryStats;
+use crate::persist::PlaybackHistoryTally;
+use crate::persist::PlaybackHistoryTotals;
 
 #[derive(Clone)]
 pub struct Handle {
     inner: persist::Handle,
+    server: String,
 }
 
 impl Handle {
-    pub fn new(persistence_handle: persist::Handle) -> Self {
-        Handle { inner: persistence_handle }
+    pub fn new(persistence_handle: persist::Handle, server: String) -> Self {
+        Handle { inner: persistence_handle, server }
     }
 }
 
@@ -26,35 +35,92 @@ pub struct HistoryEntry {
     pub id: i64,
     pub uri: String,
     pub tags: DbTags,
-    pub _duration: Duration,
+    pub duration: Duration,
+    pub session: Session,
     pub recorded_at: OffsetDateTime,
 }
 
-impl From<(PlaybackHistoryEvent, PlaybackHistoryMetadata)> for HistoryEntry {
-    fn from((event, metadata): (PlaybackHistoryEvent, PlaybackHistoryMetadata)) -> Self {
+impl From<(PlaybackHistoryEvent, PlaybackHistoryMetadata, Session)> for HistoryEntry {
+    fn from((event, metadata, session): (PlaybackHistoryEvent, PlaybackHistoryMetadata, Session)) -> Self {
         HistoryEntry {
             id: event.play_id,
             uri: metadata.uri,
             tags: metadata.tags,
-            _duration: metadata.duration,
+            duration: metadata.duration,
+            session,
             recorded_at: event.recorded_at,
         }
     }
 }
 
+pub struct HistoryStats {
+    pub from: OffsetDateTime,
+    pub to: OffsetDateTime,
+    pub current: PlaybackHistoryStats,
+    pub previous: PlaybackHistoryTotals,
+}
+
+const HOURS: std::ops::Range<u8> = 0..24;
+
+const WEEKDAYS: [Weekday; 7] = [
+    Weekday::Monday,
+    Weekday::Tuesday,
+    Weekday::Wednesday,
+    Weekday::Thursday,
+    Weekday::Friday,
+    Weekday::Saturday,
+    Weekday::Sunday,
+];
+
+// Buckets without plays are filled in so that clients always get all of them.
+fn fill<T, I>(keys: I, buckets: Vec<(T, PlaybackHistoryTally)>) -> Vec<(T, PlaybackHistoryTally)>
+    where T: PartialEq,
+          I: IntoIterator<Item = T>,
+{
+    keys.into_iter()
+        .map(|key| {
+            let tally = buckets.iter()
+                .find(|(bucket, _)| *bucket == key)
+                .map(|(_, tally)| *tally)
+                .unwrap_or_default();
+
+            (key, tally)
+        })
+        .collect()
+}
+
 impl Handle {
+    // Plays may have started before or ended after the requested period,
+    // all of their events are needed to tell how long they lasted.
+    async fn sessions(
+        &self,
+        play_ids: &[PlaybackHistoryPlayId],
+    ) -> Result<HashMap<PlaybackHistoryPlayId, Vec<PlaybackHistoryEvent>>, String> {
+        let mut sessions: HashMap<PlaybackHistoryPlayId, Vec<PlaybackHistoryEvent>> = HashMap::new();
+
+        for event in self.inner.playback_history_event(&self.server).get_all_by_play_id(play_ids).await? {
+            sessions.entry(event.play_id)
+                .or_default()
+                .push(event);
+        }
+
+        Ok(sessions)
+    }
+
     pub async fn get(
         &self,
         from: Option<OffsetDateTime>,
         to: Option<OffsetDateTime>
     ) -> Result<Vec<HistoryEntry>, String> {
-        let mut events = self.inner.playback_history_event()
+        let mut events = self.inner.playback_history_event(&self.server)
             .get_all(from, to).await?;
 
         events.dedup_by_key(|event| event.play_id);
 
-        let metadata = self.inner.playback_history_metadata()
-            .get_all_by_play_id(&events.iter().map(|x| x.play_id).collect::<Vec<_>>())
+        let play_ids = events.iter().map(|x| x.play_id).collect::<Vec<_>>();
+
+        let metadata = self.inner.playback_history_metadata(&self.server)
+            .get_all_by_play_id(&play_ids)
             .await?;
 
         let mut map: HashMap<PlaybackHistoryPlayId, PlaybackHistoryMetadata> = HashMap::new();
@@ -63,13 +129,146 @@ impl Handle {
             map.insert(entry.play_id, entry);
         }
 
+        let sessions = self.sessions(&play_ids).await?;
+
+        let now = OffsetDateTime::now_utc();
+
         events.into_iter()
             .filter_map(|x| {
                 let play_id = x.play_id;
 
-                HistoryEntry::from((x, map.remove(&play_id)?)).into_some()
+                let metadata = map.remove(&play_id)?;
+
+                let session = session::reduce(
+                    sessions.get(&play_id).into_iter().flatten(),
+                    metadata.duration,
+                    now,
+                );
+
+                HistoryEntry::from((x, metadata, session)).into_some()
             })
             .collect::<Vec<_>>()
             .into_ok()
     }
+
+    // Stats are compared against the period of the same length right before.
+    pub async fn stats(
+        &self,
+        from: OffsetDateTime,
+        to: OffsetDateTime,
+        limit: usize,
+        utc_offset: UtcOffset,
+    ) -> Result<HistoryStats, String> {
+        let events = self.inner.playback_history_event(&self.server);
+
+        let previous_from = from - (to - from);
+
+        let now = OffsetDateTime::now_utc();
+
+        let mut current = Aggregator::new(utc_offset, now);
+        let mut previous = Aggregator::new(utc_offset, now);
+
+        events.for_each_play(from, to, |play| current.add(play)).await?;
+        events.for_each_play(previous_from, from, |play| previous.add(play)).await?;
+
+        let previous = previous.totals();
+
+        let mut current = current.finish(limit);
+
+        current.hours = fill(HOURS, current.hours);
+        current.weekdays = fill(WEEKDAYS, current.weekdays);
+
+        Ok(HistoryStats { from, to, current, previous })
+    }
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::persist::CreatePlaybackHistoryEvent;
+    use crate::persist::CreatePlaybackHistoryMetadata;
+    use crate::persist::PlaybackHistoryEventKind;
+
+    use super::*;
+
+    const SERVER: &str = "default";
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::UNIX_EPOCH + Duration::days(365 * 50) + Duration::seconds(seconds)
+    }
+
+    async fn play(persistence_handle: &persist::Handle, play_id: PlaybackHistoryPlayId, events: &[(PlaybackHistoryEventKind, i64, i64)]) {
+        persistence_handle.playback_history_metadata(SERVER)
+            .create(CreatePlaybackHistoryMetadata {
+                play_id,
+                playlist_id: play_id,
+                uri: format!("{play_id}.flac"),
+                duration: Duration::seconds(300),
+                tags: DbTags { artists: vec!["Artist".to_owned()], ..Default::default() },
+            })
+            .await
+            .unwrap();
+
+        let events = events.iter()
+            .map(|(kind, elapsed, recorded_at)| CreatePlaybackHistoryEvent {
+                play_id,
+                elapsed: Duration::seconds(*elapsed),
+                kind: *kind,
+                recorded_at: at(*recorded_at),
+            })
+            .collect();
+
+        persistence_handle.playback_history_event(SERVER)
+            .create_all(events)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn should_agree_with_history_on_time_listened() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+
+        // Seeking while paused and playing past the end don't count.
+        play(&persistence_handle, 1, &[
+            (PlaybackHistoryEventKind::Start, 0, 0),
+            (PlaybackHistoryEventKind::Pause, 60, 60),
+            (PlaybackHistoryEventKind::Seek, 60, 120),
+            (PlaybackHistoryEventKind::Resume, 360, 360),
+            (PlaybackHistoryEventKind::Stop, 390, 390),
+        ]).await;
+
+        play(&persistence_handle, 2, &[
+            (PlaybackHistoryEventKind::Start, 0, 400),
+            (PlaybackHistoryEventKind::Seek, 250, 410),
+            (PlaybackHistoryEventKind::Stop, 300, 500),
+        ]).await;
+
+        let handle = Handle::new(persistence_handle, SERVER.to_owned());
+
+        let listened = handle.get(None, None).await.unwrap()
+            .into_iter()
+            .map(|entry| entry.session.listened)
+            .sum::<Duration>();
+
+        let stats = handle.stats(at(0), at(1000), 10, UtcOffset::UTC).await.unwrap();
+
+        assert_eq!(listened, Duration::seconds(150));
+        assert_eq!(stats.current.totals.tally.plays, 2);
+        assert_eq!(stats.current.totals.tally.listened, listened);
+        assert_eq!(stats.current.artists[0].tally.listened, listened);
+    }
+
+    #[test]
+    fn should_fill_buckets_without_plays() {
+        let tally = PlaybackHistoryTally { plays: 2, listened: Duration::minutes(7) };
+
+        let buckets = fill(WEEKDAYS, vec![(Weekday::Sunday, tally)]);
+
+        assert_eq!(buckets.len(), 7);
+        assert_eq!(buckets[0].0, Weekday::Monday);
+        assert_eq!(buckets[0].1.plays, 0);
+        assert_eq!(buckets[6].0, Weekday::Sunday);
+        assert_eq!(buckets[6].1.plays, 2);
+    }
 }
@@ -4,10 +4,12 @@ use std::ops::Sub;
 
 use time::Duration;
 use time::OffsetDateTime;
+use tokio::sync::broadcast;
 
 use crate::convert::IntoOption;
 use crate::convert::IntoResult;
 use crate::convert::IntoVec;
+use crate::history::keeper::error::Error;
 use crate::history::keeper::result::Result;
 use crate::mpd;
 use crate::mpd::PlaybackState;
@@ -68,7 +70,18 @@ fn diff<'a>(old: &'a Status, new: &'a Status) -> Option<StatusDiff<'a>> {
 
 const INITIAL_PLAY_ID: i64 = 1;
 
+const RESTART_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+const CHECKPOINT_INTERVAL: Duration = Duration::seconds(30);
+
 impl PlaybackHistoryEvent {
+    fn is_playing(&self) -> bool {
+        matches!(
+            self.kind,
+            PlaybackHistoryEventKind::Start | PlaybackHistoryEventKind::Resume | PlaybackHistoryEventKind::Seek
+        )
+    }
+
     fn elapsed_now(&self, duration: Duration) -> Duration {
         cmp::min(
             duration,
@@ -83,7 +96,6 @@ fn filter(updates: Vec<Update>) -> (Option<Status>, Option<Vec<QueueItem>>) {
     let mut status = None;
     let mut queue = None;
 
-    // TODO: Handle disconnect.
     for update in updates {
         match update {
             Update::Status(new_status) => {
@@ -92,7 +104,7 @@ fn filter(updates: Vec<Update>) -> (Option<Status>, Option<Vec<QueueItem>>) {
             Update::Queue(new_queue) => {
                 queue = Some(new_queue);
             },
-            Update::Db | Update::Playlists => {
+            Update::Db | Update::Playlists | Update::Outputs(_) | Update::Stickers => {
                 // We don't need these.
             },
         }
@@ -108,8 +120,8 @@ struct State {
 }
 
 impl State {
-    async fn last(persistence_handle: &persist::Handle) -> Result<Option<Self>> {
-        let event = persistence_handle.playback_history_event()
+    async fn last(persistence_handle: &persist::Handle, server: &str) -> Result<Option<Self>> {
+        let event = persistence_handle.playback_history_event(server)
             .get_latest()
             .await?;
 
@@ -119,7 +131,7 @@ impl State {
 
         let play_id = event.play_id;
 
-        let metadata = persistence_handle.playback_history_metadata()
+        let metadata = persistence_handle.playback_history_metadata(server)
             .get_by_play_id(play_id)
             .await?;
 
@@ -158,8 +170,15 @@ impl CreatePlaybackHistoryMetadata {
     }
 }
 
+// Nobody listening is fine, e.g. when there's nowhere to scrobble to.
+fn notify(events_tx: &broadcast::Sender<PlaybackHistoryEvent>, event: &PlaybackHistoryEvent) {
+    let _ = events_tx.send(event.clone());
+}
+
 async fn process_initial(
     persistence_handle: &persist::Handle,
+    events_tx: &broadcast::Sender<PlaybackHistoryEvent>,
+    server: &str,
     state: Option<State>,
     status: &Status,
     queue: &[QueueItem],
@@ -173,8 +192,13 @@ async fn process_initial(
         return Ok(state);
     };
 
-    async fn is_matching_play(p: &persist::Handle, play_id: PlaybackHistoryPlayId, song: &QueueItem) -> Result<bool> {
-        let metadata = p.playback_history_metadata().get_by_play_id(play_id).await?;
+    async fn is_matching_play(
+        p: &persist::Handle,
+        server: &str,
+        play_id: PlaybackHistoryPlayId,
+        song: &QueueItem,
+    ) -> Result<bool> {
+        let metadata = p.playback_history_metadata(server).get_by_play_id(play_id).await?;
 
         Ok(metadata.playlist_id == song.id && metadata.uri == song.uri)
     }
@@ -183,11 +207,11 @@ async fn process_initial(
         None => {
             let play_id = INITIAL_PLAY_ID;
 
-            let metadata = persistence_handle.playback_history_metadata().create(
+            let metadata = persistence_handle.playback_history_metadata(server).create(
                 CreatePlaybackHistoryMetadata::new(play_id, song)
             ).await?;
 
-            let event = persistence_handle.playback_history_event().create(
+            let event = persistence_handle.playback_history_event(server).create(
                 CreatePlaybackHistoryEvent::new(
                     play_id,
                     song_status.elapsed,
@@ -195,9 +219,27 @@ async fn process_initial(
                 )
             ).await?;
 
+            notify(events_tx, &event);
+
             Some(State { event, metadata }).into_ok()
         },
-        Some(state) if is_matching_play(persistence_handle, state.event.play_id, song).await? => {
+        Some(state) if is_matching_play(persistence_handle, server, state.event.play_id, song).await? => {
+            // The same song is still there after reconnecting, so the play goes on from wherever MPD is now.
+            if state.event.kind == PlaybackHistoryEventKind::Interrupt {
+                let kind = match status.state {
+                    PlaybackState::Paused => PlaybackHistoryEventKind::Pause,
+                    PlaybackState::Playing | PlaybackState::Stopped => PlaybackHistoryEventKind::Seek,
+                };
+
+                let event = persistence_handle.playback_history_event(server).create(
+                    CreatePlaybackHistoryEvent::new(state.event.play_id, song_status.elapsed, kind)
+                ).await?;
+
+                notify(events_tx, &event);
+
+                return Some(State { event, metadata: state.metadata }).into_ok();
+            }
+
             let is_playback_uninterrupted = (OffsetDateTime::now_utc() - state.event.recorded_at)
                 - (song_status.elapsed - state.event.elapsed)
                 > Duration::seconds(1);
@@ -206,7 +248,7 @@ async fn process_initial(
                 return Some(state).into_ok();
             }
 
-            persistence_handle.playback_history_event().create(
+            let event = persistence_handle.playback_history_event(server).create(
                 CreatePlaybackHistoryEvent::new(
                     state.event.play_id,
                     state.event.elapsed,
@@ -214,13 +256,15 @@ async fn process_initial(
                 )
             ).await?;
 
+            notify(events_tx, &event);
+
             let new_play_id = state.event.play_id + 1;
 
-            let metadata = persistence_handle.playback_history_metadata().create(
+            let metadata = persistence_handle.playback_history_metadata(server).create(
                 CreatePlaybackHistoryMetadata::new(new_play_id, song)
             ).await?;
 
-            let event = persistence_handle.playback_history_event().create(
+            let event = persistence_handle.playback_history_event(server).create(
                 CreatePlaybackHistoryEvent::new(
                     new_play_id,
                     song_status.elapsed,
@@ -228,16 +272,18 @@ async fn process_initial(
                 )
             ).await?;
 
+            notify(events_tx, &event);
+
             Some(State { event, metadata }).into_ok()
         },
         Some(state) => {
             let new_play_id = state.event.play_id + 1;
 
-            let metadata = persistence_handle.playback_history_metadata().create(
+            let metadata = persistence_handle.playback_history_metadata(server).create(
                 CreatePlaybackHistoryMetadata::new(new_play_id, song)
             ).await?;
 
-            let event = persistence_handle.playback_history_event().create(
+            let event = persistence_handle.playback_history_event(server).create(
                 CreatePlaybackHistoryEvent::new(
                     new_play_id,
                     song_status.elapsed,
@@ -245,6 +291,8 @@ async fn process_initial(
                 )
             ).await?;
 
+            notify(events_tx, &event);
+
             Some(State { event, metadata }).into_ok()
         }
     }
@@ -335,21 +383,46 @@ fn process_diff(
     }
 }
 
+// Keeps track of how far the play got, in case the process goes away without a chance to interrupt it.
+async fn checkpoint(persistence_handle: &persist::Handle, server: &str, state: Option<&State>) -> Result<()> {
+    let Some(state) = state.filter(|it| it.event.is_playing()) else {
+        return Ok(());
+    };
+
+    persistence_handle.playback_history_event(server)
+        .put_checkpoint(state.event.play_id, state.event.elapsed_now(state.metadata.duration))
+        .await?;
+
+    Ok(())
+}
+
 async fn inner(
     handle: &mpd::Handle,
     sub_handle: &mut mpd::SubscriptionHandle,
-    persistence_handle: &persist::Handle
+    persistence_handle: &persist::Handle,
+    events_tx: &broadcast::Sender<PlaybackHistoryEvent>,
+    server: &str,
 ) -> Result<()> {
     let mut queue = handle.queue().get().await?;
     let mut status = handle.status().get().await?;
 
-    let state = State::last(persistence_handle).await?;
+    let state = State::last(persistence_handle, server).await?;
+
+    let mut state = process_initial(persistence_handle, events_tx, server, state, &status, &queue).await?;
 
-    let mut state = process_initial(persistence_handle, state, &status, &queue).await?;
+    let mut checkpoints = tokio::time::interval(CHECKPOINT_INTERVAL.unsigned_abs());
 
-    // TODO: Handle interrupt / ongoing playback w/o interrupt (power cord yanked) type stuff.
     loop {
-        let (Some(new_status), new_queue) = filter(sub_handle.updates().await?) else {
+        let updates = tokio::select! {
+            updates = sub_handle.updates() => updates?,
+            _ = checkpoints.tick() => {
+                checkpoint(persistence_handle, server, state.as_ref()).await?;
+
+                continue;
+            },
+        };
+
+        let (Some(new_status), new_queue) = filter(updates) else {
             continue;
         };
 
@@ -372,15 +445,19 @@ async fn inner(
         status = new_status;
 
         let metadata = if let Some(metadata) = metadata {
-            persistence_handle.playback_history_metadata().create(metadata).await?.into_some()
+            persistence_handle.playback_history_metadata(server).create(metadata).await?.into_some()
         } else {
             None
         };
 
-        let mut events = persistence_handle.playback_history_event()
+        let mut events = persistence_handle.playback_history_event(server)
             .create_all(events)
             .await?;
 
+        for event in &events {
+            notify(events_tx, event);
+        }
+
         state = State {
             event: events.swap_remove(events.len() - 1),
             metadata: metadata.unwrap_or(state.unwrap().metadata),
@@ -388,16 +465,176 @@ async fn inner(
     }
 }
 
-pub fn run(handle: mpd::Handle, mut sub_handle: mpd::SubscriptionHandle, persistence_handle: persist::Handle) {
-    tokio::spawn(async move {
-        match inner(&handle, &mut sub_handle, &persistence_handle).await {
+// The play couldn't have gone on past the end of the song, nor for much longer than
+// the last checkpoint, since there would've been another one otherwise.
+fn estimate_interrupt(
+    elapsed: Duration,
+    recorded_at: OffsetDateTime,
+    duration: Duration,
+    now: OffsetDateTime,
+) -> (Duration, OffsetDateTime) {
+    let mut played = (now - recorded_at).clamp(Duration::ZERO, CHECKPOINT_INTERVAL);
+
+    if duration.is_positive() {
+        played = played.min((duration - elapsed).max(Duration::ZERO));
+    }
+
+    (elapsed + played, recorded_at + played)
+}
+
+// Plays that were still going on when the process went away, e.g. because of a crash
+// or a power loss, are interrupted at about the time that happened.
+async fn recover(
+    persistence_handle: &persist::Handle,
+    events_tx: &broadcast::Sender<PlaybackHistoryEvent>,
+    server: &str,
+) -> Result<()> {
+    let Some(state) = State::last(persistence_handle, server).await? else {
+        return Ok(());
+    };
+
+    if !state.event.is_playing() {
+        return Ok(());
+    }
+
+    let checkpoint = persistence_handle.playback_history_event(server)
+        .get_checkpoint()
+        .await?
+        .filter(|it| it.play_id == state.event.play_id && it.checkpointed_at > state.event.recorded_at);
+
+    let (elapsed, recorded_at) = match checkpoint {
+        Some(checkpoint) => (checkpoint.elapsed, checkpoint.checkpointed_at),
+        None => (state.event.elapsed, state.event.recorded_at),
+    };
+
+    let (elapsed, recorded_at) = estimate_interrupt(elapsed, recorded_at, state.metadata.duration, OffsetDateTime::now_utc());
+
+    tracing::info!(server, play_id = state.event.play_id, "interrupting dangling play");
+
+    let event = persistence_handle.playback_history_event(server).create(
+        CreatePlaybackHistoryEvent {
+            play_id: state.event.play_id,
+            elapsed,
+            kind: PlaybackHistoryEventKind::Interrupt,
+            recorded_at,
+        }
+    ).await?;
+
+    notify(events_tx, &event);
+
+    Ok(())
+}
+
+// Whatever was playing can't be followed anymore, so the play ends where it's estimated to be by now.
+async fn interrupt(
+    persistence_handle: &persist::Handle,
+    events_tx: &broadcast::Sender<PlaybackHistoryEvent>,
+    server: &str,
+) -> Result<()> {
+    let Some(state) = State::last(persistence_handle, server).await? else {
+        return Ok(());
+    };
+
+    let elapsed = match state.event.kind {
+        PlaybackHistoryEventKind::Start | PlaybackHistoryEventKind::Resume | PlaybackHistoryEventKind::Seek => {
+            state.event.elapsed_now(state.metadata.duration)
+        },
+        PlaybackHistoryEventKind::Pause => {
+            state.event.elapsed
+        },
+        PlaybackHistoryEventKind::Stop | PlaybackHistoryEventKind::Interrupt => {
+            return Ok(());
+        },
+    };
+
+    let event = persistence_handle.playback_history_event(server).create(
+        CreatePlaybackHistoryEvent::new(state.event.play_id, elapsed, PlaybackHistoryEventKind::Interrupt)
+    ).await?;
+
+    notify(events_tx, &event);
+
+    Ok(())
+}
+
+async fn supervise(
+    handle: &mpd::Handle,
+    sub_handle: &mut mpd::SubscriptionHandle,
+    persistence_handle: &persist::Handle,
+    events_tx: &broadcast::Sender<PlaybackHistoryEvent>,
+    server: &str,
+) {
+    if let Err(err) = recover(persistence_handle, events_tx, server).await {
+        tracing::error!(server, "failed to recover playback history: {err}");
+    }
+
+    loop {
+        let result = inner(handle, sub_handle, persistence_handle, events_tx, server).await;
+
+        if sub_handle.is_closed() {
+            tracing::debug!(server, "subscription closed, exiting");
+
+            return;
+        }
+
+        match result {
             Ok(_) => {
                 tracing::debug!("inner exited without error");
+
+                return;
+            },
+            Err(Error::Mpd(err @ (mpd::Error::Disconnected(_) | mpd::Error::Unavailable(_)))) => {
+                tracing::warn!(server, "lost connection: {err}, waiting for it to come back");
+
+                if let Err(err) = interrupt(persistence_handle, events_tx, server).await {
+                    tracing::error!(server, "failed to interrupt playback history: {err}");
+                }
+
+                if !sub_handle.reconnected().await {
+                    return;
+                }
             },
-            // TODO: Handle MPD disconnect.
             Err(err) => {
-                tracing::error!("inner exited with error: {err}");
+                tracing::error!(server, "inner exited with error: {err}, restarting in {} seconds", RESTART_TIMEOUT.as_secs());
+
+                tokio::time::sleep(RESTART_TIMEOUT).await;
             },
         }
+    }
+}
+
+pub fn run(
+    handle: mpd::Handle,
+    mut sub_handle: mpd::SubscriptionHandle,
+    persistence_handle: persist::Handle,
+    events_tx: broadcast::Sender<PlaybackHistoryEvent>,
+    server: String,
+) {
+    tokio::spawn(async move {
+        supervise(&handle, &mut sub_handle, &persistence_handle, &events_tx, &server).await;
     });
 }
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::UNIX_EPOCH + Duration::seconds(seconds)
+    }
+
+    #[test]
+    fn should_interrupt_at_end_of_song() {
+        let result = estimate_interrupt(Duration::seconds(170), at(0), Duration::seconds(180), at(3600));
+
+        assert_eq!(result, (Duration::seconds(180), at(10)));
+    }
+
+    #[test]
+    fn should_interrupt_within_checkpoint_interval() {
+        let result = estimate_interrupt(Duration::seconds(10), at(0), Duration::seconds(180), at(3600));
+
+        assert_eq!(result, (Duration::seconds(10) + CHECKPOINT_INTERVAL, at(0) + CHECKPOINT_INTERVAL));
+    }
+}
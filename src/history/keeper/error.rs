@@ -8,7 +8,7 @@ use crate::persist;
 
 #[derive(Debug)]
 pub enum Error {
-    Mpd,
+    Mpd(mpd::Error),
     Persistence,
 }
 
@@ -19,15 +19,15 @@ impl error::Error for Error {
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         match self {
-            Error::Mpd => write!(f, "mpd error"),
+            Error::Mpd(err) => write!(f, "mpd error: {err}"),
             Error::Persistence => write!(f, "persistence error"),
         }
     }
 }
 
 impl From<mpd::Error> for Error {
-    fn from(_: mpd::Error) -> Self {
-        Error::Mpd
+    fn from(err: mpd::Error) -> Self {
+        Error::Mpd(err)
     }
 }
 
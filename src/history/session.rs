@@ -0,0 +1,254 @@
+use time::Duration;
+use time::OffsetDateTime;
+
+use crate::persist::PlaybackHistoryEvent;
+use crate::persist::PlaybackHistoryEventKind;
+
+// Stopping this close to the end still counts as having played the song through.
+const SKIP_TOLERANCE: Duration = Duration::seconds(5);
+
+#[derive(Debug, PartialEq)]
+pub struct Session {
+    pub listened: Duration,
+    // Songs without a duration, e.g. streams, can't be completed.
+    pub completion: Option<f64>,
+    pub skipped: bool,
+}
+
+struct Reducer {
+    duration: Duration,
+    listened: Duration,
+    position: Duration,
+    playing_since: Option<OffsetDateTime>,
+    paused: bool,
+    stop: Option<PlaybackHistoryEventKind>,
+}
+
+impl Reducer {
+    fn new(duration: Duration) -> Self {
+        Reducer {
+            duration,
+            listened: Duration::ZERO,
+            position: Duration::ZERO,
+            playing_since: None,
+            paused: false,
+            stop: None,
+        }
+    }
+
+    // The song can't play past its end, even if the next event came later.
+    fn pause(&mut self, at: OffsetDateTime) {
+        let Some(since) = self.playing_since.take() else {
+            return;
+        };
+
+        let mut played = (at - since).max(Duration::ZERO);
+
+        if self.duration.is_positive() {
+            played = played.min((self.duration - self.position).max(Duration::ZERO));
+        }
+
+        self.listened += played;
+        self.position += played;
+    }
+
+    // Resume events are recorded with a position that counts the time spent
+    // paused, so the position is carried over from the preceding pause instead.
+    // Seeking while paused, or any other status change then, doesn't resume.
+    // Interrupted plays are picked up again once MPD is back with the same song.
+    fn apply(&mut self, event: &PlaybackHistoryEvent) {
+        self.pause(event.recorded_at);
+
+        match event.kind {
+            PlaybackHistoryEventKind::Start => {
+                self.position = event.elapsed;
+                self.playing_since = Some(event.recorded_at);
+                self.paused = false;
+                self.stop = None;
+            },
+            PlaybackHistoryEventKind::Seek => {
+                self.position = event.elapsed;
+                self.stop = None;
+
+                if !self.paused {
+                    self.playing_since = Some(event.recorded_at);
+                }
+            },
+            PlaybackHistoryEventKind::Resume => {
+                self.playing_since = Some(event.recorded_at);
+                self.paused = false;
+                self.stop = None;
+            },
+            PlaybackHistoryEventKind::Pause => {
+                self.paused = true;
+            },
+            PlaybackHistoryEventKind::Stop => {
+                self.stop = Some(event.kind);
+            },
+            // Picking the play up again says whether it's paused by then.
+            PlaybackHistoryEventKind::Interrupt => {
+                self.paused = false;
+                self.stop = Some(event.kind);
+            },
+        }
+    }
+
+    fn finish(mut self, now: OffsetDateTime) -> Session {
+        // A play that hasn't been stopped yet is still going on.
+        if self.stop.is_none() {
+            self.pause(now);
+        }
+
+        let completion = self.duration.is_positive().then(|| {
+            (self.listened.as_seconds_f64() / self.duration.as_seconds_f64()).min(1.0)
+        });
+
+        // Interrupted plays weren't cut short by the listener.
+        let skipped = self.stop == Some(PlaybackHistoryEventKind::Stop)
+            && self.duration.is_positive()
+            && self.duration - self.position > SKIP_TOLERANCE;
+
+        Session { listened: self.listened, completion, skipped }
+    }
+}
+
+// Events have to belong to the same play and be in the order they were recorded in.
+pub fn reduce<'a, I>(events: I, duration: Duration, now: OffsetDateTime) -> Session
+    where I: IntoIterator<Item = &'a PlaybackHistoryEvent>
+{
+    let mut reducer = Reducer::new(duration);
+
+    for event in events {
+        reducer.apply(event);
+
+        if reducer.stop == Some(PlaybackHistoryEventKind::Stop) {
+            break;
+        }
+    }
+
+    reducer.finish(now)
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::UNIX_EPOCH + Duration::seconds(seconds)
+    }
+
+    fn event(kind: PlaybackHistoryEventKind, elapsed: i64, recorded_at: i64) -> PlaybackHistoryEvent {
+        PlaybackHistoryEvent {
+            play_id: 1,
+            elapsed: Duration::seconds(elapsed),
+            kind,
+            recorded_at: at(recorded_at),
+        }
+    }
+
+    #[test]
+    fn should_not_count_pauses() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Pause, 60, 60),
+            event(PlaybackHistoryEventKind::Resume, 360, 360),
+            event(PlaybackHistoryEventKind::Stop, 480, 480),
+        ];
+
+        let session = reduce(&events, Duration::seconds(180), at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(180), completion: Some(1.0), skipped: false });
+    }
+
+    #[test]
+    fn should_not_count_status_changes_while_paused() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Pause, 60, 60),
+            event(PlaybackHistoryEventKind::Seek, 60, 120),
+            event(PlaybackHistoryEventKind::Resume, 360, 360),
+            event(PlaybackHistoryEventKind::Stop, 390, 390),
+        ];
+
+        let session = reduce(&events, Duration::seconds(300), at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(90), completion: Some(0.3), skipped: true });
+    }
+
+    #[test]
+    fn should_detect_skips() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Seek, 120, 30),
+            event(PlaybackHistoryEventKind::Stop, 150, 60),
+        ];
+
+        let session = reduce(&events, Duration::seconds(300), at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(60), completion: Some(0.2), skipped: true });
+    }
+
+    #[test]
+    fn should_count_ongoing_play_until_now() {
+        let events = vec![event(PlaybackHistoryEventKind::Start, 10, 0)];
+
+        let session = reduce(&events, Duration::seconds(100), at(40));
+
+        assert_eq!(session, Session { listened: Duration::seconds(40), completion: Some(0.4), skipped: false });
+    }
+
+    #[test]
+    fn should_pick_up_interrupted_plays() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Interrupt, 60, 60),
+            event(PlaybackHistoryEventKind::Seek, 90, 90),
+            event(PlaybackHistoryEventKind::Stop, 120, 120),
+        ];
+
+        let session = reduce(&events, Duration::seconds(300), at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(90), completion: Some(0.3), skipped: true });
+    }
+
+    #[test]
+    fn should_pick_up_plays_interrupted_while_paused() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Pause, 60, 60),
+            event(PlaybackHistoryEventKind::Interrupt, 60, 90),
+            event(PlaybackHistoryEventKind::Seek, 60, 120),
+            event(PlaybackHistoryEventKind::Stop, 90, 150),
+        ];
+
+        let session = reduce(&events, Duration::seconds(300), at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(90), completion: Some(0.3), skipped: true });
+    }
+
+    #[test]
+    fn should_not_count_interrupted_plays_as_skipped() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Interrupt, 60, 60),
+        ];
+
+        let session = reduce(&events, Duration::seconds(300), at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(60), completion: Some(0.2), skipped: false });
+    }
+
+    #[test]
+    fn should_not_complete_songs_without_duration() {
+        let events = vec![
+            event(PlaybackHistoryEventKind::Start, 0, 0),
+            event(PlaybackHistoryEventKind::Stop, 0, 600),
+        ];
+
+        let session = reduce(&events, Duration::ZERO, at(1000));
+
+        assert_eq!(session, Session { listened: Duration::seconds(600), completion: None, skipped: false });
+    }
+}
@@ -0,0 +1,225 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::hash::Hash;
+
+use time::Duration;
+use time::OffsetDateTime;
+use time::UtcOffset;
+use time::Weekday;
+
+use crate::history::session;
+use crate::persist::PlaybackHistoryAlbum;
+use crate::persist::PlaybackHistoryArtist;
+use crate::persist::PlaybackHistoryPlay;
+use crate::persist::PlaybackHistoryStats;
+use crate::persist::PlaybackHistoryTally;
+use crate::persist::PlaybackHistoryTotals;
+use crate::persist::PlaybackHistoryTrack;
+
+#[derive(Default)]
+struct TrackTally {
+    title: Option<String>,
+    artist: Option<String>,
+    tally: PlaybackHistoryTally,
+}
+
+// Plays are tallied one at a time as they come, listened time is reduced
+// the same way as for history entries. Buckets go by the listener's local time.
+pub struct Aggregator {
+    utc_offset: UtcOffset,
+    now: OffsetDateTime,
+    totals: PlaybackHistoryTally,
+    tracks: HashMap<String, TrackTally>,
+    artists: HashMap<String, PlaybackHistoryTally>,
+    albums: HashMap<(String, Option<String>), PlaybackHistoryTally>,
+    album_names: HashSet<String>,
+    hours: BTreeMap<u8, PlaybackHistoryTally>,
+    days: BTreeMap<String, PlaybackHistoryTally>,
+    weekdays: HashMap<Weekday, PlaybackHistoryTally>,
+}
+
+fn count(tally: &mut PlaybackHistoryTally, listened: Duration) {
+    tally.plays += 1;
+    tally.listened += listened;
+}
+
+// Like SQL's MIN, missing values don't count.
+fn min(a: Option<String>, b: Option<String>) -> Option<String> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+// The most played come first, ties are broken by time listened and then by name.
+fn top<K, T, F>(tallies: HashMap<K, T>, limit: usize, f: F) -> Vec<(K, T)>
+    where K: Ord + Hash,
+          F: Fn(&T) -> PlaybackHistoryTally,
+{
+    let mut result = tallies.into_iter().collect::<Vec<_>>();
+
+    result.sort_by(|(a_key, a), (b_key, b)| {
+        let (a, b) = (f(a), f(b));
+
+        (Reverse(a.plays), Reverse(a.listened), a_key).cmp(&(Reverse(b.plays), Reverse(b.listened), b_key))
+    });
+
+    result.truncate(limit);
+
+    result
+}
+
+impl Aggregator {
+    pub fn new(utc_offset: UtcOffset, now: OffsetDateTime) -> Self {
+        Aggregator {
+            utc_offset,
+            now,
+            totals: PlaybackHistoryTally::default(),
+            tracks: HashMap::new(),
+            artists: HashMap::new(),
+            albums: HashMap::new(),
+            album_names: HashSet::new(),
+            hours: BTreeMap::new(),
+            days: BTreeMap::new(),
+            weekdays: HashMap::new(),
+        }
+    }
+
+    pub fn add(&mut self, play: PlaybackHistoryPlay) {
+        let listened = session::reduce(&play.events, play.duration, self.now).listened;
+
+        let song = play.song;
+
+        count(&mut self.totals, listened);
+
+        if let Some(uri) = song.uri {
+            let track = self.tracks.entry(uri).or_default();
+
+            track.title = min(track.title.take(), song.title);
+            track.artist = min(track.artist.take(), song.artist);
+
+            count(&mut track.tally, listened);
+        }
+
+        for artist in song.artists {
+            count(self.artists.entry(artist).or_default(), listened);
+        }
+
+        if let Some(album) = song.album {
+            self.album_names.insert(album.clone());
+
+            count(self.albums.entry((album, song.album_artist)).or_default(), listened);
+        }
+
+        let started_at = play.started_at.to_offset(self.utc_offset);
+
+        let day = format!("{:04}-{:02}-{:02}", started_at.year(), u8::from(started_at.month()), started_at.day());
+
+        count(self.hours.entry(started_at.hour()).or_default(), listened);
+        count(self.days.entry(day).or_default(), listened);
+        count(self.weekdays.entry(started_at.weekday()).or_default(), listened);
+    }
+
+    pub fn totals(&self) -> PlaybackHistoryTotals {
+        PlaybackHistoryTotals {
+            tally: self.totals,
+            tracks: i64::try_from(self.tracks.len()).unwrap_or(i64::MAX),
+            artists: i64::try_from(self.artists.len()).unwrap_or(i64::MAX),
+            albums: i64::try_from(self.album_names.len()).unwrap_or(i64::MAX),
+        }
+    }
+
+    pub fn finish(self, limit: usize) -> PlaybackHistoryStats {
+        let totals = self.totals();
+
+        let artists = top(self.artists, limit, |tally| *tally)
+            .into_iter()
+            .map(|(artist, tally)| PlaybackHistoryArtist { artist, tally })
+            .collect();
+
+        let albums = top(self.albums, limit, |tally| *tally)
+            .into_iter()
+            .map(|((album, album_artist), tally)| PlaybackHistoryAlbum { album, album_artist, tally })
+            .collect();
+
+        let tracks = top(self.tracks, limit, |track| track.tally)
+            .into_iter()
+            .map(|(uri, TrackTally { title, artist, tally })| PlaybackHistoryTrack { uri, title, artist, tally })
+            .collect();
+
+        PlaybackHistoryStats {
+            totals,
+            artists,
+            albums,
+            tracks,
+            hours: self.hours.into_iter().collect(),
+            days: self.days.into_iter().collect(),
+            weekdays: self.weekdays.into_iter().collect(),
+        }
+    }
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::persist::PlaybackHistoryEvent;
+    use crate::persist::PlaybackHistoryEventKind;
+    use crate::persist::PlaybackHistorySong;
+
+    use super::*;
+
+    fn at(seconds: i64) -> OffsetDateTime {
+        OffsetDateTime::UNIX_EPOCH + Duration::seconds(seconds)
+    }
+
+    fn play(play_id: i64, artists: &[&str], started_at: i64, listened: i64) -> PlaybackHistoryPlay {
+        let event = |kind, elapsed, recorded_at| PlaybackHistoryEvent {
+            play_id,
+            elapsed: Duration::seconds(elapsed),
+            kind,
+            recorded_at: at(recorded_at),
+        };
+
+        PlaybackHistoryPlay {
+            play_id,
+            started_at: at(started_at),
+            duration: Duration::seconds(300),
+            song: PlaybackHistorySong {
+                uri: Some(format!("{play_id}.flac")),
+                title: None,
+                artist: artists.first().map(|artist| (*artist).to_owned()),
+                artists: artists.iter().map(|artist| (*artist).to_owned()).collect(),
+                album: None,
+                album_artist: None,
+            },
+            events: vec![
+                event(PlaybackHistoryEventKind::Start, 0, started_at),
+                event(PlaybackHistoryEventKind::Stop, listened, started_at + listened),
+            ],
+        }
+    }
+
+    #[test]
+    fn should_rank_by_plays_then_time_listened() {
+        let mut aggregator = Aggregator::new(UtcOffset::from_hms(2, 0, 0).unwrap(), at(10_000));
+
+        aggregator.add(play(1, &["Bravo"], 0, 60));
+        aggregator.add(play(2, &["Alfa", "Bravo"], 3600, 120));
+        aggregator.add(play(3, &["Alfa"], 7200, 30));
+        aggregator.add(play(4, &["Charlie"], 7300, 300));
+
+        let stats = aggregator.finish(2);
+
+        let artists = stats.artists.iter()
+            .map(|artist| (artist.artist.as_str(), artist.tally.plays, artist.tally.listened.whole_seconds()))
+            .collect::<Vec<_>>();
+
+        assert_eq!(stats.totals.tally.plays, 4);
+        assert_eq!(stats.totals.artists, 3);
+        assert_eq!(artists, vec![("Bravo", 2, 180), ("Alfa", 2, 150)]);
+        assert_eq!(stats.hours.iter().map(|(hour, tally)| (*hour, tally.plays)).collect::<Vec<_>>(), vec![(2, 1), (3, 1), (4, 2)]);
+    }
+}
@@ -0,0 +1,5 @@
+pub use handle::Handle;
+
+mod fuzzy;
+mod handle;
+pub mod indexer;
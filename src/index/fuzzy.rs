@@ -0,0 +1,293 @@
+use std::collections::HashMap;
+
+use unicode_normalization::char::is_combining_mark;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::persist::LibraryItem;
+use crate::persist::LibraryItemKind;
+
+const NAME_WEIGHT: f64 = 10.0;
+const TITLE_WEIGHT: f64 = 8.0;
+const ARTIST_WEIGHT: f64 = 6.0;
+const ALBUM_WEIGHT: f64 = 4.0;
+const URI_WEIGHT: f64 = 1.0;
+
+const EXACT_SIMILARITY: f64 = 1.0;
+const PREFIX_SIMILARITY: f64 = 0.9;
+const TYPO_SIMILARITY: f64 = 0.8;
+
+// Keeps match expressions of terms that resemble too many words in check.
+const MAX_EXPANSIONS: usize = 64;
+
+// Folds case and diacritics so that "Sigur Rós" and "sigur ros" end up
+// the same, anything that isn't alphanumeric separates words.
+pub fn normalize(s: &str) -> Vec<Vec<char>> {
+    s.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect::<String>()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.chars().collect())
+        .collect()
+}
+
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, x) in a.iter().enumerate() {
+        curr[0] = i + 1;
+
+        for (j, y) in b.iter().enumerate() {
+            curr[j + 1] = (prev[j] + usize::from(x != y))
+                .min(prev[j + 1] + 1)
+                .min(curr[j] + 1);
+        }
+
+        std::mem::swap(&mut prev, &mut curr);
+    }
+
+    prev[b.len()]
+}
+
+// Short words are too easy to confuse, so they have to be spelled right.
+fn max_edits(len: usize) -> usize {
+    match len {
+        0..=2 => 0,
+        3..=5 => 1,
+        _ => 2,
+    }
+}
+
+fn similarity(term: &[char], word: &[char]) -> Option<f64> {
+    if term == word {
+        return Some(EXACT_SIMILARITY);
+    }
+
+    if word.starts_with(term) {
+        return Some(PREFIX_SIMILARITY);
+    }
+
+    // Words are also matched by their beginning so that typos in
+    // an unfinished word don't get it rejected for being too short.
+    let edits = distance(term, word)
+        .min(distance(term, &word[..word.len().min(term.len())]));
+
+    if edits > max_edits(term.len()) {
+        return None;
+    }
+
+    Some(TYPO_SIMILARITY * (1.0 - edits as f64 / term.len() as f64))
+}
+
+// Words of the index vocabulary, normalized once for every query until the index changes.
+pub struct Vocabulary {
+    words: Vec<(String, Vec<char>)>,
+}
+
+impl Vocabulary {
+    pub fn new(terms: Vec<String>) -> Self {
+        let words = terms.into_iter()
+            .flat_map(|term| {
+                normalize(&term).into_iter()
+                    .map(move |word| (term.clone(), word))
+            })
+            .collect();
+
+        Vocabulary { words }
+    }
+}
+
+fn quote(term: &str) -> String {
+    format!(r#""{}""#, term.replace('"', r#""""#))
+}
+
+// Narrows the items down to the ones that could match at all. Every term matches
+// words it begins along with the words of the vocabulary it's a typo of.
+pub fn match_expression(terms: &[Vec<char>], vocabulary: &Vocabulary) -> Option<String> {
+    if terms.is_empty() {
+        return None;
+    }
+
+    let expressions = terms.iter()
+        .map(|term| {
+            let mut typos = vocabulary.words.iter()
+                .filter(|(_, word)| !word.starts_with(term))
+                .filter_map(|(raw, word)| Some((similarity(term, word)?, raw.as_str())))
+                .collect::<Vec<_>>();
+
+            typos.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+            typos.dedup_by_key(|(_, raw)| *raw);
+
+            let prefix = format!("{}*", quote(&term.iter().collect::<String>()));
+
+            let alternatives = typos.into_iter()
+                .take(MAX_EXPANSIONS)
+                .map(|(_, raw)| quote(raw))
+                .chain([prefix])
+                .collect::<Vec<_>>();
+
+            format!("({})", alternatives.join(" OR "))
+        })
+        .collect::<Vec<_>>();
+
+    Some(expressions.join(" AND "))
+}
+
+// Tag values repeat a lot across items, so each one is only normalized once.
+struct Normalizer<'a> {
+    cache: HashMap<&'a str, Vec<Vec<char>>>,
+}
+
+impl<'a> Normalizer<'a> {
+    fn normalize(&mut self, s: &'a str) -> &[Vec<char>] {
+        self.cache.entry(s).or_insert_with(|| normalize(s))
+    }
+}
+
+fn name(item: &LibraryItem) -> &str {
+    let name = item.uri.rsplit('/')
+        .next()
+        .unwrap_or_default();
+
+    match item.kind {
+        LibraryItemKind::File => name.rsplit_once('.').map_or(name, |(stem, _)| stem),
+        LibraryItemKind::Directory => name,
+    }
+}
+
+fn fields(item: &LibraryItem) -> Vec<(f64, &str)> {
+    let tags = [
+        (TITLE_WEIGHT, &item.tags.titles),
+        (ARTIST_WEIGHT, &item.tags.artists),
+        (ALBUM_WEIGHT, &item.tags.albums),
+        (ALBUM_WEIGHT, &item.tags.album_artists),
+    ];
+
+    tags.into_iter()
+        .flat_map(|(weight, values)| values.iter().map(move |value| (weight, value.as_str())))
+        .chain([(NAME_WEIGHT, name(item)), (URI_WEIGHT, item.uri.as_str())])
+        .collect()
+}
+
+// Every term has to match some word of the item, the score adds
+// up the best match of each term weighted by where it was found.
+fn score<'a>(terms: &[Vec<char>], item: &'a LibraryItem, normalizer: &mut Normalizer<'a>) -> Option<f64> {
+    let fields = fields(item);
+
+    terms.iter()
+        .map(|term| {
+            fields.iter()
+                .filter_map(|(weight, value)| {
+                    normalizer.normalize(value).iter()
+                        .filter_map(|word| Some(weight * similarity(term, word)?))
+                        .max_by(f64::total_cmp)
+                })
+                .max_by(f64::total_cmp)
+        })
+        .sum()
+}
+
+pub fn search(terms: &[Vec<char>], items: Vec<LibraryItem>) -> Vec<LibraryItem> {
+    if terms.is_empty() {
+        return vec![];
+    }
+
+    let mut normalizer = Normalizer { cache: HashMap::new() };
+
+    let mut scores = items.iter()
+        .enumerate()
+        .filter_map(|(i, item)| Some((score(terms, item, &mut normalizer)?, i)))
+        .collect::<Vec<_>>();
+
+    scores.sort_by(|(a_score, a), (b_score, b)| {
+        let (a, b) = (&items[*a], &items[*b]);
+
+        b_score.total_cmp(a_score)
+            .then_with(|| {
+                (a.kind != LibraryItemKind::Directory).cmp(&(b.kind != LibraryItemKind::Directory))
+            })
+            .then_with(|| a.uri.cmp(&b.uri))
+    });
+
+    let mut items = items.into_iter()
+        .map(Some)
+        .collect::<Vec<_>>();
+
+    scores.into_iter()
+        .filter_map(|(_, i)| items[i].take())
+        .collect()
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use crate::mpd::DbTags;
+
+    use super::*;
+
+    fn file(uri: &str, title: &str, artist: &str) -> LibraryItem {
+        LibraryItem {
+            kind: LibraryItemKind::File,
+            uri: uri.to_owned(),
+            tags: DbTags {
+                titles: vec![title.to_owned()],
+                artists: vec![artist.to_owned()],
+                ..Default::default()
+            },
+            format: None,
+            duration: Duration::seconds(25),
+            updated_at: None,
+        }
+    }
+
+    #[test]
+    fn should_fold_case_and_diacritics() {
+        assert_eq!(normalize("Sigur Rós - Hvarf/Heim"), normalize("sigur ros HVARF heim"));
+    }
+
+    #[test]
+    fn should_tolerate_typos() {
+        let items = search(&normalize("beatls abey"), vec![
+            file("beatles/abbey-road/01.flac", "Come Together", "The Beatles"),
+            file("stones/sticky-fingers/01.flac", "Brown Sugar", "The Rolling Stones"),
+        ]);
+
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].uri, "beatles/abbey-road/01.flac");
+    }
+
+    #[test]
+    fn should_rank_exact_matches_first() {
+        let items = search(&normalize("sigur ros"), vec![
+            file("a/01.flac", "Svefn-g-englar", "Sigur Rás"),
+            file("b/01.flac", "Svefn-g-englar", "Sigur Rós"),
+        ]);
+
+        assert_eq!(items.iter().map(|item| item.uri.as_str()).collect::<Vec<_>>(), vec!["b/01.flac", "a/01.flac"]);
+    }
+
+    #[test]
+    fn should_not_tolerate_typos_in_short_terms() {
+        assert!(search(&normalize("ab"), vec![file("x/01.flac", "Cd", "Ef")]).is_empty());
+    }
+
+    #[test]
+    fn should_expand_terms_with_typos_of_vocabulary_words() {
+        let vocabulary = Vocabulary::new(vec!["abbey".to_owned(), "beatles".to_owned(), "röad".to_owned()]);
+
+        assert_eq!(
+            match_expression(&normalize("beatl abey"), &vocabulary),
+            Some(r#"("beatl"*) AND ("abbey" OR "abey"*)"#.to_owned()),
+        );
+
+        assert_eq!(
+            match_expression(&normalize("rowd"), &vocabulary),
+            Some(r#"("röad" OR "rowd"*)"#.to_owned()),
+        );
+    }
+}
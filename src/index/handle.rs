@@ -0,0 +1,262 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use time::Duration;
+use time::OffsetDateTime;
+
+use crate::convert::IntoResult;
+use crate::index::fuzzy;
+use crate::mpd::DbAudioFormat;
+use crate::mpd::DbItem;
+use crate::mpd::DbTags;
+use crate::persist;
+use crate::persist::LibraryItem;
+use crate::persist::LibraryItemKind;
+
+// Remembers when the index the vocabulary was loaded from was synced.
+type CachedVocabulary = (OffsetDateTime, Arc<fuzzy::Vocabulary>);
+
+#[derive(Clone)]
+pub struct Handle {
+    inner: persist::Handle,
+    server: String,
+    vocabulary: Arc<Mutex<Option<CachedVocabulary>>>,
+}
+
+impl Handle {
+    pub fn new(persistence_handle: persist::Handle, server: String) -> Self {
+        Handle { inner: persistence_handle, server, vocabulary: Arc::new(Mutex::new(None)) }
+    }
+}
+
+// Scoring is CPU bound and may take a while on large libraries.
+async fn blocking<T, F>(f: F) -> Result<T, String>
+    where T: Send + 'static,
+          F: FnOnce() -> T + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await
+        .map_err(|err| format!("failed to run fuzzy search: {err}"))
+}
+
+fn format_audio_format(format: DbAudioFormat) -> String {
+    format!("{}:{}:{}", format.sampling_rate, format.bit_depth, format.number_of_channels)
+}
+
+// Playlists are not indexed, so they are skipped.
+pub(super) fn into_library_item(item: DbItem) -> Option<LibraryItem> {
+    let item = match item {
+        DbItem::File { uri, duration, tags, format, updated_at } => LibraryItem {
+            kind: LibraryItemKind::File,
+            uri,
+            tags: *tags,
+            format: format.map(format_audio_format),
+            duration,
+            updated_at: Some(updated_at),
+        },
+        DbItem::Directory { uri, updated_at } => LibraryItem {
+            kind: LibraryItemKind::Directory,
+            uri,
+            tags: DbTags::default(),
+            format: None,
+            duration: Duration::ZERO,
+            updated_at,
+        },
+        DbItem::Playlist { .. } => {
+            return None;
+        },
+    };
+
+    Some(item)
+}
+
+impl TryFrom<LibraryItem> for DbItem {
+    type Error = String;
+
+    fn try_from(item: LibraryItem) -> Result<Self, Self::Error> {
+        match item.kind {
+            LibraryItemKind::File => DbItem::File {
+                uri: item.uri,
+                duration: item.duration,
+                tags: Box::new(item.tags),
+                format: item.format
+                    .map(|format| format.parse())
+                    .transpose()?,
+                updated_at: item.updated_at.unwrap_or_default(),
+            },
+            LibraryItemKind::Directory => DbItem::Directory {
+                uri: item.uri,
+                updated_at: item.updated_at,
+            },
+        }.into_ok()
+    }
+}
+
+// Every word of the query has to match the beginning of some token,
+// quoting keeps FTS5 from interpreting any of them as syntax.
+fn match_expression(query: &str) -> Option<String> {
+    let terms = query.split_whitespace()
+        .filter(|term| term.chars().any(char::is_alphanumeric))
+        .map(|term| format!(r#""{}"*"#, term.replace('"', r#""""#)))
+        .collect::<Vec<_>>();
+
+    if terms.is_empty() {
+        return None;
+    }
+
+    Some(terms.join(" "))
+}
+
+impl Handle {
+    pub async fn is_synced(&self) -> Result<bool, String> {
+        let synced_at = self.inner.library_item(&self.server)
+            .get_synced_at()
+            .await?;
+
+        Ok(synced_at.is_some())
+    }
+
+    pub async fn search(&self, query: &str, offset: usize, limit: usize) -> Result<(Vec<DbItem>, usize), String> {
+        let Some(query) = match_expression(query) else {
+            return Ok((vec![], 0));
+        };
+
+        let (items, total) = self.inner.library_item(&self.server)
+            .search(&query, offset, limit)
+            .await?;
+
+        let items = items.into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((items, total))
+    }
+
+    // The vocabulary is only reloaded once the index has been refreshed since it was last loaded.
+    async fn vocabulary(&self, synced_at: OffsetDateTime) -> Result<Arc<fuzzy::Vocabulary>, String> {
+        if let Some((cached_at, vocabulary)) = self.vocabulary.lock().unwrap().as_ref() {
+            if *cached_at == synced_at {
+                return Ok(vocabulary.clone());
+            }
+        }
+
+        let terms = self.inner.library_item(&self.server)
+            .get_terms()
+            .await?;
+
+        let vocabulary = Arc::new(blocking(move || fuzzy::Vocabulary::new(terms)).await?);
+
+        *self.vocabulary.lock().unwrap() = Some((synced_at, vocabulary.clone()));
+
+        Ok(vocabulary)
+    }
+
+    // Typos can't be looked up in the index directly, so the query is expanded
+    // with the words of the index it resembles and only what that matches gets scored.
+    pub async fn fuzzy_search(&self, query: &str, offset: usize, limit: usize) -> Result<(Vec<DbItem>, usize), String> {
+        let synced_at = self.inner.library_item(&self.server)
+            .get_synced_at()
+            .await?;
+
+        let Some(synced_at) = synced_at else {
+            return Ok((vec![], 0));
+        };
+
+        let vocabulary = self.vocabulary(synced_at).await?;
+
+        let terms = Arc::new(fuzzy::normalize(query));
+
+        let expression = {
+            let terms = terms.clone();
+
+            blocking(move || fuzzy::match_expression(&terms, &vocabulary)).await?
+        };
+
+        let Some(expression) = expression else {
+            return Ok((vec![], 0));
+        };
+
+        let items = self.inner.library_item(&self.server)
+            .get_all_matching(&expression)
+            .await?;
+
+        let items = blocking(move || fuzzy::search(&terms, items)).await?;
+        let total = items.len();
+
+        let items = items.into_iter()
+            .skip(offset)
+            .take(limit)
+            .collect();
+
+        let items = self.inner.library_item(&self.server)
+            .with_all_tags(items)
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok((items, total))
+    }
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_build_prefix_match_expression() {
+        assert_eq!(
+            match_expression("  beatl  ABBEY \"road "),
+            Some(r#""beatl"* "ABBEY"* """road"*"#.to_owned()),
+        );
+    }
+
+    #[test]
+    fn should_not_build_match_expression_without_terms() {
+        assert_eq!(match_expression(" - * "), None);
+    }
+
+    #[tokio::test]
+    async fn should_keep_every_tag_until_removed() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+
+        let library_items = persistence_handle.library_item("default");
+
+        let directory = LibraryItem {
+            kind: LibraryItemKind::Directory,
+            uri: "alfa".to_owned(),
+            tags: DbTags::default(),
+            format: None,
+            duration: Duration::ZERO,
+            updated_at: None,
+        };
+
+        let file = LibraryItem {
+            kind: LibraryItemKind::File,
+            uri: "alfa/one.flac".to_owned(),
+            tags: DbTags {
+                titles: vec!["One".to_owned()],
+                other: [("movement".to_owned(), vec!["I. Allegro".to_owned()])].into(),
+                ..Default::default()
+            },
+            format: None,
+            duration: Duration::seconds(25),
+            updated_at: Some("2023-01-01T00:00:00Z".to_owned()),
+        };
+
+        library_items.update_all(vec![directory, file], vec![]).await.unwrap();
+
+        let handle = Handle::new(persistence_handle.clone(), "default".to_owned());
+
+        let (items, total) = handle.search("one", 0, 10).await.unwrap();
+
+        assert_eq!(total, 1);
+        assert!(matches!(&items[0], DbItem::File { tags, .. } if tags.other["movement"] == vec!["I. Allegro".to_owned()]));
+
+        library_items.update_all(vec![], vec!["alfa".to_owned()]).await.unwrap();
+
+        assert_eq!(handle.search("one", 0, 10).await.unwrap().1, 0);
+        assert!(library_items.get_all_by_parent("alfa").await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+use crate::covers;
+use crate::index::handle::into_library_item;
+use crate::index::indexer::error::Error;
+use crate::index::indexer::result::Result;
+use crate::mpd;
+use crate::mpd::DbItem;
+use crate::mpd::Update;
+use crate::persist;
+use crate::persist::LibraryItem;
+use crate::persist::LibraryItemKind;
+
+mod error;
+mod result;
+
+const ROOT_URI: &str = "";
+
+const RESTART_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+#[cfg_attr(test, derive(Debug))]
+struct Diff {
+    changed: Vec<LibraryItem>,
+    removed: Vec<String>,
+    directories: Vec<String>,
+}
+
+// Whatever MPD reports with a different modification time than the one indexed has changed,
+// whatever it no longer reports is gone.
+fn diff(listed: Vec<DbItem>, mut indexed: HashMap<String, Option<String>>) -> Diff {
+    let mut changed = Vec::new();
+    let mut directories = Vec::new();
+
+    for item in listed {
+        if let DbItem::Directory { uri, .. } = &item {
+            directories.push(uri.clone());
+        }
+
+        let Some(item) = into_library_item(item) else {
+            continue;
+        };
+
+        if indexed.remove(&item.uri) != Some(item.updated_at.clone()) {
+            changed.push(item);
+        }
+    }
+
+    Diff { changed, removed: indexed.into_keys().collect(), directories }
+}
+
+// Directories are compared against the index one at a time, so that neither the whole
+// library is held in memory nor anything that didn't change is written again.
+async fn refresh(
+    handle: &mpd::Handle,
+    persistence_handle: &persist::Handle,
+    covers: &covers::Handle,
+    server: &str,
+) -> Result<()> {
+    let library_items = persistence_handle.library_item(server);
+
+    let mut directories = vec![ROOT_URI.to_owned()];
+    let mut count = 0;
+
+    while let Some(directory) = directories.pop() {
+        let listed = handle.db().get(directory.clone(), None).await?;
+
+        let indexed = library_items.get_all_by_parent(&directory).await?
+            .into_iter()
+            .collect();
+
+        let mut diff = diff(listed, indexed);
+
+        count += diff.changed.len() + diff.removed.len();
+
+        let song = diff.changed.iter()
+            .find(|item| item.kind == LibraryItemKind::File)
+            .map(|item| item.uri.clone());
+
+        library_items.update_all(diff.changed, diff.removed).await?;
+
+        // A cover that can't be fetched only costs a placeholder, not the index.
+        if let Some(song) = song {
+            if let Err(err) = covers.precompute_placeholder(handle, &song).await {
+                tracing::warn!(server, song, "failed to precompute cover placeholder: {err}");
+            }
+        }
+
+        directories.append(&mut diff.directories);
+    }
+
+    tracing::debug!(server, count, "refreshed library index");
+
+    if count > 0 || library_items.get_synced_at().await?.is_none() {
+        library_items.set_synced().await?;
+    }
+
+    Ok(())
+}
+
+async fn inner(
+    handle: &mpd::Handle,
+    sub_handle: &mut mpd::SubscriptionHandle,
+    persistence_handle: &persist::Handle,
+    covers: &covers::Handle,
+    server: &str,
+) -> Result<()> {
+    refresh(handle, persistence_handle, covers, server).await?;
+
+    loop {
+        let updates = sub_handle.updates().await?;
+
+        if updates.iter().any(|update| matches!(update, Update::Db)) {
+            refresh(handle, persistence_handle, covers, server).await?;
+        }
+    }
+}
+
+// The library may have changed in any way while MPD was away, so
+// the index is compared against it again whenever it comes back.
+async fn supervise(
+    handle: &mpd::Handle,
+    sub_handle: &mut mpd::SubscriptionHandle,
+    persistence_handle: &persist::Handle,
+    covers: &covers::Handle,
+    server: &str,
+) {
+    loop {
+        let result = inner(handle, sub_handle, persistence_handle, covers, server).await;
+
+        if sub_handle.is_closed() {
+            tracing::debug!(server, "subscription closed, exiting");
+
+            return;
+        }
+
+        match result {
+            Ok(_) => {
+                tracing::debug!("inner exited without error");
+
+                return;
+            },
+            Err(Error::Mpd(err @ (mpd::Error::Disconnected(_) | mpd::Error::Unavailable(_)))) => {
+                tracing::warn!(server, "lost connection: {err}, waiting for it to come back");
+
+                if !sub_handle.reconnected().await {
+                    return;
+                }
+            },
+            Err(err) => {
+                tracing::error!(server, "inner exited with error: {err}, restarting in {} seconds", RESTART_TIMEOUT.as_secs());
+
+                tokio::time::sleep(RESTART_TIMEOUT).await;
+            },
+        }
+    }
+}
+
+pub fn run(
+    handle: mpd::Handle,
+    mut sub_handle: mpd::SubscriptionHandle,
+    persistence_handle: persist::Handle,
+    covers: covers::Handle,
+    server: String,
+) {
+    tokio::spawn(async move {
+        supervise(&handle, &mut sub_handle, &persistence_handle, &covers, &server).await;
+    });
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+
+    use super::*;
+
+    fn file(uri: &str, updated_at: &str) -> DbItem {
+        DbItem::File {
+            uri: uri.to_owned(),
+            duration: Duration::seconds(25),
+            tags: Box::default(),
+            format: None,
+            updated_at: updated_at.to_owned(),
+        }
+    }
+
+    fn directory(uri: &str, updated_at: &str) -> DbItem {
+        DbItem::Directory { uri: uri.to_owned(), updated_at: Some(updated_at.to_owned()) }
+    }
+
+    #[test]
+    fn should_only_keep_what_changed() {
+        let listed = vec![
+            file("alfa/one.flac", "2023-01-01T00:00:00Z"),
+            file("alfa/two.flac", "2023-01-02T00:00:00Z"),
+            file("alfa/three.flac", "2023-01-01T00:00:00Z"),
+            directory("alfa/beta", "2023-01-01T00:00:00Z"),
+            DbItem::Playlist { uri: "alfa/list.m3u".to_owned() },
+        ];
+
+        let indexed = HashMap::from([
+            ("alfa/one.flac".to_owned(), Some("2023-01-01T00:00:00Z".to_owned())),
+            ("alfa/two.flac".to_owned(), Some("2023-01-01T00:00:00Z".to_owned())),
+            ("alfa/beta".to_owned(), Some("2023-01-01T00:00:00Z".to_owned())),
+            ("alfa/gamma".to_owned(), Some("2023-01-01T00:00:00Z".to_owned())),
+        ]);
+
+        let diff = diff(listed, indexed);
+
+        let changed = diff.changed.iter()
+            .map(|item| item.uri.as_str())
+            .collect::<Vec<_>>();
+
+        assert_eq!(changed, vec!["alfa/two.flac", "alfa/three.flac"]);
+        assert_eq!(diff.removed, vec!["alfa/gamma".to_owned()]);
+        assert_eq!(diff.directories, vec!["alfa/beta".to_owned()]);
+    }
+}
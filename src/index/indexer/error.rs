@@ -0,0 +1,38 @@
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use crate::mpd;
+use crate::persist;
+
+#[derive(Debug)]
+pub enum Error {
+    Mpd(mpd::Error),
+    Persistence,
+}
+
+impl error::Error for Error {
+    // default
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Mpd(err) => write!(f, "mpd error: {err}"),
+            Error::Persistence => write!(f, "persistence error"),
+        }
+    }
+}
+
+impl From<mpd::Error> for Error {
+    fn from(err: mpd::Error) -> Self {
+        Error::Mpd(err)
+    }
+}
+
+impl From<persist::Error> for Error {
+    fn from(_: persist::Error) -> Self {
+        Error::Persistence
+    }
+}
@@ -0,0 +1,3 @@
+use crate::index::indexer::error;
+
+pub type Result<T> = std::result::Result<T, error::Error>;
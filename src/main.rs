@@ -16,10 +16,14 @@ use std::process;
 
 use assets::assets;
 use axum::Extension;
+use axum::middleware;
 use axum::Router;
 use axum::routing::delete;
 use axum::routing::get;
+use axum::routing::post;
+use axum::routing::put;
 use hyper::Server;
+use tokio::sync::broadcast;
 
 mod args;
 mod config;
@@ -31,6 +35,12 @@ mod persist;
 mod history;
 mod convert;
 mod labels;
+mod servers;
+mod index;
+mod covers;
+mod scrobbler;
+
+const HISTORY_EVENTS_CAPACITY: usize = 64;
 
 #[tokio::main(flavor = "current_thread")]
 async fn main() -> Result<(), String> {
@@ -52,51 +62,144 @@ async fn main() -> Result<(), String> {
     handle.set_level(config.logging.level)?;
 
     let labels_handle = labels::Handle::new(persistence_handle.clone());
-    let history_handle = history::Handle::new(persistence_handle.clone());
 
-    let handle = mpd::Handle::new(move || {
-        let host = config.mpd.host.clone();
-        let port = config.mpd.port;
+    let scrobblers = config.scrobblers;
+
+    let cover_cache_size = config.covers.cache_size_bytes();
 
-        let password = config.mpd.password.clone();
+    let servers = config.mpd.into_iter().map(|mpd| {
+        let address = mpd.address();
+        let password = mpd.password();
 
-        async move {
-            let addr = format!("{host}:{port}");
+        let partitions = mpd::PartitionRegistry::new(move || {
+            let address = address.clone();
+            let password = password.clone();
 
-            let mut client = mpd::connect(&addr).await?;
+            async move {
+                let mut client = mpd::connect(&address).await?;
 
-            if let Some(password) = password {
-                client.password(password).await?;
+                if let Some(password) = password {
+                    client.password(password).await?;
+                }
+
+                Ok(client)
             }
+        });
 
-            Ok(client)
-        }
-    });
+        let mpd::Partition { handle, sub_handle } = partitions.default_partition();
 
-    let sub_handle = mpd::SubscriptionHandle::new(handle.clone());
+        let (history_events_tx, history_events_rx) = broadcast::channel(HISTORY_EVENTS_CAPACITY);
 
-    history::keeper::run(
-        handle.clone(),
-        sub_handle.clone(),
-        persistence_handle.clone(),
-    );
+        history::keeper::run(
+            handle.clone(),
+            sub_handle.clone(),
+            persistence_handle.clone(),
+            history_events_tx,
+            mpd.name.clone(),
+        );
 
-    let api = Router::new()
+        scrobbler::run(
+            history_events_rx,
+            persistence_handle.clone(),
+            mpd.name.clone(),
+            scrobblers.clone(),
+        );
+
+        let covers_handle = covers::Handle::new(persistence_handle.clone(), mpd.name.clone(), cover_cache_size);
+
+        index::indexer::run(
+            handle,
+            sub_handle.clone(),
+            persistence_handle.clone(),
+            covers_handle.clone(),
+            mpd.name.clone(),
+        );
+
+        let history_handle = history::Handle::new(persistence_handle.clone(), mpd.name.clone());
+        let index_handle = index::Handle::new(persistence_handle.clone(), mpd.name.clone());
+
+        (mpd.name, servers::Server::new(partitions, history_handle, index_handle, covers_handle))
+    }).collect();
+
+    let servers = servers::Registry::new(servers);
+
+    let servers::Server {
+        partitions,
+        history: history_handle,
+        index: index_handle,
+        covers: covers_handle,
+    } = servers.default_server().clone();
+
+    let handle = partitions.default_partition().handle;
+
+    let routes = Router::new()
         .route("/ws", get(route::ws::websocket))
         .route("/database", get(route::db::database))
         .route("/database/cover", get(route::db::cover))
         .route("/database/count", get(route::db::count))
         .route("/database/recents", get(route::db::recents))
+        .route("/database/search", get(route::db::search).post(route::db::search_body))
+        .route("/database/tagtypes", get(route::db::tagtypes))
+        .route("/database/update", post(route::db::update))
+        .route("/library/artists", get(route::library::artists))
+        .route("/library/albums", get(route::library::albums))
+        .route("/library/genres", get(route::library::genres))
+        .route("/library/years", get(route::library::years))
+        .route("/queue", post(route::queue::add).put(route::queue::replace).delete(route::queue::clear))
+        .route("/queue/:id", delete(route::queue::remove))
+        .route("/queue/:id/move", post(route::queue::move_id))
+        .route("/queue/:id/next", post(route::queue::play_next))
+        .route("/queue/:id/swap", post(route::queue::swap))
+        .route("/queue/:id/priority", put(route::queue::prio_id))
+        .route("/queue/range", delete(route::queue::remove_range))
+        .route("/queue/move", post(route::queue::move_range))
+        .route("/queue/priority", put(route::queue::prio))
+        .route("/queue/shuffle", post(route::queue::shuffle))
+        .route("/queue/next", post(route::queue::next))
+        .route("/queue/prev", post(route::queue::prev))
+        .route("/queue/repeat", put(route::queue::repeat))
+        .route("/queue/consume", put(route::queue::consume))
+        .route("/queue/random", put(route::queue::random))
+        .route("/queue/single", put(route::queue::single))
+        .route("/playback/play", post(route::playback::play))
+        .route("/playback/toggle", post(route::playback::toggle))
+        .route("/playback/stop", post(route::playback::stop))
+        .route("/playback/seek", post(route::playback::seek))
+        .route("/volume", put(route::volume::set))
+        .route("/outputs", get(route::outputs::outputs))
+        .route("/outputs/:id/enable", post(route::outputs::enable))
+        .route("/outputs/:id/disable", post(route::outputs::disable))
+        .route("/outputs/:id/toggle", post(route::outputs::toggle))
+        .route("/outputs/:id/attributes", put(route::outputs::set))
+        .route("/partitions", get(route::partitions::partitions).post(route::partitions::create))
+        .route("/partitions/:name", delete(route::partitions::delete))
+        .route("/partitions/:name/outputs", post(route::partitions::move_output))
+        .route("/stickers", get(route::stickers::stickers).delete(route::stickers::clear))
+        .route("/stickers/find", get(route::stickers::find))
+        .route("/stickers/:name", get(route::stickers::sticker).put(route::stickers::set).delete(route::stickers::delete))
         .route("/playlists", get(route::playlists::playlists))
-        .route("/playlists/:name", get(route::playlists::playlist).delete(route::playlists::delete))
-        .route("/playlists/:name/songs", delete(route::playlists::delete_songs))
+        .route("/playlists/:name", get(route::playlists::playlist).post(route::playlists::save).delete(route::playlists::delete))
+        .route("/playlists/:name/songs", post(route::playlists::add_songs).delete(route::playlists::delete_songs))
+        .route("/playlists/:name/songs/move", post(route::playlists::move_song))
+        .route("/playlists/:name/songs/clear", post(route::playlists::clear))
+        .route("/playlists/:name/rename", post(route::playlists::rename))
         .route("/history", get(route::history::history))
+        .route("/history/stats", get(route::history::stats))
         .route("/labels", get(route::labels::labels).post(route::labels::create))
-        .route("/labels/:id", delete(route::labels::delete))
+        .route("/labels/:id", delete(route::labels::delete));
+
+    let api = Router::new()
+        .merge(routes.clone())
+        .route("/servers", get(route::servers::servers))
+        .nest("/servers/:server", routes.route_layer(middleware::from_fn(route::servers::select)))
+        .layer(Extension(servers))
+        .layer(Extension(config.recents))
         .layer(Extension(handle))
-        .layer(Extension(sub_handle))
+        .layer(Extension(partitions))
         .layer(Extension(labels_handle))
-        .layer(Extension(history_handle));
+        .layer(Extension(history_handle))
+        .layer(Extension(index_handle))
+        .layer(Extension(covers_handle));
 
     let app = Router::new()
         .nest("/api", api);
@@ -1,19 +1,37 @@
 pub use action::CoverArtKind;
+pub use action::DbFilter;
+pub use action::DbFilterOperator;
+pub use action::DbGroupKind;
+pub use action::DbSort;
+pub use action::DbWindow;
+pub use action::PlaylistSaveMode;
+pub use action::QueueRange;
 pub use action::QueueSource;
+pub use client::Address;
 pub use client::connect;
+pub use data::Album;
+pub use data::CoverArt;
 pub use data::DbAudioFormat;
 pub use data::DbCount;
+pub use data::DbGroup;
 pub use data::DbItem;
 pub use data::DbTags;
 pub use data::OneshotState;
+pub use data::Output;
 pub use data::PlaybackState;
 pub use data::Playlist;
 pub use data::QueueItem;
 pub use data::QueueStatus;
 pub use data::SongStatus;
 pub use data::Status;
+pub use data::Sticker;
+pub use data::StickerMatch;
+pub use data::TagCount;
 pub use error::Error;
 pub use handle::Handle;
+pub use partition::Partition;
+pub use partition::PartitionRegistry;
+pub use partition::DEFAULT_PARTITION;
 pub use result::Result;
 pub use sub::Update;
 pub use sub::SubscriptionHandle;
@@ -27,3 +45,4 @@ mod service;
 mod result;
 mod error;
 mod sub;
+mod partition;
@@ -1,17 +1,25 @@
-use bytes::Bytes;
+use time::OffsetDateTime;
 use tokio::sync::oneshot;
 
+use crate::mpd::client::escape;
+use crate::mpd::data::CoverArt;
 use crate::mpd::data::QueueItem;
 use crate::mpd::data::Playlist;
 use crate::mpd::data::OneshotState;
+use crate::mpd::data::Output;
 use crate::mpd::data::DbItem;
 use crate::mpd::data::DbCount;
+use crate::mpd::data::DbGroup;
 use crate::mpd::data::Status;
+use crate::mpd::data::Album;
+use crate::mpd::data::TagCount;
+use crate::mpd::data::Sticker;
+use crate::mpd::data::StickerMatch;
 use crate::mpd::result::Result;
 
 type ResponseSender<T> = oneshot::Sender<Result<T>>;
 
-#[derive(Debug)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum CoverArtKind {
     File,
     Embedded,
@@ -23,23 +31,188 @@ pub enum QueueSource {
     Playlist { name: String },
 }
 
+#[derive(Debug)]
+pub struct QueueRange {
+    pub start: usize,
+    pub end: Option<usize>,
+}
+
+impl QueueRange {
+    pub fn to_range_string(&self) -> String {
+        match self.end {
+            Some(end) => format!("{}:{end}", self.start),
+            None => format!("{}:", self.start),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum DbFilterOperator {
+    Equal,
+    NotEqual,
+    Contains,
+    Regex,
+    NotRegex,
+}
+
+impl DbFilterOperator {
+    fn to_operator_string(&self) -> &'static str {
+        match self {
+            DbFilterOperator::Equal => "==",
+            DbFilterOperator::NotEqual => "!=",
+            DbFilterOperator::Contains => "contains",
+            DbFilterOperator::Regex => "=~",
+            DbFilterOperator::NotRegex => "!~",
+        }
+    }
+}
+
+// Tag names are not quoted in filter expressions so
+// they're expected to have been validated beforehand.
+#[derive(Debug, Clone)]
+pub enum DbFilter {
+    Tag { tag: String, operator: DbFilterOperator, value: String },
+    Not(Box<DbFilter>),
+    And(Vec<DbFilter>),
+    Base(String),
+    ModifiedSince(String),
+    AudioFormat { value: String, mask: bool },
+}
+
+impl DbFilter {
+    pub fn to_filter_string(&self) -> String {
+        match self {
+            DbFilter::Tag { tag, operator, value } => {
+                format!(r#"({tag} {} "{}")"#, operator.to_operator_string(), escape(value))
+            },
+            DbFilter::Not(filter) => {
+                format!("(!{})", filter.to_filter_string())
+            },
+            DbFilter::And(filters) if filters.len() == 1 => {
+                filters[0].to_filter_string()
+            },
+            DbFilter::And(filters) => {
+                let filters = filters.iter()
+                    .map(DbFilter::to_filter_string)
+                    .collect::<Vec<_>>()
+                    .join(" AND ");
+
+                format!("({filters})")
+            },
+            DbFilter::Base(uri) => {
+                format!(r#"(base "{}")"#, escape(uri))
+            },
+            DbFilter::ModifiedSince(since) => {
+                format!(r#"(modified-since "{}")"#, escape(since))
+            },
+            DbFilter::AudioFormat { value, mask } => {
+                let operator = if *mask { "=~" } else { "==" };
+
+                format!(r#"(AudioFormat {operator} "{}")"#, escape(value))
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DbSort {
+    pub tag: String,
+    pub descending: bool,
+}
+
+impl DbSort {
+    pub fn to_sort_string(&self) -> String {
+        if self.descending {
+            format!("-{}", self.tag)
+        } else {
+            self.tag.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DbWindow {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl DbWindow {
+    pub fn to_window_string(&self) -> String {
+        format!("{}:{}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum DbGroupKind {
+    Album,
+    Directory,
+}
+
+#[derive(Debug)]
+pub enum PlaylistSaveMode {
+    Create,
+    Append,
+    Replace,
+}
+
+impl PlaylistSaveMode {
+    // Plain `save` is used for creation so that
+    // servers older than 0.24 keep working.
+    pub fn to_mode_string(&self) -> Option<String> {
+        let result = match self {
+            PlaylistSaveMode::Create => return None,
+            PlaylistSaveMode::Append => "append",
+            PlaylistSaveMode::Replace => "replace",
+        };
+
+        Some(result.to_owned())
+    }
+}
+
 #[derive(Debug)]
 pub enum Action {
     // Database actions.
     DbGet {
         uri: String,
+        tags: Option<Vec<String>>,
         response_tx: ResponseSender<Vec<DbItem>>,
     },
     DbCount {
         uri: String,
         response_tx: ResponseSender<DbCount>,
     },
+    DbCountFilter {
+        filter: DbFilter,
+        response_tx: ResponseSender<DbCount>,
+    },
     DbSearch {
         query: String,
+        tags: Option<Vec<String>>,
+        response_tx: ResponseSender<Vec<DbItem>>,
+    },
+    DbFilter {
+        filter: DbFilter,
+        sort: Option<DbSort>,
+        window: Option<DbWindow>,
+        tags: Option<Vec<String>>,
         response_tx: ResponseSender<Vec<DbItem>>,
     },
     DbRecents {
-      response_tx: ResponseSender<Vec<DbItem>>,
+        since: OffsetDateTime,
+        window: Option<DbWindow>,
+        response_tx: ResponseSender<Vec<DbItem>>,
+    },
+    DbRecentsCount {
+        since: OffsetDateTime,
+        response_tx: ResponseSender<DbCount>,
+    },
+    DbRecentsGroups {
+        since: OffsetDateTime,
+        kind: DbGroupKind,
+        response_tx: ResponseSender<Vec<DbGroup>>,
+    },
+    DbTagtypes {
+        response_tx: ResponseSender<Vec<String>>,
     },
     DbUpdate {
         uri: Option<String>,
@@ -48,7 +221,22 @@ pub enum Action {
     DbCoverArt {
         uri: String,
         kind: CoverArtKind,
-        response_tx: ResponseSender<Bytes>,
+        response_tx: ResponseSender<CoverArt>,
+    },
+    // Library actions.
+    LibraryArtists {
+        response_tx: ResponseSender<Vec<TagCount>>,
+    },
+    LibraryAlbums {
+        artist: Option<String>,
+        genre: Option<String>,
+        response_tx: ResponseSender<Vec<Album>>,
+    },
+    LibraryGenres {
+        response_tx: ResponseSender<Vec<TagCount>>,
+    },
+    LibraryYears {
+        response_tx: ResponseSender<Vec<TagCount>>,
     },
     // Queue actions.
     QueueGet {
@@ -69,6 +257,43 @@ pub enum Action {
         id: i64,
         response_tx: ResponseSender<()>,
     },
+    QueueRemoveRange {
+        range: QueueRange,
+        response_tx: ResponseSender<()>,
+    },
+    QueueMove {
+        range: QueueRange,
+        to: usize,
+        response_tx: ResponseSender<()>,
+    },
+    QueueMoveId {
+        id: i64,
+        to: usize,
+        response_tx: ResponseSender<()>,
+    },
+    QueuePlayNext {
+        id: i64,
+        response_tx: ResponseSender<()>,
+    },
+    QueueSwap {
+        id: i64,
+        with: i64,
+        response_tx: ResponseSender<()>,
+    },
+    QueuePrio {
+        range: QueueRange,
+        priority: u8,
+        response_tx: ResponseSender<()>,
+    },
+    QueuePrioId {
+        ids: Vec<i64>,
+        priority: u8,
+        response_tx: ResponseSender<()>,
+    },
+    QueueShuffle {
+        range: Option<QueueRange>,
+        response_tx: ResponseSender<()>,
+    },
     QueueNext {
         response_tx: ResponseSender<()>,
     },
@@ -108,6 +333,32 @@ pub enum Action {
         positions: Vec<usize>,
         response_tx: ResponseSender<()>,
     },
+    PlaylistsSave {
+        name: String,
+        mode: PlaylistSaveMode,
+        response_tx: ResponseSender<()>,
+    },
+    PlaylistsAdd {
+        name: String,
+        uris: Vec<String>,
+        position: Option<usize>,
+        response_tx: ResponseSender<()>,
+    },
+    PlaylistsRename {
+        name: String,
+        new_name: String,
+        response_tx: ResponseSender<()>,
+    },
+    PlaylistsMove {
+        name: String,
+        from: usize,
+        to: usize,
+        response_tx: ResponseSender<()>,
+    },
+    PlaylistsClear {
+        name: String,
+        response_tx: ResponseSender<()>,
+    },
     // Playback actions.
     PlaybackPlay {
         id: Option<i64>,
@@ -132,4 +383,117 @@ pub enum Action {
         value: u8,
         response_tx: ResponseSender<()>,
     },
+    // Output actions.
+    OutputsList {
+        response_tx: ResponseSender<Vec<Output>>,
+    },
+    OutputsEnable {
+        id: i64,
+        response_tx: ResponseSender<()>,
+    },
+    OutputsDisable {
+        id: i64,
+        response_tx: ResponseSender<()>,
+    },
+    OutputsToggle {
+        id: i64,
+        response_tx: ResponseSender<()>,
+    },
+    OutputsSet {
+        id: i64,
+        name: String,
+        value: String,
+        response_tx: ResponseSender<()>,
+    },
+    // Partition actions.
+    PartitionsList {
+        response_tx: ResponseSender<Vec<String>>,
+    },
+    PartitionsCreate {
+        name: String,
+        response_tx: ResponseSender<()>,
+    },
+    PartitionsDelete {
+        name: String,
+        response_tx: ResponseSender<()>,
+    },
+    PartitionsMoveOutput {
+        name: String,
+        response_tx: ResponseSender<()>,
+    },
+    // Sticker actions.
+    StickersGet {
+        uri: String,
+        name: String,
+        response_tx: ResponseSender<Sticker>,
+    },
+    StickersSet {
+        uri: String,
+        name: String,
+        value: String,
+        response_tx: ResponseSender<()>,
+    },
+    StickersDelete {
+        uri: String,
+        name: Option<String>,
+        response_tx: ResponseSender<()>,
+    },
+    StickersList {
+        uri: String,
+        response_tx: ResponseSender<Vec<Sticker>>,
+    },
+    StickersFind {
+        uri: String,
+        name: String,
+        value: Option<String>,
+        response_tx: ResponseSender<Vec<StickerMatch>>,
+    },
+    // Connection actions.
+    Close {
+        response_tx: ResponseSender<()>,
+    },
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_construct_tag_filter() {
+        let expected = r#"(Artist == "foo \"bar\"")"#;
+
+        let actual = DbFilter::Tag {
+            tag: "Artist".to_owned(),
+            operator: DbFilterOperator::Equal,
+            value: r#"foo "bar""#.to_owned(),
+        }.to_filter_string();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_nested_filter() {
+        let expected = r#"((Genre contains "metal") AND (!(Date =~ "^19")) AND (AudioFormat =~ "*:24:*"))"#;
+
+        let actual = DbFilter::And(vec![
+            DbFilter::Tag {
+                tag: "Genre".to_owned(),
+                operator: DbFilterOperator::Contains,
+                value: "metal".to_owned(),
+            },
+            DbFilter::Not(Box::new(DbFilter::Tag {
+                tag: "Date".to_owned(),
+                operator: DbFilterOperator::Regex,
+                value: "^19".to_owned(),
+            })),
+            DbFilter::AudioFormat {
+                value: "*:24:*".to_owned(),
+                mask: true,
+            },
+        ]).to_filter_string();
+
+        assert_eq!(actual, expected);
+    }
 }
@@ -2,8 +2,9 @@ use std::collections::HashMap;
 use std::error;
 use std::fmt::Display;
 use std::fmt::Formatter;
-use std::num::ParseFloatError;
+use std::path::PathBuf;
 use std::result;
+use std::str::FromStr;
 
 use serde::de::DeserializeOwned;
 use serde::de::MapAccess;
@@ -12,12 +13,13 @@ use serde::Deserialize;
 use serde::Deserializer;
 use tokio::io;
 use tokio::net::TcpStream;
-use tokio::net::ToSocketAddrs;
+use tokio::net::UnixStream;
 
 pub use crate::mpd::client::ack::Ack;
 use crate::mpd::client::cmd::Command;
 use crate::mpd::client::conn::Connection;
 pub use crate::mpd::client::conn::Error as ConnectionError;
+use crate::mpd::client::conn::Stream;
 use crate::mpd::client::frame::Frame;
 pub use crate::mpd::client::cmd::escape;
 
@@ -28,7 +30,23 @@ mod frame;
 pub mod ack;
 
 pub struct Client {
-    connection: Connection,
+    connection: Connection<Box<dyn Stream>>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(test, derive(Eq, PartialEq))]
+pub enum Address {
+    Tcp { host: String, port: u32 },
+    Unix { path: PathBuf },
+}
+
+impl Display for Address {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Address::Tcp { host, port } => write!(f, "{host}:{port}"),
+            Address::Unix { path } => write!(f, "{}", path.display()),
+        }
+    }
 }
 
 pub struct CommandListClient {
@@ -48,6 +66,18 @@ pub enum Error {
 #[derive(Debug)]
 pub struct ConnectError {
     message: String,
+    // Set when MPD itself refused, e.g. to select a partition that doesn't exist.
+    permanent: bool,
+}
+
+impl ConnectError {
+    fn new(message: String) -> Self {
+        ConnectError { message, permanent: false }
+    }
+
+    pub fn is_permanent(&self) -> bool {
+        self.permanent
+    }
 }
 
 impl Display for ConnectError {
@@ -62,26 +92,25 @@ impl error::Error for ConnectError {
 
 impl From<Error> for ConnectError {
     fn from(err: Error) -> Self {
-        let message = match err {
-            Error::Ack(ack) => ack.message,
-            Error::Parse(msg) => msg,
-            Error::Connection(err) => err.to_string(),
-            Error::Deserialization(err) => err.to_string(),
-        };
-
-        ConnectError { message }
+        match err {
+            Error::Ack(ack) if ack.code == ack::code::NO_EXIST => ConnectError { message: ack.message, permanent: true },
+            Error::Ack(ack) => ConnectError::new(ack.message),
+            Error::Parse(msg) => ConnectError::new(msg),
+            Error::Connection(err) => ConnectError::new(err.to_string()),
+            Error::Deserialization(err) => ConnectError::new(err.to_string()),
+        }
     }
 }
 
 impl From<io::Error> for ConnectError {
     fn from(err: io::Error) -> Self {
-        ConnectError { message: err.to_string() }
+        ConnectError::new(err.to_string())
     }
 }
 
 impl From<conn::Error> for ConnectError {
     fn from(err: conn::Error) -> Self {
-        ConnectError { message: err.to_string() }
+        ConnectError::new(err.to_string())
     }
 }
 
@@ -131,6 +160,22 @@ impl CommandListClient {
     pub fn playlistdelete(self, name: String, songpos: usize) -> Self {
         self.push(Command::Playlistdelete { name, songpos })
     }
+
+    pub fn playlistadd(self, name: String, uri: String, position: Option<usize>) -> Self {
+        self.push(Command::Playlistadd { name, uri, position })
+    }
+
+    pub fn count(self, filter: String) -> Self {
+        self.push(Command::Count { filter })
+    }
+
+    pub fn tagtypes_clear(self) -> Self {
+        self.push(Command::TagtypesClear)
+    }
+
+    pub fn tagtypes_enable(self, tags: Vec<String>) -> Self {
+        self.push(Command::TagtypesEnable { tags })
+    }
 }
 
 impl CommandListClient {
@@ -160,6 +205,7 @@ pub struct Status {
     pub song: Option<i64>,
     pub songid: Option<i64>,
     pub playlistlength: usize,
+    pub partition: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -173,18 +219,88 @@ pub struct DbCount {
     pub playtime: i64,
 }
 
+// Grouped responses only repeat a group tag when its value changes,
+// so they are kept as ordered key-value pairs for the caller to fold.
+pub struct KeyValues(pub Vec<(String, String)>);
+
+impl<'de> Deserialize<'de> for KeyValues {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        struct KeyValuesVisitor;
+
+        impl<'de> Visitor<'de> for KeyValuesVisitor {
+            type Value = KeyValues;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                f.write_str("key-value pairs")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> result::Result<Self::Value, A::Error>
+                where A: MapAccess<'de>
+            {
+                let mut pairs = Vec::new();
+
+                while let Some(pair) = map.next_entry()? {
+                    pairs.push(pair);
+                }
+
+                Ok(KeyValues(pairs))
+            }
+        }
+
+        deserializer.deserialize_map(KeyValuesVisitor)
+    }
+}
+
+fn collect_entries<'de, A>(mut map: A) -> result::Result<HashMap<String, Vec<String>>, A::Error>
+    where A: MapAccess<'de>
+{
+    let mut data: HashMap<String, Vec<String>> = HashMap::new();
+
+    while let Some((key, value)) = map.next_entry()? {
+        data.entry(key)
+            .or_default()
+            .push(value);
+    }
+
+    Ok(data)
+}
+
+fn remove_first(data: &mut HashMap<String, Vec<String>>, key: &str) -> Option<String> {
+    data.remove(key).map(|mut xs| xs.swap_remove(0))
+}
+
+fn parse_first<T, E>(data: &mut HashMap<String, Vec<String>>, key: &str) -> result::Result<Option<T>, E>
+    where T: FromStr, T::Err: Display, E: serde::de::Error
+{
+    remove_first(data, key)
+        .map(|it| it.parse::<T>().map_err(|e| E::custom(format!("cannot parse {key}: {e}"))))
+        .transpose()
+}
+
+// Attributes that MPD reports alongside tags without being tags.
+const SONG_ATTRIBUTES: &[&str] = &["Time", "Range", "Added", "Last-Modified", "Format", "duration"];
+
+fn into_tags(mut data: HashMap<String, Vec<String>>) -> HashMap<String, Vec<String>> {
+    for attribute in SONG_ATTRIBUTES {
+        data.remove(*attribute);
+    }
+
+    data
+}
+
 pub enum DbItem {
     File {
         file: String,
         duration: Option<f64>,
-        title: Vec<String>,
-        artist: Vec<String>,
-        album: Vec<String>,
         format: Option<String>,
         last_modified: Option<String>,
+        tags: HashMap<String, Vec<String>>,
     },
     Directory {
         directory: String,
+        last_modified: Option<String>,
     },
     Playlist {
         playlist: String,
@@ -204,47 +320,29 @@ impl<'de> Deserialize<'de> for DbItem {
                 f.write_str("enum DbItem")
             }
 
-            fn visit_map<A>(self, mut map: A) -> result::Result<Self::Value, A::Error>
+            fn visit_map<A>(self, map: A) -> result::Result<Self::Value, A::Error>
                 where A: MapAccess<'de>
             {
-                let mut data: HashMap<String, Vec<String>> = HashMap::new();
-
-                while let Some((key, value)) = map.next_entry()? {
-                    data.entry(key)
-                        .or_default()
-                        .push(value);
-                }
-
-                let first = |mut xs: Vec<String>| xs.swap_remove(0);
+                let mut data = collect_entries(map)?;
 
                 let value = if data.contains_key("file") {
                     DbItem::File {
-                        file: data.remove("file")
-                            .map(first)
+                        file: remove_first(&mut data, "file")
                             .ok_or_else(|| serde::de::Error::missing_field("file"))?,
-                        duration: data.remove("duration")
-                            .map(first)
-                            .map(|it| {
-                                it.parse::<f64>()
-                                    .map_err(|e: ParseFloatError| serde::de::Error::custom(e.to_string()))
-                            })
-                            .transpose()?,
-                        title: data.remove("Title").unwrap_or_default(),
-                        artist: data.remove("Artist").unwrap_or_default(),
-                        album: data.remove("Album").unwrap_or_default(),
-                        format: data.remove("Format").map(first),
-                        last_modified: data.remove("Last-Modified").map(first)
+                        duration: parse_first(&mut data, "duration")?,
+                        format: remove_first(&mut data, "Format"),
+                        last_modified: remove_first(&mut data, "Last-Modified"),
+                        tags: into_tags(data),
                     }
                 } else if data.contains_key("directory") {
                     DbItem::Directory {
-                        directory: data.remove("directory")
-                            .map(first)
+                        directory: remove_first(&mut data, "directory")
                             .ok_or_else(|| serde::de::Error::missing_field("directory"))?,
+                        last_modified: remove_first(&mut data, "Last-Modified"),
                     }
                 } else {
                     DbItem::Playlist {
-                        playlist: data.remove("playlist")
-                            .map(first)
+                        playlist: remove_first(&mut data, "playlist")
                             .ok_or_else(|| serde::de::Error::missing_field("playlist"))?,
                     }
                 };
@@ -257,22 +355,52 @@ impl<'de> Deserialize<'de> for DbItem {
     }
 }
 
-#[derive(Deserialize)]
 pub struct PlaylistItem {
-    #[serde(rename(deserialize = "Id"))]
     pub id: i64,
-    #[serde(rename(deserialize = "Pos"))]
     pub pos: i64,
     pub file: String,
     pub duration: f64,
-    #[serde(default, rename(deserialize = "Title"))]
-    pub title: Vec<String>,
-    #[serde(default, rename(deserialize = "Artist"))]
-    pub artist: Vec<String>,
-    #[serde(default, rename(deserialize = "Album"))]
-    pub album: Vec<String>,
-    #[serde(rename(deserialize = "Format"))]
     pub format: Option<String>,
+    pub prio: Option<u8>,
+    pub tags: HashMap<String, Vec<String>>,
+}
+
+impl<'de> Deserialize<'de> for PlaylistItem {
+    fn deserialize<D>(deserializer: D) -> result::Result<Self, D::Error>
+        where D: Deserializer<'de>
+    {
+        struct PlaylistItemVisitor;
+
+        impl<'de> Visitor<'de> for PlaylistItemVisitor {
+            type Value = PlaylistItem;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                f.write_str("struct PlaylistItem")
+            }
+
+            fn visit_map<A>(self, map: A) -> result::Result<Self::Value, A::Error>
+                where A: MapAccess<'de>
+            {
+                use serde::de::Error;
+
+                let mut data = collect_entries(map)?;
+
+                let value = PlaylistItem {
+                    id: parse_first(&mut data, "Id")?.ok_or_else(|| A::Error::missing_field("Id"))?,
+                    pos: parse_first(&mut data, "Pos")?.ok_or_else(|| A::Error::missing_field("Pos"))?,
+                    file: remove_first(&mut data, "file").ok_or_else(|| A::Error::missing_field("file"))?,
+                    duration: parse_first(&mut data, "duration")?.ok_or_else(|| A::Error::missing_field("duration"))?,
+                    format: remove_first(&mut data, "Format"),
+                    prio: parse_first(&mut data, "Prio")?,
+                    tags: into_tags(data),
+                };
+
+                Ok(value)
+            }
+        }
+
+        deserializer.deserialize_map(PlaylistItemVisitor)
+    }
 }
 
 #[derive(Deserialize)]
@@ -282,10 +410,44 @@ pub struct Playlist {
     pub last_modified: String,
 }
 
+#[derive(Deserialize)]
+pub struct Output {
+    pub outputid: i64,
+    pub outputname: String,
+    pub plugin: Option<String>,
+    pub outputenabled: bool,
+    #[serde(default)]
+    pub attribute: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct Partition {
+    pub partition: String,
+}
+
+#[derive(Deserialize)]
+pub struct Sticker {
+    pub sticker: String,
+}
+
+#[derive(Deserialize)]
+pub struct StickerMatch {
+    pub file: String,
+    pub sticker: String,
+}
+
+#[derive(Deserialize)]
+pub struct Tagtype {
+    pub tagtype: String,
+}
+
 #[derive(Deserialize)]
 pub struct BinaryInfo {
     pub size: usize,
     pub binary: usize,
+    // Only readpicture reports it and only if the file says what it is.
+    #[serde(rename = "type")]
+    pub mime_type: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -295,13 +457,20 @@ pub struct Binary(
     pub Vec<u8>,
 );
 
-pub async fn connect<T: ToSocketAddrs>(addr: T) -> result::Result<Client, ConnectError> {
-    let stream = TcpStream::connect(addr).await?;
+pub async fn connect(address: &Address) -> result::Result<Client, ConnectError> {
+    let stream: Box<dyn Stream> = match address {
+        Address::Tcp { host, port } => {
+            Box::new(TcpStream::connect(format!("{host}:{port}")).await?)
+        },
+        Address::Unix { path } => {
+            Box::new(UnixStream::connect(path).await?)
+        },
+    };
 
     let mut connection = Connection::new(stream);
 
     let Frame::Ver(_version) = connection.read_frame().await? else {
-        return Err(ConnectError { message: "unexpected frame".to_owned() });
+        return Err(ConnectError::new("unexpected frame".to_owned()));
     };
 
     Ok(Client { connection })
@@ -331,18 +500,31 @@ impl Client {
         noidle() -> Result<Vec<Change>> = Command::Noidle;
         clear() -> Result<()> = Command::Clear;
         deleteid(songid: i64) -> Result<()> = Command::Deleteid;
+        delete(range: String) -> Result<()> = Command::Delete;
+        r#move(from: String, to: String) -> Result<()> = Command::Move;
+        moveid(songid: i64, to: String) -> Result<()> = Command::Moveid;
+        swapid(songid1: i64, songid2: i64) -> Result<()> = Command::Swapid;
+        prio(priority: u8, range: String) -> Result<()> = Command::Prio;
+        prioid(priority: u8, songids: Vec<i64>) -> Result<()> = Command::Prioid;
+        shuffle(range: Option<String>) -> Result<()> = Command::Shuffle;
         playid(song_id: Option<i64>) -> Result<()> = Command::Playid;
         pause() -> Result<()> = Command::Pause;
         stop() -> Result<()> = Command::Stop;
         next() -> Result<()> = Command::Next;
         previous() -> Result<()> = Command::Previous;
         count(filter: String) -> Result<DbCount> = Command::Count;
+        count_group(filter: Option<String>, group: String) -> Result<Vec<KeyValues>> = Command::CountGroup;
+        list(tag: String, filter: Option<String>, groups: Vec<String>) -> Result<Vec<KeyValues>> = Command::List;
         lsinfo(uri: String) -> Result<Vec<DbItem>> = Command::Lsinfo;
-        search(filter: String, sort: Option<String>) -> Result<Vec<DbItem>> = Command::Search;
+        search(filter: String, sort: Option<String>, window: Option<String>) -> Result<Vec<DbItem>> = Command::Search;
         playlistinfo() -> Result<Vec<PlaylistItem>> = Command::Playlistinfo;
         listplaylists() -> Result<Vec<Playlist>> = Command::Listplaylists;
         listplaylistinfo(name: String) -> Result<Vec<DbItem>> = Command::Listplaylistinfo;
         rm(name: String) -> Result<()> = Command::Rm;
+        save(name: String, mode: Option<String>) -> Result<()> = Command::Save;
+        playlistmove(name: String, from: usize, to: usize) -> Result<()> = Command::Playlistmove;
+        playlistclear(name: String) -> Result<()> = Command::Playlistclear;
+        rename(name: String, new_name: String) -> Result<()> = Command::Rename;
         status() -> Result<Status> = Command::Status;
         password(str: String) -> Result<()> = Command::Password;
         update(uri: Option<String>) -> Result<()> = Command::Update;
@@ -354,6 +536,23 @@ impl Client {
         consume(state: String) -> Result<()> = Command::Consume;
         random(state: String) -> Result<()> = Command::Random;
         single(state: String) -> Result<()> = Command::Single;
+        outputs() -> Result<Vec<Output>> = Command::Outputs;
+        enableoutput(id: i64) -> Result<()> = Command::Enableoutput;
+        disableoutput(id: i64) -> Result<()> = Command::Disableoutput;
+        toggleoutput(id: i64) -> Result<()> = Command::Toggleoutput;
+        outputset(id: i64, name: String, value: String) -> Result<()> = Command::Outputset;
+        partition(name: String) -> Result<()> = Command::Partition;
+        listpartitions() -> Result<Vec<Partition>> = Command::Listpartitions;
+        newpartition(name: String) -> Result<()> = Command::Newpartition;
+        delpartition(name: String) -> Result<()> = Command::Delpartition;
+        moveoutput(name: String) -> Result<()> = Command::Moveoutput;
+        sticker_get(kind: String, uri: String, name: String) -> Result<Sticker> = Command::StickerGet;
+        sticker_set(kind: String, uri: String, name: String, value: String) -> Result<()> = Command::StickerSet;
+        sticker_delete(kind: String, uri: String, name: Option<String>) -> Result<()> = Command::StickerDelete;
+        sticker_list(kind: String, uri: String) -> Result<Vec<Sticker>> = Command::StickerList;
+        sticker_find(kind: String, uri: String, name: String, value: Option<String>) -> Result<Vec<StickerMatch>> = Command::StickerFind;
+        tagtypes() -> Result<Vec<Tagtype>> = Command::Tagtypes;
+        tagtypes_all() -> Result<()> = Command::TagtypesAll;
     }
 
     pub async fn idle<F: FnMut()>(&mut self, subsystems: Vec<String>, mut on_idle: F) -> Result<Vec<Change>> {
@@ -368,6 +567,13 @@ impl Client {
 
     pub async fn command_list<F>(&mut self, builder: F) -> Result<()>
         where F: FnOnce(CommandListClient) -> CommandListClient
+    {
+        self.command_list_with_response(builder).await
+    }
+
+    // Responses of the individual commands are concatenated.
+    pub async fn command_list_with_response<T, F>(&mut self, builder: F) -> Result<T>
+        where T: DeserializeOwned, F: FnOnce(CommandListClient) -> CommandListClient
     {
         let cmd = builder(CommandListClient::new())
             .into_command()
@@ -404,3 +610,53 @@ impl Client {
         }
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_deserialize_playlist_item_tags() {
+        let input = b"file: a.flac\nArtist: Alfa\nArtist: Beta\nMUSICBRAINZ_TRACKID: 42\nTime: 25\nduration: 25.005\nFormat: 44100:16:2\nPos: 0\nId: 7\n";
+
+        let result: Vec<PlaylistItem> = de::from_bytes(input).unwrap();
+
+        let item = &result[0];
+
+        assert_eq!(item.id, 7);
+        assert_eq!(item.pos, 0);
+        assert_eq!(item.file, "a.flac");
+        assert_eq!(item.prio, None);
+        assert_eq!(item.format.as_deref(), Some("44100:16:2"));
+        assert_eq!(item.tags, HashMap::from([
+            ("Artist".to_owned(), vec!["Alfa".to_owned(), "Beta".to_owned()]),
+            ("MUSICBRAINZ_TRACKID".to_owned(), vec!["42".to_owned()]),
+        ]));
+    }
+
+    #[test]
+    fn should_not_retry_missing_partition() {
+        let ack = Ack {
+            code: ack::code::NO_EXIST,
+            message: "partition does not exist".to_owned(),
+            command: Some("partition".to_owned()),
+            command_index: 0,
+        };
+
+        assert!(ConnectError::from(Error::Ack(ack)).is_permanent());
+        assert!(!ConnectError::new("connection refused".to_owned()).is_permanent());
+    }
+
+    #[test]
+    fn should_deserialize_binary_type() {
+        let input = [&b"size: 25\ntype: image/jpeg\nbinary: 3\n"[..], &[0xFF, 0xD8, 0xFF], b"\n"].concat();
+
+        let Binary(info, data) = de::from_bytes(&input).unwrap();
+
+        assert_eq!(info.size, 25);
+        assert_eq!(info.mime_type.as_deref(), Some("image/jpeg"));
+        assert_eq!(data, vec![0xFF, 0xD8, 0xFF]);
+    }
+}
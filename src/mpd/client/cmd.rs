@@ -10,14 +10,28 @@ pub enum Command {
     Noidle,
     Clear,
     Deleteid { songid: i64 },
+    Delete { range: String },
+    Move { from: String, to: String },
+    Moveid { songid: i64, to: String },
+    Swapid { songid1: i64, songid2: i64 },
+    Prio { priority: u8, range: String },
+    Prioid { priority: u8, songids: Vec<i64> },
+    Shuffle { range: Option<String> },
     Count { filter: String },
+    CountGroup { filter: Option<String>, group: String },
+    List { tag: String, filter: Option<String>, groups: Vec<String> },
     Lsinfo { uri: String },
-    Search { filter: String, sort: Option<String> },
+    Search { filter: String, sort: Option<String>, window: Option<String> },
     Playlistinfo,
     Listplaylistinfo { name: String },
     Rm { name: String },
     Playlistdelete { name: String, songpos: usize, },
     Listplaylists,
+    Save { name: String, mode: Option<String> },
+    Playlistadd { name: String, uri: String, position: Option<usize> },
+    Playlistmove { name: String, from: usize, to: usize },
+    Playlistclear { name: String },
+    Rename { name: String, new_name: String },
     Status,
     Password { str: String },
     CommandList(Vec<Command>),
@@ -30,6 +44,25 @@ pub enum Command {
     Consume { state: String },
     Random { state: String },
     Single { state: String },
+    Outputs,
+    Enableoutput { id: i64 },
+    Disableoutput { id: i64 },
+    Toggleoutput { id: i64 },
+    Outputset { id: i64, name: String, value: String },
+    Partition { name: String },
+    Listpartitions,
+    Newpartition { name: String },
+    Delpartition { name: String },
+    Moveoutput { name: String },
+    StickerGet { kind: String, uri: String, name: String },
+    StickerSet { kind: String, uri: String, name: String, value: String },
+    StickerDelete { kind: String, uri: String, name: Option<String> },
+    StickerList { kind: String, uri: String },
+    StickerFind { kind: String, uri: String, name: String, value: Option<String> },
+    Tagtypes,
+    TagtypesClear,
+    TagtypesAll,
+    TagtypesEnable { tags: Vec<String> },
 }
 
 trait ToStringOrEmpty<T: ToString> {
@@ -75,8 +108,17 @@ impl Command {
     const NOIDLE_VALUE: &'static str = "noidle";
     const CLEAR_VALUE: &'static str = "clear";
     const DELETEID_VALUE: &'static str = "deleteid";
+    const DELETE_VALUE: &'static str = "delete";
+    const MOVE_VALUE: &'static str = "move";
+    const MOVEID_VALUE: &'static str = "moveid";
+    const SWAPID_VALUE: &'static str = "swapid";
+    const PRIO_VALUE: &'static str = "prio";
+    const PRIOID_VALUE: &'static str = "prioid";
+    const SHUFFLE_VALUE: &'static str = "shuffle";
     const COUNT_VALUE: &'static str = "count";
     const LSINFO_VALUE: &'static str = "lsinfo";
+    const LIST_VALUE: &'static str = "list";
+    const GROUP_VALUE: &'static str = "group";
     const SEARCH_VALUE: &'static str = "search";
     const STATUS_VALUE: &'static str = "status";
     const PASSWORD_VALUE: &'static str = "password";
@@ -85,6 +127,11 @@ impl Command {
     const LISTPLAYLISTS_VALUE: &'static str = "listplaylists";
     const RM_VALUE: &'static str = "rm";
     const PLAYLISTDELETE_VALUE: &'static str = "playlistdelete";
+    const SAVE_VALUE: &'static str = "save";
+    const PLAYLISTADD_VALUE: &'static str = "playlistadd";
+    const PLAYLISTMOVE_VALUE: &'static str = "playlistmove";
+    const PLAYLISTCLEAR_VALUE: &'static str = "playlistclear";
+    const RENAME_VALUE: &'static str = "rename";
     const UPDATE_VALUE: &'static str = "update";
     const SEEKCUR_VALUE: &'static str = "seekcur";
     const SETVOL_VALUE: &'static str = "setvol";
@@ -94,6 +141,25 @@ impl Command {
     const CONSUME_VALUE: &'static str = "consume";
     const RANDOM_VALUE: &'static str = "random";
     const SINGLE_VALUE: &'static str = "single";
+    const OUTPUTS_VALUE: &'static str = "outputs";
+    const ENABLEOUTPUT_VALUE: &'static str = "enableoutput";
+    const DISABLEOUTPUT_VALUE: &'static str = "disableoutput";
+    const TOGGLEOUTPUT_VALUE: &'static str = "toggleoutput";
+    const OUTPUTSET_VALUE: &'static str = "outputset";
+    const PARTITION_VALUE: &'static str = "partition";
+    const LISTPARTITIONS_VALUE: &'static str = "listpartitions";
+    const NEWPARTITION_VALUE: &'static str = "newpartition";
+    const DELPARTITION_VALUE: &'static str = "delpartition";
+    const MOVEOUTPUT_VALUE: &'static str = "moveoutput";
+    const STICKER_GET_VALUE: &'static str = "sticker get";
+    const STICKER_SET_VALUE: &'static str = "sticker set";
+    const STICKER_DELETE_VALUE: &'static str = "sticker delete";
+    const STICKER_LIST_VALUE: &'static str = "sticker list";
+    const STICKER_FIND_VALUE: &'static str = "sticker find";
+    const TAGTYPES_VALUE: &'static str = "tagtypes";
+    const TAGTYPES_CLEAR_VALUE: &'static str = "tagtypes clear";
+    const TAGTYPES_ALL_VALUE: &'static str = "tagtypes all";
+    const TAGTYPES_ENABLE_VALUE: &'static str = "tagtypes enable";
     const COMMAND_LIST_BEGIN_VALUE: &'static str = "command_list_begin";
     const COMMAND_LIST_END_VALUE: &'static str = "command_list_end";
 
@@ -139,16 +205,63 @@ impl Command {
             Deleteid { songid } => {
                 format!("{} {songid}", Command::DELETEID_VALUE)
             }
+            Delete { range } => {
+                format!("{} {}", Command::DELETE_VALUE, quote(&range))
+            }
+            Move { from, to } => {
+                format!("{} {} {}", Command::MOVE_VALUE, quote(&from), quote(&to))
+            }
+            Moveid { songid, to } => {
+                format!("{} {songid} {}", Command::MOVEID_VALUE, quote(&to))
+            }
+            Swapid { songid1, songid2 } => {
+                format!("{} {songid1} {songid2}", Command::SWAPID_VALUE)
+            }
+            Prio { priority, range } => {
+                format!("{} {priority} {}", Command::PRIO_VALUE, quote(&range))
+            }
+            Prioid { priority, songids } => {
+                let songids = songids.iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                format!("{} {priority} {songids}", Command::PRIOID_VALUE)
+            }
+            Shuffle { range } => {
+                if let Some(range) = range {
+                    format!("{} {}", Command::SHUFFLE_VALUE, quote(&range))
+                } else {
+                    Command::SHUFFLE_VALUE.to_owned()
+                }
+            }
             Count { filter } => {
                 format!("{} {}", Command::COUNT_VALUE, quote(&filter))
             }
+            CountGroup { filter, group } => {
+                let filter = filter.map(|f| format!("{} ", quote(&f))).to_string_or_empty();
+
+                format!("{} {filter}{} {}", Command::COUNT_VALUE, Command::GROUP_VALUE, quote(&group))
+            }
+            List { tag, filter, groups } => {
+                let filter = filter.map(|f| format!(" {}", quote(&f))).to_string_or_empty();
+
+                let groups = groups.iter()
+                    .map(|g| format!(" {} {}", Command::GROUP_VALUE, quote(g)))
+                    .collect::<String>();
+
+                format!("{} {}{filter}{groups}", Command::LIST_VALUE, quote(&tag))
+            }
             Lsinfo { uri } => {
                 format!("{} {}", Command::LSINFO_VALUE, quote(&uri))
             }
-            Search { filter, sort } => {
+            Search { filter, sort, window } => {
                 let sort = sort.map(|s| format!("sort {s}")).to_string_or_empty();
+                let window = window.map(|w| format!("window {w}")).to_string_or_empty();
 
-                format!("{} {} {}", Command::SEARCH_VALUE, quote(&filter), sort)
+                format!("{} {} {} {}", Command::SEARCH_VALUE, quote(&filter), sort, window)
+                    .trim_end()
+                    .to_owned()
             }
             Status => {
                 Command::STATUS_VALUE.to_owned()
@@ -171,6 +284,29 @@ impl Command {
             Playlistdelete { name, songpos } => {
                 format!("{} {} {songpos}", Command::PLAYLISTDELETE_VALUE, quote(&name))
             }
+            Save { name, mode } => {
+                if let Some(mode) = mode {
+                    format!("{} {} {}", Command::SAVE_VALUE, quote(&name), quote(&mode))
+                } else {
+                    format!("{} {}", Command::SAVE_VALUE, quote(&name))
+                }
+            }
+            Playlistadd { name, uri, position } => {
+                if let Some(position) = position {
+                    format!("{} {} {} {position}", Command::PLAYLISTADD_VALUE, quote(&name), quote(&uri))
+                } else {
+                    format!("{} {} {}", Command::PLAYLISTADD_VALUE, quote(&name), quote(&uri))
+                }
+            }
+            Playlistmove { name, from, to } => {
+                format!("{} {} {from} {to}", Command::PLAYLISTMOVE_VALUE, quote(&name))
+            }
+            Playlistclear { name } => {
+                format!("{} {}", Command::PLAYLISTCLEAR_VALUE, quote(&name))
+            }
+            Rename { name, new_name } => {
+                format!("{} {} {}", Command::RENAME_VALUE, quote(&name), quote(&new_name))
+            }
             Update { uri } => {
                 if let Some(uri) = uri {
                     format!("{} {}", Command::UPDATE_VALUE, quote(&uri))
@@ -202,6 +338,90 @@ impl Command {
             Single { state } => {
                 format!("{} {}", Command::SINGLE_VALUE, quote(&state))
             }
+            Outputs => {
+                Command::OUTPUTS_VALUE.to_owned()
+            }
+            Enableoutput { id } => {
+                format!("{} {id}", Command::ENABLEOUTPUT_VALUE)
+            }
+            Disableoutput { id } => {
+                format!("{} {id}", Command::DISABLEOUTPUT_VALUE)
+            }
+            Toggleoutput { id } => {
+                format!("{} {id}", Command::TOGGLEOUTPUT_VALUE)
+            }
+            Outputset { id, name, value } => {
+                format!("{} {id} {} {}", Command::OUTPUTSET_VALUE, quote(&name), quote(&value))
+            }
+            Partition { name } => {
+                format!("{} {}", Command::PARTITION_VALUE, quote(&name))
+            }
+            Listpartitions => {
+                Command::LISTPARTITIONS_VALUE.to_owned()
+            }
+            Newpartition { name } => {
+                format!("{} {}", Command::NEWPARTITION_VALUE, quote(&name))
+            }
+            Delpartition { name } => {
+                format!("{} {}", Command::DELPARTITION_VALUE, quote(&name))
+            }
+            Moveoutput { name } => {
+                format!("{} {}", Command::MOVEOUTPUT_VALUE, quote(&name))
+            }
+            StickerGet { kind, uri, name } => {
+                format!("{} {} {} {}", Command::STICKER_GET_VALUE, quote(&kind), quote(&uri), quote(&name))
+            }
+            StickerSet { kind, uri, name, value } => {
+                format!(
+                    "{} {} {} {} {}",
+                    Command::STICKER_SET_VALUE,
+                    quote(&kind),
+                    quote(&uri),
+                    quote(&name),
+                    quote(&value),
+                )
+            }
+            StickerDelete { kind, uri, name } => {
+                if let Some(name) = name {
+                    format!("{} {} {} {}", Command::STICKER_DELETE_VALUE, quote(&kind), quote(&uri), quote(&name))
+                } else {
+                    format!("{} {} {}", Command::STICKER_DELETE_VALUE, quote(&kind), quote(&uri))
+                }
+            }
+            StickerList { kind, uri } => {
+                format!("{} {} {}", Command::STICKER_LIST_VALUE, quote(&kind), quote(&uri))
+            }
+            StickerFind { kind, uri, name, value } => {
+                if let Some(value) = value {
+                    format!(
+                        "{} {} {} {} = {}",
+                        Command::STICKER_FIND_VALUE,
+                        quote(&kind),
+                        quote(&uri),
+                        quote(&name),
+                        quote(&value),
+                    )
+                } else {
+                    format!("{} {} {} {}", Command::STICKER_FIND_VALUE, quote(&kind), quote(&uri), quote(&name))
+                }
+            }
+            Tagtypes => {
+                Command::TAGTYPES_VALUE.to_owned()
+            }
+            TagtypesClear => {
+                Command::TAGTYPES_CLEAR_VALUE.to_owned()
+            }
+            TagtypesAll => {
+                Command::TAGTYPES_ALL_VALUE.to_owned()
+            }
+            TagtypesEnable { tags } => {
+                let tags = tags.iter()
+                    .map(|it| quote(it))
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                format!("{} {tags}", Command::TAGTYPES_ENABLE_VALUE)
+            }
             CommandList(xs) => {
                 format!(
                     "{}\n{}{}",
@@ -263,6 +483,107 @@ mod tests {
         let actual = Command::Search {
             filter: r#"(Artist == "foo\'bar\"")"#.to_owned(),
             sort: Some("Last-Modified".to_owned()),
+            window: None,
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_search_with_window() {
+        let expected = r#"search "(Album == \"Foo\")" sort -Date window 0:50"#;
+
+        let actual = Command::Search {
+            filter: r#"(Album == "Foo")"#.to_owned(),
+            sort: Some("-Date".to_owned()),
+            window: Some("0:50".to_owned()),
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_list_with_groups() {
+        let expected = r#"list "Album" "(Genre == \"Jazz\")" group "AlbumArtist" group "Date""#;
+
+        let actual = Command::List {
+            tag: "Album".to_owned(),
+            filter: Some(r#"(Genre == "Jazz")"#.to_owned()),
+            groups: vec!["AlbumArtist".to_owned(), "Date".to_owned()],
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_count_group() {
+        let expected = r#"count group "Genre""#;
+
+        let actual = Command::CountGroup {
+            filter: None,
+            group: "Genre".to_owned(),
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_tagtypes_enable() {
+        let expected = r#"tagtypes enable "Artist" "MUSICBRAINZ_TRACKID""#;
+
+        let actual = Command::TagtypesEnable {
+            tags: vec!["Artist".to_owned(), "MUSICBRAINZ_TRACKID".to_owned()],
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_prioid() {
+        let expected = "prioid 255 3 5 8";
+
+        let actual = Command::Prioid {
+            priority: 255,
+            songids: vec![3, 5, 8],
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_relative_moveid() {
+        let expected = r#"moveid 42 "+0""#;
+
+        let actual = Command::Moveid {
+            songid: 42,
+            to: "+0".to_owned(),
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_sticker_find_with_value() {
+        let expected = r#"sticker find "song" "metal" "rating" = "10""#;
+
+        let actual = Command::StickerFind {
+            kind: "song".to_owned(),
+            uri: "metal".to_owned(),
+            name: "rating".to_owned(),
+            value: Some("10".to_owned()),
+        }.into_prepared();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_construct_playlistadd_at_position() {
+        let expected = r#"playlistadd "Road \"Trip\"" "test/song.flac" 3"#;
+
+        let actual = Command::Playlistadd {
+            name: r#"Road "Trip""#.to_owned(),
+            uri: "test/song.flac".to_owned(),
+            position: Some(3),
         }.into_prepared();
 
         assert_eq!(actual, expected);
@@ -7,16 +7,25 @@ use std::result;
 use bytes::Buf;
 use bytes::BytesMut;
 use tokio::io;
+use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
+use tokio::io::AsyncWrite;
 use tokio::io::AsyncWriteExt;
-use tokio::net::TcpStream;
 
 use crate::mpd::client::frame;
 use crate::mpd::client::frame::Frame;
 
-pub struct Connection {
+pub trait Stream: AsyncRead + AsyncWrite + Send + Sync + Unpin {
+    // marker
+}
+
+impl<T: AsyncRead + AsyncWrite + Send + Sync + Unpin> Stream for T {
+    // default
+}
+
+pub struct Connection<S> {
     buffer: BytesMut,
-    stream: TcpStream,
+    stream: S,
 }
 
 #[derive(Debug)]
@@ -50,18 +59,18 @@ impl From<io::Error> for Error {
     }
 }
 
-impl Connection {
+impl<S> Connection<S> {
     const BUF_CAPACITY: usize = 2048;
 
-    pub fn new(stream: TcpStream) -> Connection {
+    pub fn new(stream: S) -> Connection<S> {
         Connection {
             stream,
-            buffer: BytesMut::with_capacity(Connection::BUF_CAPACITY),
+            buffer: BytesMut::with_capacity(Self::BUF_CAPACITY),
         }
     }
 }
 
-impl Connection {
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
     pub async fn read_frame(&mut self) -> Result<Frame> {
         loop {
             if let Some(frame) = self.parse_frame()? {
@@ -1,8 +1,12 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 use std::str::FromStr;
 
+use bytes::Bytes;
 use time::Duration;
 
 use crate::convert::IntoResult;
+use crate::mpd::action::DbGroupKind;
 use crate::mpd::client;
 use crate::mpd::Error;
 
@@ -91,6 +95,7 @@ pub struct Status {
     pub consume: OneshotState,
     pub song: Option<SongStatus>,
     pub queue: QueueStatus,
+    pub partition: Option<String>,
 }
 
 fn to_bool(value: i8) -> Result<bool, String> {
@@ -128,6 +133,7 @@ impl TryFrom<client::Status> for Status {
             queue: QueueStatus {
                 length: status.playlistlength,
             },
+            partition: status.partition,
         };
 
         Ok(status)
@@ -142,6 +148,8 @@ pub enum Subsystem {
     Player,
     Volume,
     Options,
+    Output,
+    Sticker,
 }
 
 impl Subsystem {
@@ -151,6 +159,8 @@ impl Subsystem {
     const VOLUME_VALUE: &'static str = "mixer";
     const PLAYER_VALUE: &'static str = "player";
     const OPTIONS_VALUE: &'static str = "options";
+    const OUTPUT_VALUE: &'static str = "output";
+    const STICKER_VALUE: &'static str = "sticker";
 }
 
 impl TryFrom<&str> for Subsystem {
@@ -166,6 +176,8 @@ impl TryFrom<&str> for Subsystem {
             Subsystem::PLAYER_VALUE => Player,
             Subsystem::VOLUME_VALUE => Volume,
             Subsystem::OPTIONS_VALUE => Options,
+            Subsystem::OUTPUT_VALUE => Output,
+            Subsystem::STICKER_VALUE => Sticker,
             _ => return Err(format!("unknown subsystem '{s}'"))
         };
 
@@ -184,6 +196,8 @@ impl From<Subsystem> for String {
             Player => Subsystem::PLAYER_VALUE,
             Volume => Subsystem::VOLUME_VALUE,
             Options => Subsystem::OPTIONS_VALUE,
+            Output => Subsystem::OUTPUT_VALUE,
+            Sticker => Subsystem::STICKER_VALUE,
         };
 
         value.to_owned()
@@ -196,6 +210,12 @@ pub fn to_subsystems(changes: Vec<client::Change>) -> Result<Vec<Subsystem>, Str
         .collect()
 }
 
+#[derive(Debug)]
+pub struct CoverArt {
+    pub data: Bytes,
+    pub content_type: String,
+}
+
 #[derive(Debug)]
 pub struct DbCount {
     pub nsongs: i64,
@@ -211,6 +231,53 @@ impl From<client::DbCount> for DbCount {
     }
 }
 
+#[derive(Debug)]
+pub struct TagCount {
+    pub value: String,
+    pub count: DbCount,
+}
+
+impl TryFrom<client::KeyValues> for TagCount {
+    type Error = Error;
+
+    // The group tag is the only key besides the counts.
+    fn try_from(client::KeyValues(pairs): client::KeyValues) -> Result<Self, Self::Error> {
+        let mut value = None;
+        let mut songs = None;
+        let mut playtime = None;
+
+        for (key, it) in pairs {
+            match key.as_str() {
+                "songs" => songs = Some(it),
+                "playtime" => playtime = Some(it),
+                _ => value = Some(it),
+            }
+        }
+
+        let parse = |name: &str, it: Option<String>| -> Result<i64, Error> {
+            it.ok_or_else(|| Error::Internal(format!("{name} is missing from group count")))?
+                .parse()
+                .map_err(|e| Error::Internal(format!("cannot parse {name} as i64: {e}")))
+        };
+
+        TagCount {
+            value: value.ok_or_else(|| Error::Internal("group is missing from group count".to_owned()))?,
+            count: client::DbCount {
+                songs: parse("songs", songs)?,
+                playtime: parse("playtime", playtime)?,
+            }.into(),
+        }.into_ok()
+    }
+}
+
+#[derive(Debug)]
+pub struct Album {
+    pub artist: String,
+    pub name: String,
+    pub date: String,
+    pub count: DbCount,
+}
+
 #[derive(Debug, Clone)]
 #[cfg_attr(test, derive(Eq, PartialEq))]
 pub struct DbAudioFormat {
@@ -219,12 +286,87 @@ pub struct DbAudioFormat {
     pub number_of_channels: i64,
 }
 
-#[derive(Debug, Clone)]
-#[cfg_attr(test, derive(Eq, PartialEq))]
-pub struct DbTags {
-    pub titles: Vec<String>,
-    pub artists: Vec<String>,
-    pub albums: Vec<String>,
+macro_rules! db_tags {
+    ($($field:ident = $name:literal;)*) => {
+        #[derive(Debug, Clone, Default)]
+        #[cfg_attr(test, derive(Eq, PartialEq))]
+        pub struct DbTags {
+            $(pub $field: Vec<String>,)*
+            // Whatever else the server's tagtypes include, keyed by the lowercase MPD name.
+            pub other: BTreeMap<String, Vec<String>>,
+        }
+
+        impl DbTags {
+            // Tag names as reported by MPD.
+            pub const NAMES: &'static [&'static str] = &[$($name),*];
+
+            // MPD doesn't care about the case of tag names, so neither does this.
+            pub fn from_entries<I: IntoIterator<Item=(String, Vec<String>)>>(entries: I) -> Self {
+                let mut result = DbTags::default();
+
+                for (name, mut values) in entries {
+                    match name.to_lowercase() {
+                        $(name if name.eq_ignore_ascii_case($name) => result.$field.append(&mut values),)*
+                        name => result.other.entry(name).or_default().append(&mut values),
+                    }
+                }
+
+                result
+            }
+
+            // Keeps only the given tags, like narrowing tagtypes would have.
+            pub fn narrow(self, names: &[String]) -> Self {
+                DbTags::from_entries(
+                    self.into_entries()
+                        .into_iter()
+                        .filter(|(name, _)| names.iter().any(|it| it.eq_ignore_ascii_case(name)))
+                )
+            }
+
+            pub fn into_entries(self) -> Vec<(String, Vec<String>)> {
+                let mut result = vec![$(($name.to_owned(), self.$field)),*];
+
+                result.extend(self.other);
+
+                result
+            }
+        }
+    };
+}
+
+db_tags! {
+    titles = "Title";
+    artists = "Artist";
+    artist_sorts = "ArtistSort";
+    albums = "Album";
+    album_sorts = "AlbumSort";
+    album_artists = "AlbumArtist";
+    album_artist_sorts = "AlbumArtistSort";
+    tracks = "Track";
+    discs = "Disc";
+    dates = "Date";
+    original_dates = "OriginalDate";
+    genres = "Genre";
+    composers = "Composer";
+    performers = "Performer";
+    conductors = "Conductor";
+    works = "Work";
+    groupings = "Grouping";
+    names = "Name";
+    comments = "Comment";
+    labels = "Label";
+    musicbrainz_artist_ids = "MUSICBRAINZ_ARTISTID";
+    musicbrainz_album_ids = "MUSICBRAINZ_ALBUMID";
+    musicbrainz_album_artist_ids = "MUSICBRAINZ_ALBUMARTISTID";
+    musicbrainz_track_ids = "MUSICBRAINZ_TRACKID";
+    musicbrainz_release_track_ids = "MUSICBRAINZ_RELEASETRACKID";
+    musicbrainz_work_ids = "MUSICBRAINZ_WORKID";
+}
+
+impl From<HashMap<String, Vec<String>>> for DbTags {
+    fn from(tags: HashMap<String, Vec<String>>) -> Self {
+        DbTags::from_entries(tags)
+    }
 }
 
 #[derive(Debug)]
@@ -233,12 +375,14 @@ pub enum DbItem {
     File {
         uri: String,
         duration: Duration,
-        tags: DbTags,
+        tags: Box<DbTags>,
         format: Option<DbAudioFormat>,
         updated_at: String,
     },
+    // Directories that MPD didn't report themselves have no modification time.
     Directory {
         uri: String,
+        updated_at: Option<String>,
     },
     Playlist {
         uri: String,
@@ -273,11 +417,9 @@ impl TryFrom<client::DbItem> for DbItem {
             File {
                 file,
                 duration,
-                title,
-                artist,
-                album,
                 format,
-                last_modified
+                last_modified,
+                tags,
             } => {
                 let uri = file.clone();
 
@@ -288,28 +430,35 @@ impl TryFrom<client::DbItem> for DbItem {
                 DbItem::File {
                     uri: file,
                     duration: duration.map(Duration::seconds_f64).ok_or_else(|| error("duration"))?,
-                    tags: DbTags {
-                        titles: title,
-                        artists: artist,
-                        albums: album,
-                    },
+                    tags: Box::new(tags.into()),
                     format: format
                         .map(|s| s.parse())
                         .transpose()?,
                     updated_at: last_modified.ok_or_else(|| error("last_modified"))?,
                 }
             }
-            Directory { directory } => DbItem::Directory { uri: directory },
+            Directory { directory, last_modified } => DbItem::Directory { uri: directory, updated_at: last_modified },
             Playlist { playlist } => DbItem::Playlist { uri: playlist },
         }.into_ok()
     }
 }
 
+#[derive(Debug)]
+#[cfg_attr(test, derive(Eq, PartialEq))]
+pub struct DbGroup {
+    pub kind: DbGroupKind,
+    pub name: String,
+    pub artist: Option<String>,
+    pub uri: String,
+    pub count: usize,
+    pub updated_at: String,
+}
+
 impl DbItem {
     pub fn uri(&self) -> &str {
         match self {
             | DbItem::File { uri, .. }
-            | DbItem::Directory { uri }
+            | DbItem::Directory { uri, .. }
             | DbItem::Playlist { uri } => uri,
         }
     }
@@ -323,6 +472,7 @@ pub struct QueueItem {
     pub duration: Duration,
     pub tags: DbTags,
     pub format: Option<DbAudioFormat>,
+    pub priority: u8,
 }
 
 impl TryFrom<client::PlaylistItem> for QueueItem {
@@ -334,10 +484,9 @@ impl TryFrom<client::PlaylistItem> for QueueItem {
             pos,
             file,
             duration,
-            title,
-            artist,
-            album,
             format,
+            prio,
+            tags,
         }: client::PlaylistItem
     ) -> Result<Self, Self::Error> {
         QueueItem {
@@ -345,12 +494,9 @@ impl TryFrom<client::PlaylistItem> for QueueItem {
             position: pos,
             uri: file,
             duration: Duration::seconds_f64(duration),
-            tags: DbTags {
-                titles: title,
-                artists: artist,
-                albums: album,
-            },
+            tags: tags.into(),
             format: format.map(|s| s.parse()).transpose()?,
+            priority: prio.unwrap_or_default(),
         }.into_ok()
     }
 }
@@ -369,3 +515,114 @@ impl From<client::Playlist> for Playlist {
         }
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub id: i64,
+    pub name: String,
+    pub plugin: Option<String>,
+    pub enabled: bool,
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl TryFrom<client::Output> for Output {
+    type Error = Error;
+
+    fn try_from(
+        client::Output {
+            outputid,
+            outputname,
+            plugin,
+            outputenabled,
+            attribute,
+        }: client::Output
+    ) -> Result<Self, Self::Error> {
+        let attributes = attribute.into_iter()
+            .map(|it| {
+                it.split_once('=')
+                    .map(|(name, value)| (name.to_owned(), value.to_owned()))
+                    .ok_or_else(|| Error::Internal(format!("malformed output attribute '{it}'")))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Output {
+            id: outputid,
+            name: outputname,
+            plugin,
+            enabled: outputenabled,
+            attributes,
+        }.into_ok()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sticker {
+    pub name: String,
+    pub value: String,
+}
+
+impl TryFrom<String> for Sticker {
+    type Error = Error;
+
+    // Stickers come as 'name=value' where only the name is free of '='.
+    fn try_from(sticker: String) -> Result<Self, Self::Error> {
+        let Some((name, value)) = sticker.split_once('=') else {
+            return Err(Error::Internal(format!("malformed sticker '{sticker}'")));
+        };
+
+        Sticker {
+            name: name.to_owned(),
+            value: value.to_owned(),
+        }.into_ok()
+    }
+}
+
+impl TryFrom<client::Sticker> for Sticker {
+    type Error = Error;
+
+    fn try_from(client::Sticker { sticker }: client::Sticker) -> Result<Self, Self::Error> {
+        sticker.try_into()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct StickerMatch {
+    pub uri: String,
+    pub sticker: Sticker,
+}
+
+impl TryFrom<client::StickerMatch> for StickerMatch {
+    type Error = Error;
+
+    fn try_from(client::StickerMatch { file, sticker }: client::StickerMatch) -> Result<Self, Self::Error> {
+        StickerMatch {
+            uri: file,
+            sticker: sticker.try_into()?,
+        }.into_ok()
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_keep_tags_without_a_field() {
+        let tags = DbTags::from(HashMap::from([
+            ("Artist".to_owned(), vec!["Alfa".to_owned()]),
+            ("Movement".to_owned(), vec!["I. Allegro".to_owned()]),
+        ]));
+
+        assert_eq!(tags.artists, vec!["Alfa".to_owned()]);
+        assert_eq!(tags.other, BTreeMap::from([("movement".to_owned(), vec!["I. Allegro".to_owned()])]));
+
+        // Stored tags are keyed by their lowercase name, which has to come back the same.
+        let entries = tags.clone().into_entries()
+            .into_iter()
+            .map(|(name, values)| (name.to_lowercase(), values));
+
+        assert_eq!(DbTags::from_entries(entries), tags);
+    }
+}
@@ -2,6 +2,7 @@ use std::error;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Formatter;
+use std::io;
 
 use crate::mpd::client;
 use crate::mpd::client::ConnectionError;
@@ -14,6 +15,7 @@ pub enum Error {
     Forbidden(String),
     NotFound(String),
     AlreadyExists(String),
+    Invalid(String),
 }
 
 impl error::Error for Error {
@@ -30,6 +32,7 @@ impl Display for Error {
             Forbidden(msg) => write!(f, "unauthorized: {msg}"),
             NotFound(msg) => write!(f, "not found: {msg}"),
             AlreadyExists(msg) => write!(f, "already exists: {msg}"),
+            Invalid(msg) => write!(f, "invalid: {msg}"),
             Disconnected(msg) => write!(f, "disconnected: {msg}"),
         }
     }
@@ -46,6 +49,18 @@ impl From<client::Ack> for Error {
     }
 }
 
+// The other end going away mid-read or mid-write is no different from it closing the connection.
+fn is_disconnect(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::UnexpectedEof
+            | io::ErrorKind::NotConnected
+    )
+}
+
 impl From<client::Error> for Error {
     fn from(err: client::Error) -> Self {
         use client::Error::*;
@@ -53,6 +68,7 @@ impl From<client::Error> for Error {
         match err {
             Ack(ack) => ack.into(),
             Connection(ConnectionError::Closed) => Error::Disconnected("connection closed".to_owned()),
+            Connection(ConnectionError::Io(err)) if is_disconnect(&err) => Error::Disconnected(err.to_string()),
             Connection(err) => Error::Internal(err.to_string()),
             Parse(msg) => Error::Internal(msg),
             Deserialization(err) => Error::Internal(err.to_string()),
@@ -2,7 +2,7 @@ use std::future::Future;
 use std::result;
 use std::time::Duration;
 
-use bytes::Bytes;
+use ::time::OffsetDateTime;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tokio::sync::watch;
@@ -10,16 +10,29 @@ use tokio::time;
 
 use crate::mpd::action::Action;
 use crate::mpd::action::CoverArtKind;
+use crate::mpd::action::DbFilter;
+use crate::mpd::action::DbGroupKind;
+use crate::mpd::action::DbSort;
+use crate::mpd::action::DbWindow;
+use crate::mpd::action::PlaylistSaveMode;
+use crate::mpd::action::QueueRange;
 use crate::mpd::action::QueueSource;
 use crate::mpd::client::Client;
 use crate::mpd::client::ConnectError;
+use crate::mpd::data::Album;
+use crate::mpd::data::CoverArt;
 use crate::mpd::data::DbCount;
+use crate::mpd::data::DbGroup;
 use crate::mpd::data::DbItem;
 use crate::mpd::data::OneshotState;
+use crate::mpd::data::Output;
 use crate::mpd::data::Playlist;
 use crate::mpd::data::QueueItem;
 use crate::mpd::data::Status;
+use crate::mpd::data::Sticker;
+use crate::mpd::data::StickerMatch;
 use crate::mpd::data::Subsystem;
+use crate::mpd::data::TagCount;
 use crate::mpd::error::Error;
 use crate::mpd::manager::Manager;
 use crate::mpd::manager::run;
@@ -62,7 +75,9 @@ macro_rules! actions {
 
             let action = $en::$ev { response_tx: tx, $($pn),* };
 
-            self.inner.action_tx.send(action).await.expect("expected to be able send action");
+            if self.inner.action_tx.send(action).await.is_err() {
+                return Err(Error::Unavailable("connection is closed".to_owned()));
+            }
 
             tokio::select! {
                 _ = time::sleep(ACTION_TIMEOUT) => {
@@ -84,12 +99,35 @@ pub struct DbHandle<'a> {
 
 impl<'a> DbHandle<'a> {
     actions! {
-        get(uri: String) -> Result<Vec<DbItem>> = Action::DbGet;
+        get(uri: String, tags: Option<Vec<String>>) -> Result<Vec<DbItem>> = Action::DbGet;
         count(uri: String) -> Result<DbCount> = Action::DbCount;
-        search(query: String) -> Result<Vec<DbItem>> = Action::DbSearch;
+        count_filter(filter: DbFilter) -> Result<DbCount> = Action::DbCountFilter;
+        search(query: String, tags: Option<Vec<String>>) -> Result<Vec<DbItem>> = Action::DbSearch;
+        filter(
+            filter: DbFilter,
+            sort: Option<DbSort>,
+            window: Option<DbWindow>,
+            tags: Option<Vec<String>>
+        ) -> Result<Vec<DbItem>> = Action::DbFilter;
+        tagtypes() -> Result<Vec<String>> = Action::DbTagtypes;
         update(uri: Option<String>) -> Result<()> = Action::DbUpdate;
-        recents() -> Result<Vec<DbItem>> = Action::DbRecents;
-        cover_art(uri: String, kind: CoverArtKind) -> Result<Bytes> = Action::DbCoverArt;
+        recents(since: OffsetDateTime, window: Option<DbWindow>) -> Result<Vec<DbItem>> = Action::DbRecents;
+        recents_count(since: OffsetDateTime) -> Result<DbCount> = Action::DbRecentsCount;
+        recents_groups(since: OffsetDateTime, kind: DbGroupKind) -> Result<Vec<DbGroup>> = Action::DbRecentsGroups;
+        cover_art(uri: String, kind: CoverArtKind) -> Result<CoverArt> = Action::DbCoverArt;
+    }
+}
+
+pub struct LibraryHandle<'a> {
+    inner: &'a Handle,
+}
+
+impl<'a> LibraryHandle<'a> {
+    actions! {
+        artists() -> Result<Vec<TagCount>> = Action::LibraryArtists;
+        albums(artist: Option<String>, genre: Option<String>) -> Result<Vec<Album>> = Action::LibraryAlbums;
+        genres() -> Result<Vec<TagCount>> = Action::LibraryGenres;
+        years() -> Result<Vec<TagCount>> = Action::LibraryYears;
     }
 }
 
@@ -104,6 +142,14 @@ impl<'a> QueueHandle<'a> {
         replace(sources: Vec<QueueSource>) -> Result<()> = Action::QueueReplace;
         clear() -> Result<()> = Action::QueueClear;
         remove(id: i64) -> Result<()> = Action::QueueRemove;
+        remove_range(range: QueueRange) -> Result<()> = Action::QueueRemoveRange;
+        move_range(range: QueueRange, to: usize) -> Result<()> = Action::QueueMove;
+        move_id(id: i64, to: usize) -> Result<()> = Action::QueueMoveId;
+        play_next(id: i64) -> Result<()> = Action::QueuePlayNext;
+        swap(id: i64, with: i64) -> Result<()> = Action::QueueSwap;
+        prio(range: QueueRange, priority: u8) -> Result<()> = Action::QueuePrio;
+        prio_ids(ids: Vec<i64>, priority: u8) -> Result<()> = Action::QueuePrioId;
+        shuffle(range: Option<QueueRange>) -> Result<()> = Action::QueueShuffle;
         next() -> Result<()> = Action::QueueNext;
         prev() -> Result<()> = Action::QueuePrev;
         repeat(state: bool) -> Result<()> = Action::QueueRepeat;
@@ -123,6 +169,11 @@ impl<'a> PlaylistHandle<'a> {
         list() -> Result<Vec<Playlist>> = Action::PlaylistsList;
         delete(name: String) -> Result<()> = Action::PlaylistsDelete;
         delete_songs(name: String, positions: Vec<usize>) -> Result<()> = Action::PlaylistsDeleteSongs;
+        save(name: String, mode: PlaylistSaveMode) -> Result<()> = Action::PlaylistsSave;
+        add(name: String, uris: Vec<String>, position: Option<usize>) -> Result<()> = Action::PlaylistsAdd;
+        rename(name: String, new_name: String) -> Result<()> = Action::PlaylistsRename;
+        move_song(name: String, from: usize, to: usize) -> Result<()> = Action::PlaylistsMove;
+        clear(name: String) -> Result<()> = Action::PlaylistsClear;
     }
 }
 
@@ -159,33 +210,112 @@ impl<'a> VolumeHandle<'a> {
     }
 }
 
+pub struct OutputHandle<'a> {
+    inner: &'a Handle,
+}
+
+impl<'a> OutputHandle<'a> {
+    actions! {
+        list() -> Result<Vec<Output>> = Action::OutputsList;
+        enable(id: i64) -> Result<()> = Action::OutputsEnable;
+        disable(id: i64) -> Result<()> = Action::OutputsDisable;
+        toggle(id: i64) -> Result<()> = Action::OutputsToggle;
+        set(id: i64, name: String, value: String) -> Result<()> = Action::OutputsSet;
+    }
+}
+
+pub struct PartitionHandle<'a> {
+    inner: &'a Handle,
+}
+
+impl<'a> PartitionHandle<'a> {
+    actions! {
+        list() -> Result<Vec<String>> = Action::PartitionsList;
+        create(name: String) -> Result<()> = Action::PartitionsCreate;
+        delete(name: String) -> Result<()> = Action::PartitionsDelete;
+        move_output(name: String) -> Result<()> = Action::PartitionsMoveOutput;
+    }
+}
+
+pub struct StickerHandle<'a> {
+    inner: &'a Handle,
+}
+
+impl<'a> StickerHandle<'a> {
+    actions! {
+        get(uri: String, name: String) -> Result<Sticker> = Action::StickersGet;
+        set(uri: String, name: String, value: String) -> Result<()> = Action::StickersSet;
+        delete(uri: String, name: Option<String>) -> Result<()> = Action::StickersDelete;
+        list(uri: String) -> Result<Vec<Sticker>> = Action::StickersList;
+        find(uri: String, name: String, value: Option<String>) -> Result<Vec<StickerMatch>> = Action::StickersFind;
+    }
+}
+
+struct ConnectionHandle<'a> {
+    inner: &'a Handle,
+}
+
+impl<'a> ConnectionHandle<'a> {
+    actions! {
+        close() -> Result<()> = Action::Close;
+    }
+}
+
 impl Handle {
-    pub fn db(&self) -> DbHandle {
+    pub fn db(&self) -> DbHandle<'_> {
         DbHandle { inner: self }
     }
 
-    pub fn queue(&self) -> QueueHandle {
+    pub fn library(&self) -> LibraryHandle<'_> {
+        LibraryHandle { inner: self }
+    }
+
+    pub fn queue(&self) -> QueueHandle<'_> {
         QueueHandle { inner: self }
     }
 
-    pub fn playlists(&self) -> PlaylistHandle {
+    pub fn playlists(&self) -> PlaylistHandle<'_> {
         PlaylistHandle { inner: self }
     }
 
-    pub fn playback(&self) -> PlaybackHandle {
+    pub fn playback(&self) -> PlaybackHandle<'_> {
         PlaybackHandle { inner: self }
     }
 
-    pub fn status(&self) -> StatusHandle {
+    pub fn status(&self) -> StatusHandle<'_> {
         StatusHandle { inner: self }
     }
 
-    pub fn volume(&self) -> VolumeHandle {
+    pub fn volume(&self) -> VolumeHandle<'_> {
         VolumeHandle { inner: self }
     }
 
+    pub fn outputs(&self) -> OutputHandle<'_> {
+        OutputHandle { inner: self }
+    }
+
+    pub fn partitions(&self) -> PartitionHandle<'_> {
+        PartitionHandle { inner: self }
+    }
+
+    pub fn stickers(&self) -> StickerHandle<'_> {
+        StickerHandle { inner: self }
+    }
+
+    // Shuts the connection down for good, actions sent
+    // through any of the clones will fail afterwards.
+    pub async fn close(&self) -> Result<()> {
+        ConnectionHandle { inner: self }.close().await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.action_tx.is_closed()
+    }
+
     pub async fn changes(&mut self) -> Result<Vec<Subsystem>> {
-        self.idle_rx.changed().await.expect("changes sender is dropped");
+        if self.idle_rx.changed().await.is_err() {
+            return Err(Error::Unavailable("connection is closed".to_owned()));
+        }
 
         self.idle_rx.borrow_and_update().clone()
     }
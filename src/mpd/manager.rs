@@ -45,6 +45,7 @@ mod recv {
     use std::time::Duration;
     use std::ops::ControlFlow;
 
+    use tokio::sync::oneshot;
     use tokio::time;
 
     use crate::mpd::client;
@@ -59,17 +60,21 @@ mod recv {
         Subsystem::Volume,
         Subsystem::Player,
         Subsystem::Options,
+        Subsystem::Output,
+        Subsystem::Sticker,
     ];
 
     const RECONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
     pub struct RecvLoop<'a, T: Fn() -> F, F: Future<Output=result::Result<Client, ConnectError>>> {
         manager: &'a mut Manager<T, F>,
+        closed: bool,
+        close_tx: Option<oneshot::Sender<Result<()>>>,
     }
 
     impl<'a, T: Fn() -> F, F: Future<Output=result::Result<Client, ConnectError>>> RecvLoop<'a, T, F> {
         pub fn new(manager: &'a mut Manager<T, F>) -> RecvLoop<'a, T, F> {
-            RecvLoop { manager }
+            RecvLoop { manager, closed: false, close_tx: None }
         }
     }
 
@@ -108,13 +113,13 @@ mod recv {
 
             let control_flow = as_control_flow(&result);
 
-            self.manager.idle_tx.send(result)
-                .expect("expected to be able to propagate changes");
+            // Every handle might be gone already if the connection is being closed.
+            let _ = self.manager.idle_tx.send(result);
 
             control_flow
         }
 
-        async fn handle_action(&self, client: &mut Client, action: Action) -> ControlFlow<()> {
+        async fn handle_action(&mut self, client: &mut Client, action: Action) -> ControlFlow<()> {
             macro_rules! send {
                 ($response_tx:ident << $result:expr) => {
                     let result = $result;
@@ -131,9 +136,9 @@ mod recv {
 
             match action {
                 // Database actions.
-                Action::DbGet { uri, response_tx } => {
+                Action::DbGet { uri, tags, response_tx } => {
                     send! {
-                        response_tx << service.db().get(uri).await
+                        response_tx << service.db().get(uri, tags).await
                     }
                 },
                 Action::DbCount { uri, response_tx } => {
@@ -141,14 +146,39 @@ mod recv {
                         response_tx << service.db().count(uri).await
                     }
                 },
-                Action::DbSearch { query, response_tx } => {
+                Action::DbCountFilter { filter, response_tx } => {
                     send! {
-                        response_tx << service.db().search(query).await
+                        response_tx << service.db().count_filter(filter).await
                     }
                 },
-                Action::DbRecents { response_tx } => {
+                Action::DbSearch { query, tags, response_tx } => {
                     send! {
-                        response_tx << service.db().recents().await
+                        response_tx << service.db().search(query, tags).await
+                    }
+                },
+                Action::DbFilter { filter, sort, window, tags, response_tx } => {
+                    send! {
+                        response_tx << service.db().filter(filter, sort, window, tags).await
+                    }
+                },
+                Action::DbTagtypes { response_tx } => {
+                    send! {
+                        response_tx << service.db().tagtypes().await
+                    }
+                },
+                Action::DbRecents { since, window, response_tx } => {
+                    send! {
+                        response_tx << service.db().recents(since, window).await
+                    }
+                },
+                Action::DbRecentsCount { since, response_tx } => {
+                    send! {
+                        response_tx << service.db().recents_count(since).await
+                    }
+                },
+                Action::DbRecentsGroups { since, kind, response_tx } => {
+                    send! {
+                        response_tx << service.db().recents_groups(since, kind).await
                     }
                 },
                 Action::DbUpdate { uri, response_tx } => {
@@ -161,6 +191,27 @@ mod recv {
                         response_tx << service.db().cover_art(uri, kind).await
                     }
                 },
+                // Library actions.
+                Action::LibraryArtists { response_tx } => {
+                    send! {
+                        response_tx << service.library().artists().await
+                    }
+                },
+                Action::LibraryAlbums { artist, genre, response_tx } => {
+                    send! {
+                        response_tx << service.library().albums(artist, genre).await
+                    }
+                },
+                Action::LibraryGenres { response_tx } => {
+                    send! {
+                        response_tx << service.library().genres().await
+                    }
+                },
+                Action::LibraryYears { response_tx } => {
+                    send! {
+                        response_tx << service.library().years().await
+                    }
+                },
                 // Queue actions.
                 Action::QueueGet { response_tx } => {
                     send! {
@@ -187,6 +238,46 @@ mod recv {
                         response_tx << service.queue().remove(id).await
                     }
                 },
+                Action::QueueRemoveRange { range, response_tx } => {
+                    send! {
+                        response_tx << service.queue().remove_range(range).await
+                    }
+                },
+                Action::QueueMove { range, to, response_tx } => {
+                    send! {
+                        response_tx << service.queue().move_range(range, to).await
+                    }
+                },
+                Action::QueueMoveId { id, to, response_tx } => {
+                    send! {
+                        response_tx << service.queue().move_id(id, to).await
+                    }
+                },
+                Action::QueuePlayNext { id, response_tx } => {
+                    send! {
+                        response_tx << service.queue().play_next(id).await
+                    }
+                },
+                Action::QueueSwap { id, with, response_tx } => {
+                    send! {
+                        response_tx << service.queue().swap(id, with).await
+                    }
+                },
+                Action::QueuePrio { range, priority, response_tx } => {
+                    send! {
+                        response_tx << service.queue().prio(range, priority).await
+                    }
+                },
+                Action::QueuePrioId { ids, priority, response_tx } => {
+                    send! {
+                        response_tx << service.queue().prio_ids(ids, priority).await
+                    }
+                },
+                Action::QueueShuffle { range, response_tx } => {
+                    send! {
+                        response_tx << service.queue().shuffle(range).await
+                    }
+                },
                 Action::QueueNext { response_tx } => {
                     send! {
                         response_tx << service.queue().next().await
@@ -238,6 +329,31 @@ mod recv {
                         response_tx << service.playlists().delete_songs(name, positions).await
                     }
                 },
+                Action::PlaylistsSave { name, mode, response_tx } => {
+                    send! {
+                        response_tx << service.playlists().save(name, mode).await
+                    }
+                },
+                Action::PlaylistsAdd { name, uris, position, response_tx } => {
+                    send! {
+                        response_tx << service.playlists().add(name, uris, position).await
+                    }
+                },
+                Action::PlaylistsRename { name, new_name, response_tx } => {
+                    send! {
+                        response_tx << service.playlists().rename(name, new_name).await
+                    }
+                },
+                Action::PlaylistsMove { name, from, to, response_tx } => {
+                    send! {
+                        response_tx << service.playlists().move_song(name, from, to).await
+                    }
+                },
+                Action::PlaylistsClear { name, response_tx } => {
+                    send! {
+                        response_tx << service.playlists().clear(name).await
+                    }
+                },
                 // Playback actions.
                 Action::PlaybackPlay { id, response_tx } => {
                     send! {
@@ -271,6 +387,87 @@ mod recv {
                         response_tx << service.volume().set(value).await
                     }
                 },
+                // Output actions.
+                Action::OutputsList { response_tx } => {
+                    send! {
+                        response_tx << service.outputs().list().await
+                    }
+                },
+                Action::OutputsEnable { id, response_tx } => {
+                    send! {
+                        response_tx << service.outputs().enable(id).await
+                    }
+                },
+                Action::OutputsDisable { id, response_tx } => {
+                    send! {
+                        response_tx << service.outputs().disable(id).await
+                    }
+                },
+                Action::OutputsToggle { id, response_tx } => {
+                    send! {
+                        response_tx << service.outputs().toggle(id).await
+                    }
+                },
+                Action::OutputsSet { id, name, value, response_tx } => {
+                    send! {
+                        response_tx << service.outputs().set(id, name, value).await
+                    }
+                },
+                // Partition actions.
+                Action::PartitionsList { response_tx } => {
+                    send! {
+                        response_tx << service.partitions().list().await
+                    }
+                },
+                Action::PartitionsCreate { name, response_tx } => {
+                    send! {
+                        response_tx << service.partitions().create(name).await
+                    }
+                },
+                Action::PartitionsDelete { name, response_tx } => {
+                    send! {
+                        response_tx << service.partitions().delete(name).await
+                    }
+                },
+                Action::PartitionsMoveOutput { name, response_tx } => {
+                    send! {
+                        response_tx << service.partitions().move_output(name).await
+                    }
+                },
+                // Sticker actions.
+                Action::StickersGet { uri, name, response_tx } => {
+                    send! {
+                        response_tx << service.stickers().get(uri, name).await
+                    }
+                },
+                Action::StickersSet { uri, name, value, response_tx } => {
+                    send! {
+                        response_tx << service.stickers().set(uri, name, value).await
+                    }
+                },
+                Action::StickersDelete { uri, name, response_tx } => {
+                    send! {
+                        response_tx << service.stickers().delete(uri, name).await
+                    }
+                },
+                Action::StickersList { uri, response_tx } => {
+                    send! {
+                        response_tx << service.stickers().list(uri).await
+                    }
+                },
+                Action::StickersFind { uri, name, value, response_tx } => {
+                    send! {
+                        response_tx << service.stickers().find(uri, name, value).await
+                    }
+                },
+                // Connection actions.
+                Action::Close { response_tx } => {
+                    // Respond once the connection is actually dropped.
+                    self.closed = true;
+                    self.close_tx = Some(response_tx);
+
+                    ControlFlow::Break(())
+                },
             }
         }
 
@@ -282,7 +479,14 @@ mod recv {
                     changes = { client.idle(idle_input(), || is_idling = true) } => {
                         self.handle_changes(changes)?;
                     },
-                    Some(action) = self.manager.action_rx.recv() => {
+                    action = self.manager.action_rx.recv() => {
+                        let Some(action) = action else {
+                            // Every handle is gone, there is nobody left to serve.
+                            self.closed = true;
+
+                            return ControlFlow::Break(());
+                        };
+
                         if is_idling {
                             let changes = client.noidle().await;
 
@@ -300,6 +504,17 @@ mod recv {
 
             loop {
                 match (self.manager.connect)().await {
+                    Err(_) if self.manager.idle_tx.is_closed() => {
+                        tracing::info!("connection failed and every handle is gone, giving up");
+
+                        return;
+                    }
+                    // Closes every handle, so that whoever holds one can tell it's gone for good.
+                    Err(err) if err.is_permanent() => {
+                        tracing::warn!("connection failed: {err}, giving up");
+
+                        return;
+                    }
                     Err(err) => {
                         tracing::warn!("connection failed: {err}, will retry in {} seconds", RECONNECT_TIMEOUT.as_secs());
 
@@ -315,6 +530,18 @@ mod recv {
                         }
 
                         self.inner(&mut client).await;
+
+                        if self.closed {
+                            drop(client);
+
+                            if let Some(close_tx) = self.close_tx.take() {
+                                let _ = close_tx.send(Ok(()));
+                            }
+
+                            tracing::info!("connection closed");
+
+                            return;
+                        }
                     }
                 }
 
@@ -0,0 +1,139 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::result;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+use crate::mpd::client::Client;
+use crate::mpd::client::ConnectError;
+use crate::mpd::error::Error;
+use crate::mpd::handle::Handle;
+use crate::mpd::result::Result;
+use crate::mpd::sub::SubscriptionHandle;
+
+pub const DEFAULT_PARTITION: &str = "default";
+
+#[derive(Clone)]
+pub struct Partition {
+    pub handle: Handle,
+    pub sub_handle: SubscriptionHandle,
+}
+
+impl Partition {
+    fn new(handle: Handle) -> Self {
+        let sub_handle = SubscriptionHandle::new(handle.clone());
+
+        Partition { handle, sub_handle }
+    }
+}
+
+type NewHandle = dyn Fn(String) -> Handle + Send + Sync;
+
+// Every partition gets a dedicated connection since MPD
+// scopes both commands and idle events to the partition
+// that a client has selected.
+#[derive(Clone)]
+pub struct PartitionRegistry {
+    default: Partition,
+    partitions: Arc<Mutex<HashMap<String, Partition>>>,
+    new_handle: Arc<NewHandle>,
+}
+
+impl PartitionRegistry {
+    pub fn new<F, R>(connect: F) -> Self
+        where
+            F: Send + Sync + 'static + Fn() -> R,
+            R: Send + Sync + 'static + Future<Output=result::Result<Client, ConnectError>>,
+    {
+        let connect = Arc::new(connect);
+
+        let default = Partition::new(Handle::new({
+            let connect = connect.clone();
+
+            move || connect()
+        }));
+
+        let new_handle = move |name: String| {
+            let connect = connect.clone();
+
+            Handle::new(move || {
+                let client = connect();
+                let name = name.clone();
+
+                async move {
+                    let mut client = client.await?;
+
+                    client.partition(name).await?;
+
+                    Ok(client)
+                }
+            })
+        };
+
+        PartitionRegistry {
+            default,
+            partitions: Arc::new(Mutex::new(HashMap::new())),
+            new_handle: Arc::new(new_handle),
+        }
+    }
+}
+
+impl PartitionRegistry {
+    pub fn default_partition(&self) -> Partition {
+        self.default.clone()
+    }
+
+    pub async fn get(&self, name: &str) -> Result<Partition> {
+        if name == DEFAULT_PARTITION {
+            return Ok(self.default_partition());
+        }
+
+        if let Some(partition) = self.lock().get(name) {
+            // A partition deleted behind our back closes its connection.
+            if !partition.handle.is_closed() {
+                return Ok(partition.clone());
+            }
+        }
+
+        if !self.list().await?.iter().any(|it| it == name) {
+            return Err(Error::NotFound(format!("partition '{name}' does not exist")));
+        }
+
+        let mut partitions = self.lock();
+
+        partitions.retain(|_, partition| !partition.handle.is_closed());
+
+        let partition = partitions
+            .entry(name.to_owned())
+            .or_insert_with(|| Partition::new((self.new_handle)(name.to_owned())))
+            .clone();
+
+        Ok(partition)
+    }
+
+    pub async fn list(&self) -> Result<Vec<String>> {
+        self.default.handle.partitions().list().await
+    }
+
+    pub async fn create(&self, name: String) -> Result<()> {
+        self.default.handle.partitions().create(name).await
+    }
+
+    pub async fn delete(&self, name: String) -> Result<()> {
+        let partition = self.lock().remove(&name);
+
+        // MPD refuses to delete partitions that still have clients.
+        if let Some(partition) = partition {
+            if let Err(err) = partition.handle.close().await {
+                tracing::warn!("failed to close connection to partition '{name}': {err}");
+            }
+        }
+
+        self.default.handle.partitions().delete(name).await
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Partition>> {
+        self.partitions.lock().expect("partition registry lock is poisoned")
+    }
+}
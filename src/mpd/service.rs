@@ -1,26 +1,44 @@
 use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
 
 use bytes::BufMut;
-use bytes::Bytes;
 use bytes::BytesMut;
 use time::Duration;
 use time::format_description;
 use time::OffsetDateTime;
+use time::UtcOffset;
 
 use crate::convert::IntoOption;
 use crate::convert::IntoResult;
 use crate::mpd::action::CoverArtKind;
+use crate::mpd::action::DbFilter;
+use crate::mpd::action::DbFilterOperator;
+use crate::mpd::action::DbGroupKind;
+use crate::mpd::action::DbSort;
+use crate::mpd::action::DbWindow;
+use crate::mpd::action::PlaylistSaveMode;
+use crate::mpd::action::QueueRange;
 use crate::mpd::action::QueueSource;
+use crate::mpd::client;
 use crate::mpd::client::Binary;
 use crate::mpd::client::CommandListClient;
 use crate::mpd::client::Client;
 use crate::mpd::client::escape;
+use crate::mpd::client::KeyValues;
+use crate::mpd::data::Album;
+use crate::mpd::data::CoverArt;
 use crate::mpd::data::DbCount;
+use crate::mpd::data::DbGroup;
 use crate::mpd::data::DbItem;
 use crate::mpd::data::OneshotState;
+use crate::mpd::data::Output;
 use crate::mpd::data::Playlist;
 use crate::mpd::data::QueueItem;
 use crate::mpd::data::Status;
+use crate::mpd::data::Sticker;
+use crate::mpd::data::StickerMatch;
+use crate::mpd::data::TagCount;
 use crate::mpd::data::to_state_string;
 use crate::mpd::Error;
 use crate::mpd::result::Result;
@@ -35,6 +53,82 @@ impl<'a> Service<'a> {
     }
 }
 
+impl<'a> Service<'a> {
+    // Tag types are per-connection state, so a narrowed set must be reset once the command
+    // that needed it is done. Unknown tags are refused up front since MPD would only refuse
+    // them after the set was already cleared.
+    async fn narrow_tags(&mut self, tags: &Option<Vec<String>>) -> Result<()> {
+        let Some(tags) = tags else {
+            return Ok(());
+        };
+
+        let tagtypes = self.client.tagtypes().await?;
+
+        let is_known = |tag: &String| tagtypes.iter().any(|it| it.tagtype.eq_ignore_ascii_case(tag));
+
+        if let Some(tag) = tags.iter().find(|tag| !is_known(tag)) {
+            return Err(Error::Invalid(format!("unknown tag '{tag}'")));
+        }
+
+        let enabled = tags.clone();
+
+        let result = self.client
+            .command_list(|builder| {
+                let builder = builder.tagtypes_clear();
+
+                if enabled.is_empty() {
+                    builder
+                } else {
+                    builder.tagtypes_enable(enabled)
+                }
+            })
+            .await;
+
+        match result {
+            Ok(_) => Ok(()),
+            Err(_) => self.restore_tags(result).await,
+        }
+    }
+
+    async fn reset_tags<T>(&mut self, tags: &Option<Vec<String>>, result: client::Result<T>) -> Result<T> {
+        if tags.is_none() {
+            return Ok(result?);
+        }
+
+        self.restore_tags(result).await
+    }
+
+    // Resets on every path, but an error from the command itself takes precedence.
+    async fn restore_tags<T>(&mut self, result: client::Result<T>) -> Result<T> {
+        match (self.client.tagtypes_all().await, result) {
+            (Ok(_), result) => Ok(result?),
+            (Err(err), Ok(_)) => Err(err.into()),
+            (Err(err), Err(original)) => {
+                tracing::error!("failed to reset tag types: {}", Error::from(err));
+
+                Err(original.into())
+            },
+        }
+    }
+}
+
+const DEFAULT_COVER_ART_CONTENT_TYPE: &str = "application/octet-stream";
+
+// MPD doesn't say what albumart is, so it's recognized by its first bytes.
+fn sniff_content_type(data: &[u8]) -> Option<&'static str> {
+    let content_type = match data {
+        [0xFF, 0xD8, 0xFF, ..] => "image/jpeg",
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => "image/png",
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => "image/gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [_, _, _, _, b'f', b't', b'y', b'p', b'a', b'v', b'i', b'f' | b's', ..] => "image/avif",
+        [b'B', b'M', ..] => "image/bmp",
+        _ => return None,
+    };
+
+    Some(content_type)
+}
+
 pub struct DbService<'a> {
     inner: &'a mut Service<'a>,
 }
@@ -51,12 +145,10 @@ fn base_filter(uri: &str) -> String {
     format!(r#"(base "{uri}")"#)
 }
 
-fn recents_filter() -> Result<String> {
-    let since = OffsetDateTime::now_utc() - Duration::days(7);
-
-    let since = since.date();
+fn recents_filter(since: OffsetDateTime) -> Result<String> {
+    let since = since.to_offset(UtcOffset::UTC);
 
-    let format = format_description::parse("[year]-[month]-[day]")
+    let format = format_description::parse("[year]-[month]-[day]T[hour]:[minute]:[second]Z")
         .map_err(|e| format!("failed to create format description for modified-since filter: {e}"))?;
 
     let since = since.format(&format)
@@ -65,6 +157,76 @@ fn recents_filter() -> Result<String> {
     format!(r#"(modified-since "{since}")"#).into_ok()
 }
 
+// Files are expected to come sorted by modification time so groups
+// end up ordered by their most recently modified file as well.
+//
+// Files without an album are grouped by directory even when grouping by album.
+// Albums are told apart by their album artist or, lacking one, by their directory,
+// since the tracks of a compilation have nothing else in common.
+fn group_items(items: Vec<DbItem>, kind: DbGroupKind) -> Vec<DbGroup> {
+    let mut groups: Vec<DbGroup> = Vec::new();
+    let mut indices: HashMap<_, usize> = HashMap::new();
+
+    for item in items {
+        let DbItem::File { uri, tags, updated_at, .. } = item else {
+            continue;
+        };
+
+        let tags = *tags;
+
+        let directory = uri.rsplit_once('/')
+            .map(|(directory, _)| directory.to_owned())
+            .unwrap_or_default();
+
+        let album = match kind {
+            DbGroupKind::Album => tags.albums.into_iter().next(),
+            DbGroupKind::Directory => None,
+        };
+
+        let (kind, artist, scope, name) = match album {
+            Some(album) => {
+                let album_artist = tags.album_artists.into_iter().next();
+
+                let artist = album_artist.clone()
+                    .or_else(|| tags.artists.into_iter().next());
+
+                let scope = album_artist.unwrap_or_else(|| directory.clone());
+
+                (DbGroupKind::Album, artist, scope, album)
+            },
+            None => (DbGroupKind::Directory, None, directory.clone(), directory.clone()),
+        };
+
+        let key = (kind, scope, name.clone());
+
+        if let Some(&i) = indices.get(&key) {
+            let group = &mut groups[i];
+
+            group.count += 1;
+
+            // Track artists of a compilation without an album artist don't speak for the album.
+            if group.artist != artist {
+                group.artist = None;
+            }
+
+            continue;
+        }
+
+        indices.insert(key, groups.len());
+
+        groups.push(DbGroup {
+            kind,
+            name,
+            artist,
+            uri: directory,
+            count: 1,
+            updated_at,
+        });
+    }
+
+    groups
+}
+
 // Since MPD does not allow us to search for directories we go over
 // every path segment in a URI that definitely contains a match somewhere
 // and construct pseudo-results for matching directories. We also include the
@@ -104,7 +266,7 @@ fn uri_matches(item: DbItem, query: &str) -> Result<Vec<DbItem>> {
 
             uri.pop();
 
-            Some(DbItem::Directory { uri })
+            Some(DbItem::Directory { uri, updated_at: None })
         })
         .collect::<Vec<_>>();
 
@@ -117,8 +279,12 @@ fn uri_matches(item: DbItem, query: &str) -> Result<Vec<DbItem>> {
 }
 
 impl<'a> DbService<'a> {
-    pub async fn get(&mut self, uri: String) -> Result<Vec<DbItem>> {
-        let result = self.inner.client.lsinfo(uri).await?
+    pub async fn get(&mut self, uri: String, tags: Option<Vec<String>>) -> Result<Vec<DbItem>> {
+        self.inner.narrow_tags(&tags).await?;
+
+        let result = self.inner.client.lsinfo(uri).await;
+
+        let result = self.inner.reset_tags(&tags, result).await?
             .into_iter()
             .map(TryInto::try_into)
             .collect::<Result<_, >>()?;
@@ -132,10 +298,20 @@ impl<'a> DbService<'a> {
         Ok(result.into())
     }
 
-    pub async fn search(&mut self, query: String) -> Result<Vec<DbItem>> {
+    pub async fn count_filter(&mut self, filter: DbFilter) -> Result<DbCount> {
+        let result = self.inner.client.count(filter.to_filter_string()).await?;
+
+        Ok(result.into())
+    }
+
+    pub async fn search(&mut self, query: String, tags: Option<Vec<String>>) -> Result<Vec<DbItem>> {
         let query = query.to_lowercase();
 
-        let mut items = self.inner.client.search(file_filter(&query), None).await?
+        self.inner.narrow_tags(&tags).await?;
+
+        let result = self.inner.client.search(file_filter(&query), None, None).await;
+
+        let mut items = self.inner.reset_tags(&tags, result).await?
             .into_iter()
             .map(|it| uri_matches(it.try_into()?, &query))
             .collect::<Result<Vec<_>>>()?
@@ -160,10 +336,36 @@ impl<'a> DbService<'a> {
         Ok(items)
     }
 
-    pub async fn recents(&mut self) -> Result<Vec<DbItem>> {
+    pub async fn filter(
+        &mut self,
+        filter: DbFilter,
+        sort: Option<DbSort>,
+        window: Option<DbWindow>,
+        tags: Option<Vec<String>>,
+    ) -> Result<Vec<DbItem>> {
+        self.inner.narrow_tags(&tags).await?;
+
+        let result = self.inner.client
+            .search(
+                filter.to_filter_string(),
+                sort.as_ref().map(DbSort::to_sort_string),
+                window.as_ref().map(DbWindow::to_window_string),
+            )
+            .await;
+
+        let result = self.inner.reset_tags(&tags, result).await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_, >>()?;
+
+        Ok(result)
+    }
+
+    pub async fn recents(&mut self, since: OffsetDateTime, window: Option<DbWindow>) -> Result<Vec<DbItem>> {
         let sort = "-Last-Modified".to_owned().into_some();
+        let window = window.as_ref().map(DbWindow::to_window_string);
 
-        let result = self.inner.client.search(recents_filter()?, sort).await?
+        let result = self.inner.client.search(recents_filter(since)?, sort, window).await?
             .into_iter()
             .map(TryInto::try_into)
             .collect::<Result<_, >>()?;
@@ -171,14 +373,36 @@ impl<'a> DbService<'a> {
         Ok(result)
     }
 
+    pub async fn recents_count(&mut self, since: OffsetDateTime) -> Result<DbCount> {
+        let result = self.inner.client.count(recents_filter(since)?).await?;
+
+        Ok(result.into())
+    }
+
+    pub async fn recents_groups(&mut self, since: OffsetDateTime, kind: DbGroupKind) -> Result<Vec<DbGroup>> {
+        let items = self.recents(since, None).await?;
+
+        Ok(group_items(items, kind))
+    }
+
+    pub async fn tagtypes(&mut self) -> Result<Vec<String>> {
+        let result = self.inner.client.tagtypes().await?
+            .into_iter()
+            .map(|it| it.tagtype)
+            .collect();
+
+        Ok(result)
+    }
+
     pub async fn update(&mut self, uri: Option<String>) -> Result<()> {
         self.inner.client.update(uri).await?;
 
         Ok(())
     }
 
-    pub async fn cover_art(&mut self, uri: String, kind: CoverArtKind) -> Result<Bytes> {
+    pub async fn cover_art(&mut self, uri: String, kind: CoverArtKind) -> Result<CoverArt> {
         let mut result = BytesMut::new();
+        let mut mime_type = None;
 
         let mut size = usize::MAX;
         let mut offset = 0;
@@ -197,10 +421,16 @@ impl<'a> DbService<'a> {
             size = info.size;
             offset += info.binary;
 
+            mime_type = mime_type.or(info.mime_type);
+
             result.put_slice(&data);
         }
 
-        Ok(result.freeze())
+        let content_type = mime_type
+            .or_else(|| sniff_content_type(&result).map(ToOwned::to_owned))
+            .unwrap_or_else(|| DEFAULT_COVER_ART_CONTENT_TYPE.to_owned());
+
+        Ok(CoverArt { data: result.freeze(), content_type })
     }
 }
 
@@ -269,6 +499,64 @@ impl<'a> QueueService<'a> {
         Ok(())
     }
 
+    pub async fn remove_range(&mut self, range: QueueRange) -> Result<()> {
+        self.inner.client.delete(range.to_range_string()).await?;
+
+        Ok(())
+    }
+
+    pub async fn move_range(&mut self, range: QueueRange, to: usize) -> Result<()> {
+        self.inner.client.r#move(range.to_range_string(), to.to_string()).await?;
+
+        Ok(())
+    }
+
+    pub async fn move_id(&mut self, id: i64, to: usize) -> Result<()> {
+        self.inner.client.moveid(id, to.to_string()).await?;
+
+        Ok(())
+    }
+
+    // Order only matters outside of random mode, where the highest priority is what plays next instead.
+    // Moving a song to "+0" places it right after the current one, but there may be no current one.
+    pub async fn play_next(&mut self, id: i64) -> Result<()> {
+        let status = self.inner.client.status().await?;
+
+        if status.random == 1 {
+            self.inner.client.prioid(u8::MAX, vec![id]).await?;
+        } else if status.songid.is_some() {
+            self.inner.client.moveid(id, "+0".to_owned()).await?;
+        } else {
+            self.inner.client.moveid(id, "0".to_owned()).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn swap(&mut self, id: i64, with: i64) -> Result<()> {
+        self.inner.client.swapid(id, with).await?;
+
+        Ok(())
+    }
+
+    pub async fn prio(&mut self, range: QueueRange, priority: u8) -> Result<()> {
+        self.inner.client.prio(priority, range.to_range_string()).await?;
+
+        Ok(())
+    }
+
+    pub async fn prio_ids(&mut self, ids: Vec<i64>, priority: u8) -> Result<()> {
+        self.inner.client.prioid(priority, ids).await?;
+
+        Ok(())
+    }
+
+    pub async fn shuffle(&mut self, range: Option<QueueRange>) -> Result<()> {
+        self.inner.client.shuffle(range.as_ref().map(QueueRange::to_range_string)).await?;
+
+        Ok(())
+    }
+
     pub async fn next(&mut self) -> Result<()> {
         self.inner.client.next().await?;
 
@@ -310,6 +598,168 @@ impl<'a> QueueService<'a> {
     }
 }
 
+pub struct LibraryService<'a> {
+    inner: &'a mut Service<'a>,
+}
+
+fn tag_filter(tag: &str, value: String) -> DbFilter {
+    DbFilter::Tag {
+        tag: tag.to_owned(),
+        operator: DbFilterOperator::Equal,
+        value,
+    }
+}
+
+impl<'a> LibraryService<'a> {
+    const ALBUM_ARTIST_TAG: &'static str = "AlbumArtist";
+    const ALBUM_TAG: &'static str = "Album";
+    const DATE_TAG: &'static str = "Date";
+    const GENRE_TAG: &'static str = "Genre";
+
+    async fn count_group(&mut self, filter: Option<String>, group: &str) -> Result<Vec<TagCount>> {
+        let result = self.inner.client.count_group(filter, group.to_owned()).await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+
+        Ok(result)
+    }
+
+    pub async fn artists(&mut self) -> Result<Vec<TagCount>> {
+        self.count_group(None, LibraryService::ALBUM_ARTIST_TAG).await
+    }
+
+    pub async fn genres(&mut self) -> Result<Vec<TagCount>> {
+        self.count_group(None, LibraryService::GENRE_TAG).await
+    }
+
+    // Dates may be full dates as well as years, so
+    // they are merged by the leading year if there is one.
+    pub async fn years(&mut self) -> Result<Vec<TagCount>> {
+        let mut years: BTreeMap<String, DbCount> = BTreeMap::new();
+
+        for TagCount { value, count } in self.count_group(None, LibraryService::DATE_TAG).await? {
+            let year = value.get(..4)
+                .filter(|it| it.chars().all(|c| c.is_ascii_digit()))
+                .unwrap_or(&value)
+                .to_owned();
+
+            let entry = years.entry(year).or_insert(DbCount { nsongs: 0, playtime: Duration::ZERO });
+
+            entry.nsongs += count.nsongs;
+            entry.playtime += count.playtime;
+        }
+
+        let result = years.into_iter()
+            .map(|(value, count)| TagCount { value, count })
+            .collect();
+
+        Ok(result)
+    }
+
+    pub async fn albums(&mut self, artist: Option<String>, genre: Option<String>) -> Result<Vec<Album>> {
+        let genre_filter = || genre.clone().map(|genre| tag_filter(LibraryService::GENRE_TAG, genre));
+
+        let filters = artist.map(|artist| tag_filter(LibraryService::ALBUM_ARTIST_TAG, artist))
+            .into_iter()
+            .chain(genre_filter())
+            .collect::<Vec<_>>();
+
+        let filter = (!filters.is_empty()).then(|| DbFilter::And(filters).to_filter_string());
+
+        let pairs = self.inner.client
+            .list(
+                LibraryService::ALBUM_TAG.to_owned(),
+                filter.clone(),
+                vec![LibraryService::ALBUM_ARTIST_TAG.to_owned(), LibraryService::DATE_TAG.to_owned()],
+            )
+            .await?;
+
+        let mut artist = String::new();
+        let mut date = String::new();
+        let mut groups = Vec::new();
+
+        for (key, value) in pairs.into_iter().flat_map(|KeyValues(pairs)| pairs) {
+            if key.eq_ignore_ascii_case(LibraryService::ALBUM_ARTIST_TAG) {
+                artist = value;
+            } else if key.eq_ignore_ascii_case(LibraryService::DATE_TAG) {
+                date = value;
+            } else if key.eq_ignore_ascii_case(LibraryService::ALBUM_TAG) {
+                groups.push((artist.clone(), value, date.clone()));
+            }
+        }
+
+        if groups.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // MPD only groups counts by a single tag, so albums are counted by name in
+        // one pass and joined with the listing above. Names shared by several
+        // artists or dates would be merged that way, so only those are counted
+        // separately.
+        let mut occurrences: HashMap<&str, usize> = HashMap::new();
+
+        for (_, album, _) in &groups {
+            *occurrences.entry(album.as_str()).or_default() += 1;
+        }
+
+        let ambiguous = groups.iter()
+            .filter(|(_, album, _)| occurrences[album.as_str()] > 1)
+            .collect::<Vec<_>>();
+
+        let mut ambiguous_counts: HashMap<(&str, &str, &str), client::DbCount> = HashMap::new();
+
+        if !ambiguous.is_empty() {
+            let counts: Vec<client::DbCount> = self.inner.client
+                .command_list_with_response(|builder| {
+                    ambiguous.iter().fold(builder, |it, (artist, album, date)| {
+                        let filters = vec![
+                            tag_filter(LibraryService::ALBUM_ARTIST_TAG, artist.clone()),
+                            tag_filter(LibraryService::ALBUM_TAG, album.clone()),
+                            tag_filter(LibraryService::DATE_TAG, date.clone()),
+                        ];
+
+                        let filters = filters.into_iter()
+                            .chain(genre_filter())
+                            .collect();
+
+                        it.count(DbFilter::And(filters).to_filter_string())
+                    })
+                })
+                .await?;
+
+            if counts.len() != ambiguous.len() {
+                return Err(Error::Internal(format!("expected {} album counts, got {}", ambiguous.len(), counts.len())));
+            }
+
+            ambiguous_counts.extend(
+                ambiguous.iter()
+                    .map(|(artist, album, date)| (artist.as_str(), album.as_str(), date.as_str()))
+                    .zip(counts)
+            );
+        }
+
+        let mut album_counts: HashMap<String, DbCount> = self.count_group(filter, LibraryService::ALBUM_TAG).await?
+            .into_iter()
+            .map(|TagCount { value, count }| (value, count))
+            .collect();
+
+        let mut result = Vec::with_capacity(groups.len());
+
+        for (artist, name, date) in &groups {
+            let count = match ambiguous_counts.remove(&(artist.as_str(), name.as_str(), date.as_str())) {
+                Some(count) => count.into(),
+                None => album_counts.remove(name)
+                    .ok_or_else(|| Error::Internal(format!("album {name} is missing from group count")))?,
+            };
+
+            result.push(Album { artist: artist.clone(), name: name.clone(), date: date.clone(), count });
+        }
+
+        Ok(result)
+    }
+}
+
 pub struct PlaylistService<'a> {
     inner: &'a mut Service<'a>,
 }
@@ -354,6 +804,43 @@ impl<'a> PlaylistService<'a> {
 
         Ok(())
     }
+
+    pub async fn save(&mut self, name: String, mode: PlaylistSaveMode) -> Result<()> {
+        self.inner.client.save(name, mode.to_mode_string()).await?;
+
+        Ok(())
+    }
+
+    pub async fn add(&mut self, name: String, uris: Vec<String>, position: Option<usize>) -> Result<()> {
+        self.inner.client
+            .command_list(|builder| {
+                uris.into_iter().enumerate().fold(builder, |it, (i, uri)| {
+                    // Keep the songs in order when inserting at a position.
+                    it.playlistadd(name.clone(), uri, position.map(|position| position + i))
+                })
+            })
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn rename(&mut self, name: String, new_name: String) -> Result<()> {
+        self.inner.client.rename(name, new_name).await?;
+
+        Ok(())
+    }
+
+    pub async fn move_song(&mut self, name: String, from: usize, to: usize) -> Result<()> {
+        self.inner.client.playlistmove(name, from, to).await?;
+
+        Ok(())
+    }
+
+    pub async fn clear(&mut self, name: String) -> Result<()> {
+        self.inner.client.playlistclear(name).await?;
+
+        Ok(())
+    }
 }
 
 pub struct PlaybackService<'a> {
@@ -410,30 +897,170 @@ impl<'a> VolumeService<'a> {
     }
 }
 
+pub struct OutputService<'a> {
+    inner: &'a mut Service<'a>,
+}
+
+impl<'a> OutputService<'a> {
+    pub async fn list(&mut self) -> Result<Vec<Output>> {
+        let result = self.inner.client.outputs().await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+
+        Ok(result)
+    }
+
+    pub async fn enable(&mut self, id: i64) -> Result<()> {
+        self.inner.client.enableoutput(id).await?;
+
+        Ok(())
+    }
+
+    pub async fn disable(&mut self, id: i64) -> Result<()> {
+        self.inner.client.disableoutput(id).await?;
+
+        Ok(())
+    }
+
+    pub async fn toggle(&mut self, id: i64) -> Result<()> {
+        self.inner.client.toggleoutput(id).await?;
+
+        Ok(())
+    }
+
+    pub async fn set(&mut self, id: i64, name: String, value: String) -> Result<()> {
+        self.inner.client.outputset(id, name, value).await?;
+
+        Ok(())
+    }
+}
+
+pub struct PartitionService<'a> {
+    inner: &'a mut Service<'a>,
+}
+
+impl<'a> PartitionService<'a> {
+    pub async fn list(&mut self) -> Result<Vec<String>> {
+        let result = self.inner.client.listpartitions().await?
+            .into_iter()
+            .map(|it| it.partition)
+            .collect();
+
+        Ok(result)
+    }
+
+    pub async fn create(&mut self, name: String) -> Result<()> {
+        self.inner.client.newpartition(name).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&mut self, name: String) -> Result<()> {
+        self.inner.client.delpartition(name).await?;
+
+        Ok(())
+    }
+
+    pub async fn move_output(&mut self, name: String) -> Result<()> {
+        self.inner.client.moveoutput(name).await?;
+
+        Ok(())
+    }
+}
+
+pub struct StickerService<'a> {
+    inner: &'a mut Service<'a>,
+}
+
+impl<'a> StickerService<'a> {
+    // MPD only supports stickers on songs.
+    const SONG_TYPE: &'static str = "song";
+
+    pub async fn get(&mut self, uri: String, name: String) -> Result<Sticker> {
+        let result = self.inner.client
+            .sticker_get(StickerService::SONG_TYPE.to_owned(), uri, name)
+            .await?
+            .try_into()?;
+
+        Ok(result)
+    }
+
+    pub async fn set(&mut self, uri: String, name: String, value: String) -> Result<()> {
+        self.inner.client.sticker_set(StickerService::SONG_TYPE.to_owned(), uri, name, value).await?;
+
+        Ok(())
+    }
+
+    pub async fn delete(&mut self, uri: String, name: Option<String>) -> Result<()> {
+        self.inner.client.sticker_delete(StickerService::SONG_TYPE.to_owned(), uri, name).await?;
+
+        Ok(())
+    }
+
+    pub async fn list(&mut self, uri: String) -> Result<Vec<Sticker>> {
+        let result = self.inner.client
+            .sticker_list(StickerService::SONG_TYPE.to_owned(), uri)
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+
+        Ok(result)
+    }
+
+    pub async fn find(&mut self, uri: String, name: String, value: Option<String>) -> Result<Vec<StickerMatch>> {
+        let result = self.inner.client
+            .sticker_find(StickerService::SONG_TYPE.to_owned(), uri, name, value)
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<Result<_>>()?;
+
+        Ok(result)
+    }
+}
+
 impl<'a> Service<'a> {
-    pub fn db(&'a mut self) -> DbService {
+    pub fn db(&'a mut self) -> DbService<'a> {
         DbService { inner: self }
     }
 
-    pub fn queue(&'a mut self) -> QueueService {
+    pub fn library(&'a mut self) -> LibraryService<'a> {
+        LibraryService { inner: self }
+    }
+
+    pub fn queue(&'a mut self) -> QueueService<'a> {
         QueueService { inner: self }
     }
 
-    pub fn playlists(&'a mut self) -> PlaylistService {
+    pub fn playlists(&'a mut self) -> PlaylistService<'a> {
         PlaylistService { inner: self }
     }
 
-    pub fn playback(&'a mut self) -> PlaybackService {
+    pub fn playback(&'a mut self) -> PlaybackService<'a> {
         PlaybackService { inner: self }
     }
 
-    pub fn status(&'a mut self) -> StatusService {
+    pub fn status(&'a mut self) -> StatusService<'a> {
         StatusService { inner: self }
     }
 
-    pub fn volume(&'a mut self) -> VolumeService {
+    pub fn volume(&'a mut self) -> VolumeService<'a> {
         VolumeService { inner: self }
     }
+
+    pub fn outputs(&'a mut self) -> OutputService<'a> {
+        OutputService { inner: self }
+    }
+
+    pub fn partitions(&'a mut self) -> PartitionService<'a> {
+        PartitionService { inner: self }
+    }
+
+    pub fn stickers(&'a mut self) -> StickerService<'a> {
+        StickerService { inner: self }
+    }
 }
 
 ///////////////////////////////////////////////////////////////////////////////
@@ -449,11 +1076,12 @@ mod tests {
         let file = DbItem::File {
             uri: "alfa/test/beta/test.flac".to_owned(),
             duration: Duration::seconds(25),
-            tags: DbTags {
+            tags: Box::new(DbTags {
                 titles: vec!["Test".to_owned()],
                 artists: vec!["Test".to_owned()],
                 albums: vec![],
-            },
+                ..Default::default()
+            }),
             format: None,
             updated_at: "2023-10-01T00:39:58Z".to_owned(),
         };
@@ -461,7 +1089,7 @@ mod tests {
         let actual = uri_matches(file.clone(), "tes").unwrap();
 
         let expected = vec![
-            DbItem::Directory { uri: "alfa/test".to_owned() },
+            DbItem::Directory { uri: "alfa/test".to_owned(), updated_at: None },
             file,
         ];
 
@@ -473,11 +1101,12 @@ mod tests {
         let file = DbItem::File {
             uri: "alfa/test/beta/test.flac".to_owned(),
             duration: Duration::seconds(55),
-            tags: DbTags {
+            tags: Box::new(DbTags {
                 titles: vec!["tseT".to_owned()],
                 artists: vec!["tseT".to_owned()],
                 albums: vec![],
-            },
+                ..Default::default()
+            }),
             format: None,
             updated_at: "2023-10-01T00:39:58Z".to_owned(),
         };
@@ -490,8 +1119,81 @@ mod tests {
     #[test]
     #[should_panic]
     fn should_panic_uri_matches() {
-        let directory = DbItem::Directory { uri: "dir".to_owned() };
+        let directory = DbItem::Directory { uri: "dir".to_owned(), updated_at: None };
 
         uri_matches(directory, "dir").unwrap();
     }
+
+    #[test]
+    fn should_group_items_by_album() {
+        let file = |uri: &str, artist: &str, album: Option<&str>, updated_at: &str| DbItem::File {
+            uri: uri.to_owned(),
+            duration: Duration::seconds(25),
+            tags: Box::new(DbTags {
+                artists: vec![artist.to_owned()],
+                albums: album.map(ToOwned::to_owned).into_iter().collect(),
+                ..Default::default()
+            }),
+            format: None,
+            updated_at: updated_at.to_owned(),
+        };
+
+        let items = vec![
+            file("alfa/beta/2.flac", "Alfa", Some("Beta"), "2023-10-02T00:00:00Z"),
+            file("misc/1.flac", "Alfa", None, "2023-10-01T12:00:00Z"),
+            file("alfa/beta/1.flac", "Alfa", Some("Beta"), "2023-10-01T00:00:00Z"),
+            file("hits/1.flac", "Charlie", Some("Hits"), "2023-09-30T00:00:00Z"),
+            file("hits/2.flac", "Delta", Some("Hits"), "2023-09-29T00:00:00Z"),
+            file("more-hits/1.flac", "Echo", Some("Hits"), "2023-09-28T00:00:00Z"),
+        ];
+
+        let actual = group_items(items, DbGroupKind::Album);
+
+        let expected = vec![
+            DbGroup {
+                kind: DbGroupKind::Album,
+                name: "Beta".to_owned(),
+                artist: Some("Alfa".to_owned()),
+                uri: "alfa/beta".to_owned(),
+                count: 2,
+                updated_at: "2023-10-02T00:00:00Z".to_owned(),
+            },
+            DbGroup {
+                kind: DbGroupKind::Directory,
+                name: "misc".to_owned(),
+                artist: None,
+                uri: "misc".to_owned(),
+                count: 1,
+                updated_at: "2023-10-01T12:00:00Z".to_owned(),
+            },
+            DbGroup {
+                kind: DbGroupKind::Album,
+                name: "Hits".to_owned(),
+                artist: None,
+                uri: "hits".to_owned(),
+                count: 2,
+                updated_at: "2023-09-30T00:00:00Z".to_owned(),
+            },
+            DbGroup {
+                kind: DbGroupKind::Album,
+                name: "Hits".to_owned(),
+                artist: Some("Echo".to_owned()),
+                uri: "more-hits".to_owned(),
+                count: 1,
+                updated_at: "2023-09-28T00:00:00Z".to_owned(),
+            },
+        ];
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn should_sniff_cover_art_content_type() {
+        assert_eq!(sniff_content_type(&[0xFF, 0xD8, 0xFF, 0xE0]), Some("image/jpeg"));
+        assert_eq!(sniff_content_type(b"\x89PNG\r\n\x1a\n...."), Some("image/png"));
+        assert_eq!(sniff_content_type(b"RIFF\x00\x10\x00\x00WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_content_type(b"GIF89a"), Some("image/gif"));
+        assert_eq!(sniff_content_type(b"\x00\x00\x00\x1cftypavif"), Some("image/avif"));
+        assert_eq!(sniff_content_type(b"not an image"), None);
+    }
 }
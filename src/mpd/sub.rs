@@ -1,6 +1,8 @@
 use tokio::sync::watch;
 
+use crate::mpd::data::Output;
 use crate::mpd::data::QueueItem;
+use crate::mpd::error::Error;
 use crate::mpd::data::Status;
 use crate::mpd::handle::Handle;
 use crate::mpd::result::Result;
@@ -33,10 +35,30 @@ impl Clone for SubscriptionHandle {
 
 impl SubscriptionHandle {
     pub async fn updates(&mut self) -> Result<Vec<Update>> {
-        self.updates_rx.changed().await.expect("updates sender is dropped");
+        if self.updates_rx.changed().await.is_err() {
+            return Err(Error::Unavailable("subscription is closed".to_owned()));
+        }
 
         self.updates_rx.borrow_and_update().clone()
     }
+
+    pub fn is_closed(&self) -> bool {
+        self.updates_rx.has_changed().is_err()
+    }
+
+    // Updates keep failing for as long as MPD is unreachable, so the first one that
+    // doesn't means that it's back. Returns false if there won't be any more updates.
+    pub async fn reconnected(&mut self) -> bool {
+        loop {
+            if self.updates().await.is_ok() {
+                return true;
+            }
+
+            if self.is_closed() {
+                return false;
+            }
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +67,8 @@ pub enum Update {
     Playlists,
     Status(Status),
     Queue(Vec<QueueItem>),
+    Outputs(Vec<Output>),
+    Stickers,
 }
 
 mod recv {
@@ -113,6 +137,14 @@ mod recv {
                 updates.push(Update::Queue(self.handle.queue().get().await?));
             }
 
+            if changes.contains(&Subsystem::Output) {
+                updates.push(Update::Outputs(self.handle.outputs().list().await?));
+            }
+
+            if changes.contains(&Subsystem::Sticker) {
+                updates.push(Update::Stickers);
+            }
+
             Ok(updates)
         }
     }
@@ -121,8 +153,14 @@ mod recv {
         loop {
             let updates = recv_loop.updates().await;
 
-            recv_loop.updates_tx.send(updates)
-                .expect("expected to be able to propagate updates");
+            if recv_loop.handle.is_closed() {
+                break;
+            }
+
+            // Stop once every subscriber is gone.
+            if recv_loop.updates_tx.send(updates).is_err() {
+                break;
+            }
         }
     }
 }
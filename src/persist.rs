@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use std::str::FromStr;
 
+use futures_util::TryStreamExt;
 use sqlx::ConnectOptions;
 use sqlx::migrate::Migrator;
 use sqlx::sqlite::SqliteConnectOptions;
@@ -10,6 +11,8 @@ use sqlx::SqlitePool;
 use time::Duration;
 use time::format_description::well_known::Iso8601;
 use time::OffsetDateTime;
+use time::UtcOffset;
+use time::Weekday;
 use tracing::log::LevelFilter;
 
 use crate::convert::IntoResult;
@@ -20,8 +23,25 @@ use crate::persist::repo::DbItemLabelRow;
 use crate::persist::repo::CreatePlaybackHistoryEventRow;
 use crate::persist::repo::CreatePlaybackHistoryMetadataRow;
 use crate::persist::repo::IdRow;
+use crate::persist::repo::CoverRow;
+pub use crate::persist::repo::CoverThumbnailFormat;
+use crate::persist::repo::CoverThumbnailRow;
+use crate::persist::repo::CreateCoverPlaceholderRow;
+use crate::persist::repo::CreateCoverRow;
+use crate::persist::repo::CreateCoverThumbnailRow;
+use crate::persist::repo::CreateLibraryItemRow;
+use crate::persist::repo::CreateLibraryItemTagRow;
+pub use crate::persist::repo::CoverKind;
+pub use crate::persist::repo::CoverStrategy;
+pub use crate::persist::repo::LibraryItemKind;
+use crate::persist::repo::LibraryItemRow;
 pub use crate::persist::repo::PlaybackHistoryEventKind;
+pub use crate::persist::repo::ScrobbleId;
+use crate::persist::repo::PlaybackHistoryCheckpointRow;
 use crate::persist::repo::PlaybackHistoryEventRow;
+use crate::persist::repo::PlaybackHistoryPlayEventRow;
+use crate::persist::repo::CreateScrobbleRow;
+use crate::persist::repo::ScrobbleRow;
 use crate::persist::repo::PlaybackHistoryMetadataRow;
 pub use crate::persist::repo::PlaybackHistoryPlayId;
 use crate::persist::repo::Pool;
@@ -36,6 +56,7 @@ mod result;
 
 pub struct PlaybackHistoryEventHandle<'a> {
     inner: &'a Handle,
+    server: &'a str,
 }
 
 pub struct CreatePlaybackHistoryEvent {
@@ -45,7 +66,7 @@ pub struct CreatePlaybackHistoryEvent {
     pub recorded_at: OffsetDateTime,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PlaybackHistoryEvent {
     pub play_id: i64,
     pub elapsed: Duration,
@@ -53,16 +74,19 @@ pub struct PlaybackHistoryEvent {
     pub recorded_at: OffsetDateTime,
 }
 
+// Stored timestamps are compared as strings, which only works as long as they're all in UTC.
 fn format_iso8601(datetime: OffsetDateTime) -> std::result::Result<String, String> {
-    datetime.format(&Iso8601::DEFAULT)
+    datetime.to_offset(UtcOffset::UTC)
+        .format(&Iso8601::DEFAULT)
         .map_err(|err| format!("failed to format date: {err}"))
 }
 
-impl TryFrom<CreatePlaybackHistoryEvent> for CreatePlaybackHistoryEventRow {
+impl TryFrom<(&str, CreatePlaybackHistoryEvent)> for CreatePlaybackHistoryEventRow {
     type Error = String;
 
-    fn try_from(create: CreatePlaybackHistoryEvent) -> std::result::Result<Self, Self::Error> {
+    fn try_from((server, create): (&str, CreatePlaybackHistoryEvent)) -> std::result::Result<Self, Self::Error> {
         CreatePlaybackHistoryEventRow {
+            server: server.to_owned(),
             play_id: create.play_id,
             elapsed: create.elapsed.as_seconds_f64(),
             kind: create.kind,
@@ -85,12 +109,171 @@ impl TryFrom<PlaybackHistoryEventRow> for PlaybackHistoryEvent {
     }
 }
 
+// Where a play was last known to be, kept up to date while it's going on.
+#[derive(Debug)]
+pub struct PlaybackHistoryCheckpoint {
+    pub play_id: PlaybackHistoryPlayId,
+    pub elapsed: Duration,
+    pub checkpointed_at: OffsetDateTime,
+}
+
+impl TryFrom<PlaybackHistoryCheckpointRow> for PlaybackHistoryCheckpoint {
+    type Error = String;
+
+    fn try_from(row: PlaybackHistoryCheckpointRow) -> std::result::Result<Self, Self::Error> {
+        PlaybackHistoryCheckpoint {
+            play_id: row.play_id,
+            elapsed: Duration::seconds_f64(row.elapsed),
+            checkpointed_at: OffsetDateTime::parse(&row.checkpointed_at, &Iso8601::DEFAULT)
+                .map_err(|err| format!("failed to parse checkpointed_at timestamp: {err}"))?,
+        }.into_ok()
+    }
+}
+
+#[derive(Debug, Default, Copy, Clone)]
+pub struct PlaybackHistoryTally {
+    pub plays: i64,
+    pub listened: Duration,
+}
+
+#[derive(Debug)]
+pub struct PlaybackHistoryTotals {
+    pub tally: PlaybackHistoryTally,
+    pub tracks: i64,
+    pub artists: i64,
+    pub albums: i64,
+}
+
+#[derive(Debug)]
+pub struct PlaybackHistoryArtist {
+    pub artist: String,
+    pub tally: PlaybackHistoryTally,
+}
+
+#[derive(Debug)]
+pub struct PlaybackHistoryAlbum {
+    pub album: String,
+    pub album_artist: Option<String>,
+    pub tally: PlaybackHistoryTally,
+}
+
+#[derive(Debug)]
+pub struct PlaybackHistoryTrack {
+    pub uri: String,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub tally: PlaybackHistoryTally,
+}
+
+#[derive(Debug)]
+pub struct PlaybackHistoryStats {
+    pub totals: PlaybackHistoryTotals,
+    pub artists: Vec<PlaybackHistoryArtist>,
+    pub albums: Vec<PlaybackHistoryAlbum>,
+    pub tracks: Vec<PlaybackHistoryTrack>,
+    pub hours: Vec<(u8, PlaybackHistoryTally)>,
+    pub days: Vec<(String, PlaybackHistoryTally)>,
+    pub weekdays: Vec<(Weekday, PlaybackHistoryTally)>,
+}
+
+// What was played, as far as stats are concerned.
+#[derive(Debug)]
+pub struct PlaybackHistorySong {
+    pub uri: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub artists: Vec<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct PlaybackHistoryPlay {
+    pub play_id: PlaybackHistoryPlayId,
+    pub started_at: OffsetDateTime,
+    pub duration: Duration,
+    pub song: PlaybackHistorySong,
+    pub events: Vec<PlaybackHistoryEvent>,
+}
+
+impl TryFrom<PlaybackHistoryPlayEventRow> for PlaybackHistoryPlay {
+    type Error = String;
+
+    fn try_from(row: PlaybackHistoryPlayEventRow) -> std::result::Result<Self, Self::Error> {
+        // Artists are separated by line breaks, which tag values can't contain.
+        let mut artists = row.artists
+            .map(|artists| artists.split('\n').map(ToOwned::to_owned).collect::<Vec<_>>())
+            .unwrap_or_default();
+
+        artists.sort();
+        artists.dedup();
+
+        PlaybackHistoryPlay {
+            play_id: row.play_id,
+            started_at: OffsetDateTime::parse(&row.started_at, &Iso8601::DEFAULT)
+                .map_err(|err| format!("failed to parse started_at timestamp: {err}"))?,
+            duration: row.duration.map_or(Duration::ZERO, Duration::seconds_f64),
+            song: PlaybackHistorySong {
+                uri: row.uri,
+                title: row.title,
+                artist: row.artist,
+                artists,
+                album: row.album,
+                album_artist: row.album_artist,
+            },
+            events: vec![],
+        }.into_ok()
+    }
+}
+
 impl<'a> PlaybackHistoryEventHandle<'a> {
+    // Plays started within the given period are handed over one at a time, so that
+    // only one of them is held in memory no matter how long the period is.
+    pub async fn for_each_play<F>(&self, from: OffsetDateTime, to: OffsetDateTime, mut f: F) -> Result<()>
+        where F: FnMut(PlaybackHistoryPlay)
+    {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let from = format_iso8601(from)?;
+        let to = format_iso8601(to)?;
+
+        let mut repo = repo.playback_history_event();
+
+        let mut rows = repo.get_all_plays(self.server, &from, &to);
+
+        let mut current: Option<PlaybackHistoryPlay> = None;
+
+        while let Some(row) = rows.try_next().await? {
+            let event = PlaybackHistoryEvent::try_from(PlaybackHistoryEventRow {
+                play_id: row.play_id,
+                elapsed: row.elapsed,
+                kind: row.kind,
+                recorded_at: row.recorded_at.clone(),
+            })?;
+
+            if current.as_ref().is_none_or(|play| play.play_id != row.play_id) {
+                if let Some(play) = current.replace(row.try_into()?) {
+                    f(play);
+                }
+            }
+
+            if let Some(play) = &mut current {
+                play.events.push(event);
+            }
+        }
+
+        if let Some(play) = current {
+            f(play);
+        }
+
+        Ok(())
+    }
+
     pub async fn get_latest(&self) -> Result<Option<PlaybackHistoryEvent>> {
         let mut repo = self.inner.pool.acquire().await?;
 
         let result = repo.playback_history_event()
-            .get_latest()
+            .get_latest(self.server)
             .await?
             .map(TryInto::try_into)
             .transpose()?;
@@ -105,7 +288,20 @@ impl<'a> PlaybackHistoryEventHandle<'a> {
         let to = to.map(format_iso8601).transpose()?;
 
         let result = repo.playback_history_event()
-            .get_all(from.as_deref(), to.as_deref())
+            .get_all(self.server, from.as_deref(), to.as_deref())
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(result)
+    }
+
+    pub async fn get_all_by_play_id(&self, play_ids: &[PlaybackHistoryPlayId]) -> Result<Vec<PlaybackHistoryEvent>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.playback_history_event()
+            .get_all_by_play_id(self.server, play_ids)
             .await?
             .into_iter()
             .map(TryInto::try_into)
@@ -118,7 +314,7 @@ impl<'a> PlaybackHistoryEventHandle<'a> {
         let mut repo = self.inner.pool.begin().await?;
 
         let IdRow { id } = repo.playback_history_event()
-            .create(create.try_into()?)
+            .create((self.server, create).try_into()?)
             .await?;
 
         let result = repo.playback_history_event()
@@ -136,7 +332,7 @@ impl<'a> PlaybackHistoryEventHandle<'a> {
 
         let ids = repo.playback_history_event().create_all(
             create.into_iter()
-                .map(TryInto::try_into)
+                .map(|create| (self.server, create).try_into())
                 .collect::<std::result::Result<_, _>>()?
         ).await?.into_iter().map(|IdRow { id }| id).collect::<Vec<_>>();
 
@@ -151,6 +347,35 @@ impl<'a> PlaybackHistoryEventHandle<'a> {
 
         Ok(result)
     }
+
+    pub async fn get_checkpoint(&self) -> Result<Option<PlaybackHistoryCheckpoint>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.playback_history_event()
+            .get_checkpoint(self.server)
+            .await?
+            .map(TryInto::try_into)
+            .transpose()?;
+
+        Ok(result)
+    }
+
+    pub async fn put_checkpoint(&self, play_id: PlaybackHistoryPlayId, elapsed: Duration) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        repo.playback_history_event()
+            .create_or_replace_checkpoint(
+                self.server,
+                play_id,
+                elapsed.as_seconds_f64(),
+                &format_iso8601(OffsetDateTime::now_utc())?,
+            )
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
 }
 
 // </editor-fold>
@@ -159,6 +384,7 @@ impl<'a> PlaybackHistoryEventHandle<'a> {
 
 pub struct PlaybackHistoryMetadataHandle<'a> {
     inner: &'a Handle,
+    server: &'a str,
 }
 
 pub struct CreatePlaybackHistoryMetadata {
@@ -178,45 +404,47 @@ pub struct PlaybackHistoryMetadata {
     pub tags: DbTags,
 }
 
-impl From<CreatePlaybackHistoryMetadata> for Vec<CreatePlaybackHistoryMetadataRow> {
-    fn from(create: CreatePlaybackHistoryMetadata) -> Self {
-        let tags = |key: String, vec: Vec<String>| -> Vec<CreatePlaybackHistoryMetadataRow> {
-            vec.into_iter().map(|value| {
+fn into_metadata_rows(server: &str, create: CreatePlaybackHistoryMetadata) -> Vec<CreatePlaybackHistoryMetadataRow> {
+    // Tags are keyed by their lowercase MPD name.
+    let tags = create.tags.into_entries()
+        .into_iter()
+        .flat_map(|(name, values)| {
+            let key = name.to_lowercase();
+
+            values.into_iter().map(move |value| {
                 CreatePlaybackHistoryMetadataRow {
+                    server: server.to_owned(),
                     play_id: create.play_id,
                     key: key.clone(),
                     value,
                 }
-            }).collect()
-        };
-
-        let titles = tags("title".to_owned(), create.tags.titles);
-        let artists = tags("artist".to_owned(), create.tags.artists);
-        let albums = tags("album".to_owned(), create.tags.albums);
+            })
+        })
+        .collect::<Vec<_>>();
 
+    vec![
         vec![
-            vec![
-                CreatePlaybackHistoryMetadataRow {
-                    play_id: create.play_id,
-                    key: "uri".to_owned(),
-                    value: create.uri,
-                },
-                CreatePlaybackHistoryMetadataRow {
-                    play_id: create.play_id,
-                    key: "playlist_id".to_owned(),
-                    value: create.playlist_id.to_string(),
-                },
-                CreatePlaybackHistoryMetadataRow {
-                    play_id: create.play_id,
-                    key: "duration".to_owned(),
-                    value: create.duration.as_seconds_f64().to_string(),
-                },
-            ],
-            titles,
-            artists,
-            albums,
-        ].concat()
-    }
+            CreatePlaybackHistoryMetadataRow {
+                server: server.to_owned(),
+                play_id: create.play_id,
+                key: "uri".to_owned(),
+                value: create.uri,
+            },
+            CreatePlaybackHistoryMetadataRow {
+                server: server.to_owned(),
+                play_id: create.play_id,
+                key: "playlist_id".to_owned(),
+                value: create.playlist_id.to_string(),
+            },
+            CreatePlaybackHistoryMetadataRow {
+                server: server.to_owned(),
+                play_id: create.play_id,
+                key: "duration".to_owned(),
+                value: create.duration.as_seconds_f64().to_string(),
+            },
+        ],
+        tags,
+    ].concat()
 }
 
 impl TryFrom<Vec<PlaybackHistoryMetadataRow>> for PlaybackHistoryMetadata {
@@ -240,11 +468,7 @@ impl TryFrom<Vec<PlaybackHistoryMetadataRow>> for PlaybackHistoryMetadata {
             uri: map.remove("uri").ok_or("uri is missing")?.swap_remove(0),
             duration: map.remove("duration").ok_or("duration is missing")?
                 .swap_remove(0).parse().map(Duration::seconds_f64).map_err(|e| format!("cannot parse duration as f64: {e}"))?,
-            tags: DbTags {
-                titles: map.remove("title").unwrap_or_default(),
-                artists: map.remove("artist").unwrap_or_default(),
-                albums: map.remove("album").unwrap_or_default(),
-            }
+            tags: DbTags::from_entries(map),
         }.into_ok()
     }
 }
@@ -256,11 +480,11 @@ impl<'a> PlaybackHistoryMetadataHandle<'a> {
         let play_id = create.play_id;
 
         repo.playback_history_metadata()
-            .create_all(create.into())
+            .create_all(into_metadata_rows(self.server, create))
             .await?;
 
         let result = repo.playback_history_metadata()
-            .get_by_play_id(play_id)
+            .get_by_play_id(self.server, play_id)
             .await?
             .try_into()?;
 
@@ -273,7 +497,7 @@ impl<'a> PlaybackHistoryMetadataHandle<'a> {
         let mut repo = self.inner.pool.acquire().await?;
 
         let result = repo.playback_history_metadata()
-            .get_by_play_id(play_id)
+            .get_by_play_id(self.server, play_id)
             .await?
             .try_into()?;
 
@@ -287,7 +511,7 @@ impl<'a> PlaybackHistoryMetadataHandle<'a> {
         let mut repo = self.inner.pool.acquire().await?;
 
         let rows = repo.playback_history_metadata()
-            .get_all_by_play_id(play_ids)
+            .get_all_by_play_id(self.server, play_ids)
             .await?;
 
         let mut map: HashMap<_, Vec<_>> = HashMap::new();
@@ -407,6 +631,640 @@ impl<'a> DbItemLabelHandle<'a> {
 
 // </editor-fold>
 
+// <editor-fold desc="Library Item">
+
+pub struct LibraryItemHandle<'a> {
+    inner: &'a Handle,
+    server: &'a str,
+}
+
+#[derive(Debug)]
+pub struct LibraryItem {
+    pub kind: LibraryItemKind,
+    pub uri: String,
+    pub tags: DbTags,
+    pub format: Option<String>,
+    pub duration: Duration,
+    pub updated_at: Option<String>,
+}
+
+// Multiple tag values share a column, the separator doesn't survive tokenization.
+const LIBRARY_TAG_SEPARATOR: &str = "\n";
+
+fn join_tags(values: &[String]) -> String {
+    values.join(LIBRARY_TAG_SEPARATOR)
+}
+
+fn split_tags(value: String) -> Vec<String> {
+    if value.is_empty() {
+        return vec![];
+    }
+
+    value.split(LIBRARY_TAG_SEPARATOR)
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+// The searchable tags get columns of their own, every tag is also kept by its lowercase MPD name.
+fn into_library_item_rows(server: &str, item: LibraryItem) -> (CreateLibraryItemRow, Vec<CreateLibraryItemTagRow>) {
+    let (parent, name) = item.uri.rsplit_once('/')
+        .unwrap_or(("", item.uri.as_str()));
+
+    let item_row = CreateLibraryItemRow {
+        server: server.to_owned(),
+        kind: item.kind,
+        parent: parent.to_owned(),
+        name: name.to_owned(),
+        uri: item.uri.clone(),
+        title: join_tags(&item.tags.titles),
+        artist: join_tags(&item.tags.artists),
+        album: join_tags(&item.tags.albums),
+        album_artist: join_tags(&item.tags.album_artists),
+        composer: join_tags(&item.tags.composers),
+        genre: join_tags(&item.tags.genres),
+        date: join_tags(&item.tags.dates),
+        format: item.format,
+        duration: item.duration.as_seconds_f64(),
+        updated_at: item.updated_at,
+    };
+
+    let tags = item.tags
+        .into_entries()
+        .into_iter()
+        .flat_map(|(name, values)| {
+            let key = name.to_lowercase();
+
+            values.into_iter().map(move |value| (key.clone(), value))
+        })
+        .map(|(key, value)| {
+            CreateLibraryItemTagRow {
+                server: server.to_owned(),
+                uri: item.uri.clone(),
+                key,
+                value,
+            }
+        })
+        .collect();
+
+    (item_row, tags)
+}
+
+impl From<LibraryItemRow> for LibraryItem {
+    fn from(row: LibraryItemRow) -> Self {
+        LibraryItem {
+            kind: row.kind,
+            uri: row.uri,
+            tags: DbTags {
+                titles: split_tags(row.title),
+                artists: split_tags(row.artist),
+                albums: split_tags(row.album),
+                album_artists: split_tags(row.album_artist),
+                composers: split_tags(row.composer),
+                genres: split_tags(row.genre),
+                dates: split_tags(row.date),
+                ..Default::default()
+            },
+            format: row.format,
+            duration: Duration::seconds_f64(row.duration),
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+impl<'a> LibraryItemHandle<'a> {
+    // Expects a prepared FTS5 match expression.
+    pub async fn search(&self, query: &str, offset: usize, limit: usize) -> Result<(Vec<LibraryItem>, usize)> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let items = repo.library_item()
+            .search(
+                self.server,
+                query,
+                i64::try_from(limit).unwrap_or(i64::MAX),
+                i64::try_from(offset).unwrap_or(i64::MAX),
+            )
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        let total = repo.library_item()
+            .count(self.server, query)
+            .await?;
+
+        drop(repo);
+
+        Ok((self.with_all_tags(items).await?, total as usize))
+    }
+
+    // Items only come with their searchable tags otherwise.
+    pub async fn with_all_tags(&self, mut items: Vec<LibraryItem>) -> Result<Vec<LibraryItem>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let uris = items.iter()
+            .map(|item| item.uri.as_str())
+            .collect::<Vec<_>>();
+
+        let mut entries: HashMap<String, Vec<(String, Vec<String>)>> = HashMap::new();
+
+        for row in repo.library_item().get_all_tags(self.server, &uris).await? {
+            entries.entry(row.uri)
+                .or_default()
+                .push((row.key, vec![row.value]));
+        }
+
+        for item in &mut items {
+            if let Some(entries) = entries.remove(&item.uri) {
+                item.tags = DbTags::from_entries(entries);
+            }
+        }
+
+        Ok(items)
+    }
+
+    // Expects a prepared FTS5 match expression.
+    pub async fn get_all_matching(&self, query: &str) -> Result<Vec<LibraryItem>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.library_item()
+            .get_all_matching(self.server, query)
+            .await?
+            .into_iter()
+            .map(Into::into)
+            .collect();
+
+        Ok(result)
+    }
+
+    pub async fn get_terms(&self) -> Result<Vec<String>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.library_item()
+            .get_terms()
+            .await?;
+
+        Ok(result)
+    }
+
+    // Returns the URI and modification time of every item directly within a directory.
+    pub async fn get_all_by_parent(&self, parent: &str) -> Result<Vec<(String, Option<String>)>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.library_item()
+            .get_all_by_parent(self.server, parent)
+            .await?
+            .into_iter()
+            .map(|row| (row.uri, row.updated_at))
+            .collect();
+
+        Ok(result)
+    }
+
+    // Changed items replace what was there, removed directories take their contents with them.
+    pub async fn update_all(&self, changed: Vec<LibraryItem>, removed: Vec<String>) -> Result<()> {
+        if changed.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+
+        let mut repo = self.inner.pool.begin().await?;
+
+        let uris = changed.iter()
+            .map(|item| item.uri.as_str())
+            .chain(removed.iter().map(String::as_str))
+            .collect::<Vec<_>>();
+
+        repo.library_item()
+            .delete_all_by_uri(self.server, &uris)
+            .await?;
+
+        for uri in &removed {
+            repo.library_item()
+                .delete_all_descendants(self.server, uri)
+                .await?;
+
+            repo.cover()
+                .delete_all_under(self.server, uri)
+                .await?;
+        }
+
+        // Cover files come and go along with their directory's modification time, embedded covers
+        // with their song's. Stale covers are validated against MPD the next time they're served,
+        // placeholders are made again by the indexer.
+        let covers = changed.iter()
+            .map(|item| match item.kind {
+                LibraryItemKind::File => (CoverKind::Embedded, item.uri.as_str()),
+                LibraryItemKind::Directory => (CoverKind::File, item.uri.as_str()),
+            })
+            .collect::<Vec<_>>();
+
+        let directories = changed.iter()
+            .filter(|item| item.kind == LibraryItemKind::Directory)
+            .map(|item| item.uri.as_str())
+            .collect::<Vec<_>>();
+
+        repo.cover()
+            .set_all_stale(self.server, &covers)
+            .await?;
+
+        repo.cover()
+            .delete_all_placeholders(self.server, &covers)
+            .await?;
+
+        repo.cover()
+            .delete_all_strategies(self.server, &directories)
+            .await?;
+
+        let (items, tags): (Vec<_>, Vec<_>) = changed.into_iter()
+            .map(|item| into_library_item_rows(self.server, item))
+            .unzip();
+
+        repo.library_item()
+            .create_all(items)
+            .await?;
+
+        repo.library_item()
+            .create_all_tags(tags.into_iter().flatten().collect())
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn set_synced(&self) -> Result<()> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        repo.library_item()
+            .set_synced_at(self.server, format_iso8601(OffsetDateTime::now_utc())?)
+            .await?;
+
+        Ok(())
+    }
+
+    pub async fn get_synced_at(&self) -> Result<Option<OffsetDateTime>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.library_item()
+            .get_synced_at(self.server)
+            .await?
+            .map(|synced_at| {
+                OffsetDateTime::parse(&synced_at, &Iso8601::DEFAULT)
+                    .map_err(|err| format!("failed to parse synced_at timestamp: {err}"))
+            })
+            .transpose()?;
+
+        Ok(result)
+    }
+}
+
+// </editor-fold>
+
+// <editor-fold desc="Cover">
+
+pub struct CoverHandle<'a> {
+    inner: &'a Handle,
+    server: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub struct CoverPlaceholder {
+    pub blurhash: String,
+    pub dominant_color: String,
+}
+
+#[derive(Debug)]
+pub struct Cover {
+    pub last_modified: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub stale: bool,
+}
+
+#[derive(Debug)]
+pub struct CoverThumbnail {
+    pub source_modified_at: i64,
+    pub data: Vec<u8>,
+}
+
+impl From<CoverRow> for Cover {
+    fn from(row: CoverRow) -> Self {
+        Cover {
+            last_modified: row.last_modified,
+            content_type: row.content_type,
+            data: row.data,
+            stale: row.stale,
+        }
+    }
+}
+
+impl From<CoverThumbnailRow> for (String, CoverThumbnail) {
+    fn from(row: CoverThumbnailRow) -> Self {
+        let thumbnail = CoverThumbnail {
+            source_modified_at: row.source_modified_at,
+            data: row.data,
+        };
+
+        (row.last_modified, thumbnail)
+    }
+}
+
+// Keeps the number of bound parameters per statement well below SQLite's limit.
+const COVER_PLACEHOLDERS_CHUNK_SIZE: usize = 1000;
+
+impl<'a> CoverHandle<'a> {
+    pub async fn get(&self, kind: CoverKind, key: &str) -> Result<Option<Cover>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.cover()
+            .get(self.server, kind, key)
+            .await?
+            .map(Into::into);
+
+        Ok(result)
+    }
+
+    pub async fn get_all_placeholders(
+        &self,
+        keys: &[(CoverKind, &str)],
+    ) -> Result<HashMap<(CoverKind, String), CoverPlaceholder>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let mut result = HashMap::new();
+
+        for keys in keys.chunks(COVER_PLACEHOLDERS_CHUNK_SIZE) {
+            let rows = repo.cover()
+                .get_all_placeholders(self.server, keys)
+                .await?;
+
+            for row in rows {
+                result.insert(
+                    (row.kind, row.key),
+                    CoverPlaceholder { blurhash: row.blurhash, dominant_color: row.dominant_color },
+                );
+            }
+        }
+
+        Ok(result)
+    }
+
+    // Along with when the cover it was made from was last modified.
+    pub async fn get_thumbnail(
+        &self,
+        kind: CoverKind,
+        key: &str,
+        size: u32,
+        format: CoverThumbnailFormat,
+    ) -> Result<Option<(String, CoverThumbnail)>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.cover()
+            .get_thumbnail(self.server, kind, key, size, format)
+            .await?
+            .map(Into::into);
+
+        Ok(result)
+    }
+
+    pub async fn put_thumbnail(
+        &self,
+        kind: CoverKind,
+        key: &str,
+        size: u32,
+        format: CoverThumbnailFormat,
+        thumbnail: CoverThumbnail,
+    ) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        repo.cover()
+            .create_or_replace_thumbnail(CreateCoverThumbnailRow {
+                server: self.server.to_owned(),
+                kind,
+                key: key.to_owned(),
+                size,
+                format,
+                source_modified_at: thumbnail.source_modified_at,
+                data: thumbnail.data,
+                created_at: format_iso8601(OffsetDateTime::now_utc())?,
+            })
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+
+    // The cache is shared by all servers, so is its size.
+    pub async fn put(&self, kind: CoverKind, key: &str, cover: Cover, max_size: u64) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        repo.cover()
+            .create_or_replace(CreateCoverRow {
+                server: self.server.to_owned(),
+                kind,
+                key: key.to_owned(),
+                last_modified: cover.last_modified,
+                content_type: cover.content_type,
+                data: cover.data,
+                cached_at: format_iso8601(OffsetDateTime::now_utc())?,
+            })
+            .await?;
+
+        repo.cover()
+            .delete_least_recently_accessed(i64::try_from(max_size).unwrap_or(i64::MAX))
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn put_placeholder(&self, kind: CoverKind, key: &str, placeholder: CoverPlaceholder) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        repo.cover()
+            .create_or_replace_placeholder(CreateCoverPlaceholderRow {
+                server: self.server.to_owned(),
+                kind,
+                key: key.to_owned(),
+                blurhash: placeholder.blurhash,
+                dominant_color: placeholder.dominant_color,
+                created_at: format_iso8601(OffsetDateTime::now_utc())?,
+            })
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+
+    // Marks the cover as served and valid.
+    pub async fn refresh(&self, kind: CoverKind, key: &str) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        repo.cover()
+            .touch(self.server, kind, key, &format_iso8601(OffsetDateTime::now_utc())?)
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn get_strategy(&self, directory: &str) -> Result<Option<CoverStrategy>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.cover()
+            .get_strategy(self.server, directory)
+            .await?;
+
+        Ok(result)
+    }
+
+    pub async fn put_strategy(&self, directory: &str, strategy: CoverStrategy) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        repo.cover()
+            .create_or_replace_strategy(
+                self.server,
+                directory,
+                strategy,
+                &format_iso8601(OffsetDateTime::now_utc())?,
+            )
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+}
+
+// </editor-fold>
+
+// <editor-fold desc="Scrobble">
+
+pub struct ScrobbleHandle<'a> {
+    inner: &'a Handle,
+    server: &'a str,
+}
+
+#[derive(Debug, Clone)]
+pub struct Scrobble {
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub duration: Duration,
+    pub listened_at: OffsetDateTime,
+}
+
+#[derive(Debug)]
+pub struct QueuedScrobble {
+    pub id: ScrobbleId,
+    pub scrobble: Scrobble,
+}
+
+impl TryFrom<ScrobbleRow> for QueuedScrobble {
+    type Error = String;
+
+    fn try_from(row: ScrobbleRow) -> std::result::Result<Self, Self::Error> {
+        QueuedScrobble {
+            id: row.id,
+            scrobble: Scrobble {
+                artist: row.artist,
+                title: row.title,
+                album: row.album,
+                album_artist: row.album_artist,
+                duration: Duration::seconds_f64(row.duration),
+                listened_at: OffsetDateTime::parse(&row.listened_at, &Iso8601::DEFAULT)
+                    .map_err(|err| format!("failed to parse listened_at timestamp: {err}"))?,
+            },
+        }.into_ok()
+    }
+}
+
+impl<'a> ScrobbleHandle<'a> {
+    pub async fn enqueue(&self, targets: &[&str], play_id: PlaybackHistoryPlayId, scrobble: Scrobble) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        let listened_at = format_iso8601(scrobble.listened_at)?;
+        let queued_at = format_iso8601(OffsetDateTime::now_utc())?;
+
+        let rows = targets.iter()
+            .map(|target| CreateScrobbleRow {
+                server: self.server.to_owned(),
+                target: (*target).to_owned(),
+                play_id,
+                artist: scrobble.artist.clone(),
+                title: scrobble.title.clone(),
+                album: scrobble.album.clone(),
+                album_artist: scrobble.album_artist.clone(),
+                duration: scrobble.duration.as_seconds_f64(),
+                listened_at: listened_at.clone(),
+                queued_at: queued_at.clone(),
+            })
+            .collect();
+
+        repo.scrobble()
+            .create_all(rows)
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+
+    // When the last play that was queued, whether it has been submitted since or not, was listened to.
+    pub async fn get_last_listened_at(&self) -> Result<Option<OffsetDateTime>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.scrobble()
+            .get_last_listened_at(self.server)
+            .await?
+            .map(|listened_at| {
+                OffsetDateTime::parse(&listened_at, &Iso8601::DEFAULT)
+                    .map_err(|err| format!("failed to parse listened_at timestamp: {err}"))
+            })
+            .transpose()?;
+
+        Ok(result)
+    }
+
+    // Oldest first, that's the order they have to be submitted in.
+    pub async fn get_queued(&self, target: &str, limit: usize) -> Result<Vec<QueuedScrobble>> {
+        let mut repo = self.inner.pool.acquire().await?;
+
+        let result = repo.scrobble()
+            .get_all(self.server, target, i64::try_from(limit).unwrap_or(i64::MAX))
+            .await?
+            .into_iter()
+            .map(TryInto::try_into)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+
+        Ok(result)
+    }
+
+    // Submitted scrobbles are done with, except for the last one which marks where to catch up from.
+    pub async fn set_submitted(&self, ids: &[ScrobbleId]) -> Result<()> {
+        let mut repo = self.inner.pool.begin().await?;
+
+        let submitted_at = format_iso8601(OffsetDateTime::now_utc())?;
+
+        repo.scrobble()
+            .set_all_submitted(ids, &submitted_at)
+            .await?;
+
+        repo.scrobble()
+            .delete_all_submitted_but_last(self.server)
+            .await?;
+
+        repo.commit().await?;
+
+        Ok(())
+    }
+}
+
+// </editor-fold>
+
 #[derive(Clone)]
 pub struct Handle {
     pool: Pool,
@@ -427,6 +1285,21 @@ pub async fn init(path: PathBuf) -> Result<Handle> {
     Handle::new(pool).into_ok()
 }
 
+// Every connection to an in-memory database gets a database of its own, so there's only one.
+#[cfg(test)]
+pub async fn init_in_memory() -> Result<Handle> {
+    let options = SqliteConnectOptions::from_str("sqlite::memory:")?;
+
+    let pool = sqlx::sqlite::SqlitePoolOptions::new()
+        .max_connections(1)
+        .connect_with(options)
+        .await?;
+
+    MIGRATOR.run(&pool).await?;
+
+    Handle::new(pool).into_ok()
+}
+
 impl Handle {
     pub fn new(pool: SqlitePool) -> Self {
         Handle { pool: Pool::new(pool) }
@@ -434,15 +1307,27 @@ impl Handle {
 }
 
 impl Handle {
-    pub fn playback_history_event(&self) -> PlaybackHistoryEventHandle {
-        PlaybackHistoryEventHandle { inner: self }
+    pub fn playback_history_event<'a>(&'a self, server: &'a str) -> PlaybackHistoryEventHandle<'a> {
+        PlaybackHistoryEventHandle { inner: self, server }
     }
 
-    pub fn playback_history_metadata(&self) -> PlaybackHistoryMetadataHandle {
-        PlaybackHistoryMetadataHandle { inner: self }
+    pub fn playback_history_metadata<'a>(&'a self, server: &'a str) -> PlaybackHistoryMetadataHandle<'a> {
+        PlaybackHistoryMetadataHandle { inner: self, server }
     }
 
-    pub fn db_item_label(&self) -> DbItemLabelHandle {
+    pub fn db_item_label(&self) -> DbItemLabelHandle<'_> {
         DbItemLabelHandle { inner: self }
     }
+
+    pub fn library_item<'a>(&'a self, server: &'a str) -> LibraryItemHandle<'a> {
+        LibraryItemHandle { inner: self, server }
+    }
+
+    pub fn cover<'a>(&'a self, server: &'a str) -> CoverHandle<'a> {
+        CoverHandle { inner: self, server }
+    }
+
+    pub fn scrobble<'a>(&'a self, server: &'a str) -> ScrobbleHandle<'a> {
+        ScrobbleHandle { inner: self, server }
+    }
 }
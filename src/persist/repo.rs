@@ -1,7 +1,11 @@
+use futures_util::stream::BoxStream;
+use futures_util::StreamExt;
+use futures_util::TryStreamExt;
 use sqlx::FromRow;
 use sqlx::query;
 use sqlx::pool::PoolConnection;
 use sqlx::query_as;
+use sqlx::query_scalar;
 use sqlx::QueryBuilder;
 use sqlx::Sqlite;
 use sqlx::SqliteConnection;
@@ -45,12 +49,37 @@ pub struct PlaybackHistoryEventRow {
 }
 
 pub struct CreatePlaybackHistoryEventRow {
+    pub server: String,
     pub play_id: PlaybackHistoryPlayId,
     pub elapsed: f64,
     pub kind: PlaybackHistoryEventKind,
     pub recorded_at: String,
 }
 
+#[derive(FromRow)]
+pub struct PlaybackHistoryCheckpointRow {
+    pub play_id: PlaybackHistoryPlayId,
+    pub elapsed: f64,
+    pub checkpointed_at: String,
+}
+
+// An event of a play along with what was played, which repeats for every event of the play.
+#[derive(FromRow)]
+pub struct PlaybackHistoryPlayEventRow {
+    pub play_id: PlaybackHistoryPlayId,
+    pub elapsed: f64,
+    pub kind: PlaybackHistoryEventKind,
+    pub recorded_at: String,
+    pub started_at: String,
+    pub duration: Option<f64>,
+    pub uri: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub artists: Option<String>,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+}
+
 impl<'c> PlaybackHistoryEventRepository<'c> {
     pub async fn get_by_id(&mut self, id: PlaybackHistoryEventId) -> Result<PlaybackHistoryEventRow> {
         let sql = /* language=sql */ r#"
@@ -66,30 +95,34 @@ impl<'c> PlaybackHistoryEventRepository<'c> {
             .map_err(Into::into)
     }
 
-    pub async fn get_latest(&mut self) -> Result<Option<PlaybackHistoryEventRow>> {
+    pub async fn get_latest(&mut self, server: &str) -> Result<Option<PlaybackHistoryEventRow>> {
         let sql = /* language=sql */ r#"
             SELECT "play_id", "elapsed", "kind", "recorded_at"
             FROM "playback_history_events"
+            WHERE "server" = ?
             ORDER BY "recorded_at" DESC
             LIMIT 1
         "#;
 
         query_as(sql)
+            .bind(server)
             .fetch_optional(&mut *self.inner)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn get_all(&mut self, from: Option<&str>, to: Option<&str>) -> Result<Vec<PlaybackHistoryEventRow>> {
+    pub async fn get_all(&mut self, server: &str, from: Option<&str>, to: Option<&str>) -> Result<Vec<PlaybackHistoryEventRow>> {
         let sql = /* language=sql */ r#"
             SELECT "play_id", "elapsed", "kind", "recorded_at"
             FROM "playback_history_events"
-            WHERE (?1 IS NULL OR "recorded_at" >= ?1)
-              AND (?2 IS NULL OR "recorded_at" < ?2)
+            WHERE "server" = ?1
+              AND (?2 IS NULL OR "recorded_at" >= ?2)
+              AND (?3 IS NULL OR "recorded_at" < ?3)
             ORDER BY "recorded_at" DESC
         "#;
 
         query_as(sql)
+            .bind(server)
             .bind(from)
             .bind(to)
             .fetch_all(&mut *self.inner)
@@ -97,6 +130,56 @@ impl<'c> PlaybackHistoryEventRepository<'c> {
             .map_err(Into::into)
     }
 
+    // Plays belong to the period they were started in, all of their events come in the order they were recorded in.
+    pub fn get_all_plays<'a>(
+        &'a mut self,
+        server: &'a str,
+        from: &'a str,
+        to: &'a str,
+    ) -> BoxStream<'a, Result<PlaybackHistoryPlayEventRow>> {
+        let sql = /* language=sql */ r#"
+            WITH "plays" AS (
+                SELECT "play_id", MIN("recorded_at") AS "started_at"
+                FROM "playback_history_events"
+                WHERE "server" = ?1
+                GROUP BY "play_id"
+                HAVING "started_at" >= ?2 AND "started_at" < ?3
+            ),
+            "songs" AS (
+                SELECT "play_id",
+                       CAST(MIN(CASE WHEN "key" = 'duration' THEN "value" END) AS REAL) AS "duration",
+                       MIN(CASE WHEN "key" = 'uri' THEN "value" END) AS "uri",
+                       MIN(CASE WHEN "key" = 'title' THEN "value" END) AS "title",
+                       MIN(CASE WHEN "key" = 'artist' THEN "value" END) AS "artist",
+                       group_concat(CASE WHEN "key" = 'artist' THEN "value" END, char(10)) AS "artists",
+                       MIN(CASE WHEN "key" = 'album' THEN "value" END) AS "album",
+                       COALESCE(
+                           MIN(CASE WHEN "key" = 'albumartist' THEN "value" END),
+                           MIN(CASE WHEN "key" = 'artist' THEN "value" END)
+                       ) AS "album_artist"
+                FROM "playback_history_metadata"
+                WHERE "server" = ?1 AND "play_id" IN (SELECT "play_id" FROM "plays")
+                GROUP BY "play_id"
+            )
+            SELECT "events"."play_id", "events"."elapsed", "events"."kind", "events"."recorded_at", "plays"."started_at",
+                   "songs"."duration", "songs"."uri", "songs"."title", "songs"."artist", "songs"."artists",
+                   "songs"."album", "songs"."album_artist"
+            FROM "playback_history_events" AS "events"
+            JOIN "plays" ON "plays"."play_id" = "events"."play_id"
+            LEFT JOIN "songs" ON "songs"."play_id" = "events"."play_id"
+            WHERE "events"."server" = ?1
+            ORDER BY "events"."play_id", "events"."recorded_at", "events"."id"
+        "#;
+
+        query_as(sql)
+            .bind(server)
+            .bind(from)
+            .bind(to)
+            .fetch(&mut *self.inner)
+            .map_err(Into::into)
+            .boxed()
+    }
+
     pub async fn get_all_by_id(&mut self, ids: &[PlaybackHistoryEventId]) -> Result<Vec<PlaybackHistoryEventRow>> {
         if ids.is_empty() {
             return Ok(vec![]);
@@ -122,16 +205,47 @@ impl<'c> PlaybackHistoryEventRepository<'c> {
             .map_err(Into::into)
     }
 
+    pub async fn get_all_by_play_id(&mut self, server: &str, play_ids: &[PlaybackHistoryPlayId]) -> Result<Vec<PlaybackHistoryEventRow>> {
+        if play_ids.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut builder = QueryBuilder::new(r#"
+            SELECT "play_id", "elapsed", "kind", "recorded_at"
+            FROM "playback_history_events"
+            WHERE "server" =
+        "#);
+
+        builder.push_bind(server);
+        builder.push(r#" AND "play_id" IN ("#);
+
+        let mut separated = builder.separated(", ");
+
+        for play_id in play_ids {
+            separated.push_bind(play_id);
+        }
+
+        separated.push_unseparated(")");
+
+        builder.push(r#" ORDER BY "recorded_at", "id""#);
+
+        builder.build_query_as()
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
     pub async fn create(&mut self, create: CreatePlaybackHistoryEventRow) -> Result<IdRow<PlaybackHistoryEventId>> {
         let sql = /* language=sql */ r#"
             INSERT INTO "playback_history_events"
-            ("play_id", "elapsed", "kind", "recorded_at")
+            ("server", "play_id", "elapsed", "kind", "recorded_at")
             VALUES
-            (?, ?, ?, ?)
+            (?, ?, ?, ?, ?)
             RETURNING "id"
         "#;
 
         query_as(sql)
+            .bind(create.server)
             .bind(create.play_id)
             .bind(create.elapsed)
             .bind(create.kind)
@@ -147,11 +261,12 @@ impl<'c> PlaybackHistoryEventRepository<'c> {
         }
 
         let mut builder = QueryBuilder::new(r#"
-            INSERT INTO "playback_history_events" ("play_id", "elapsed", "kind", "recorded_at")
+            INSERT INTO "playback_history_events" ("server", "play_id", "elapsed", "kind", "recorded_at")
         "#);
 
         builder.push_values(create, |mut builder, create| {
-            builder.push_bind(create.play_id)
+            builder.push_bind(create.server)
+                .push_bind(create.play_id)
                 .push_bind(create.elapsed)
                 .push_bind(create.kind)
                 .push_bind(create.recorded_at);
@@ -164,6 +279,44 @@ impl<'c> PlaybackHistoryEventRepository<'c> {
             .await
             .map_err(Into::into)
     }
+
+    pub async fn get_checkpoint(&mut self, server: &str) -> Result<Option<PlaybackHistoryCheckpointRow>> {
+        let sql = /* language=sql */ r#"
+            SELECT "play_id", "elapsed", "checkpointed_at"
+            FROM "playback_history_checkpoints"
+            WHERE "server" = ?
+        "#;
+
+        query_as(sql)
+            .bind(server)
+            .fetch_optional(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn create_or_replace_checkpoint(
+        &mut self,
+        server: &str,
+        play_id: PlaybackHistoryPlayId,
+        elapsed: f64,
+        checkpointed_at: &str,
+    ) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            INSERT OR REPLACE INTO "playback_history_checkpoints" ("server", "play_id", "elapsed", "checkpointed_at")
+            VALUES
+            (?, ?, ?, ?)
+        "#;
+
+        query(sql)
+            .bind(server)
+            .bind(play_id)
+            .bind(elapsed)
+            .bind(checkpointed_at)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
 }
 
 // </editor-fold>
@@ -183,6 +336,7 @@ pub struct PlaybackHistoryMetadataRow {
 
 #[derive(Clone)]
 pub struct CreatePlaybackHistoryMetadataRow {
+    pub server: String,
     pub play_id: PlaybackHistoryPlayId,
     pub key: String,
     pub value: String,
@@ -195,11 +349,12 @@ impl<'c> PlaybackHistoryMetadataRepository<'c> {
         }
 
         let mut builder = QueryBuilder::new(r#"
-            INSERT INTO "playback_history_metadata" ("play_id", "key", "value")
+            INSERT INTO "playback_history_metadata" ("server", "play_id", "key", "value")
         "#);
 
         builder.push_values(create, |mut builder, create| {
-            builder.push_bind(create.play_id)
+            builder.push_bind(create.server)
+                .push_bind(create.play_id)
                 .push_bind(create.key)
                 .push_bind(create.value);
         });
@@ -211,27 +366,31 @@ impl<'c> PlaybackHistoryMetadataRepository<'c> {
             .map_err(Into::into)
     }
 
-    pub async fn get_by_play_id(&mut self, play_id: PlaybackHistoryPlayId) -> Result<Vec<PlaybackHistoryMetadataRow>> {
+    pub async fn get_by_play_id(&mut self, server: &str, play_id: PlaybackHistoryPlayId) -> Result<Vec<PlaybackHistoryMetadataRow>> {
         let sql = /* language=sql */ r#"
             SELECT "play_id", "key", "value"
             FROM "playback_history_metadata"
-            WHERE "play_id" = ?
+            WHERE "server" = ? AND "play_id" = ?
         "#;
 
         query_as(sql)
+            .bind(server)
             .bind(play_id)
             .fetch_all(&mut *self.inner)
             .await
             .map_err(Into::into)
     }
 
-    pub async fn get_all_by_play_id(&mut self, play_ids: &[PlaybackHistoryPlayId]) -> Result<Vec<PlaybackHistoryMetadataRow>> {
+    pub async fn get_all_by_play_id(&mut self, server: &str, play_ids: &[PlaybackHistoryPlayId]) -> Result<Vec<PlaybackHistoryMetadataRow>> {
         let mut builder = QueryBuilder::new(r#"
             SELECT "play_id", "key", "value"
             FROM "playback_history_metadata"
-            WHERE "play_id" IN (
+            WHERE "server" =
         "#);
 
+        builder.push_bind(server);
+        builder.push(r#" AND "play_id" IN ("#);
+
         let mut separated = builder.separated(", ");
 
         for play_id in play_ids {
@@ -341,20 +500,928 @@ impl<'c> DbItemLabelRepository<'c> {
 
 // </editor-fold>
 
+// <editor-fold desc="Library Item">
+
+pub struct LibraryItemRepository<'c> {
+    inner: &'c mut SqliteConnection,
+}
+
+#[derive(Type, Debug, Copy, Clone, PartialEq)]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LibraryItemKind {
+    File,
+    Directory,
+}
+
+#[derive(FromRow)]
+pub struct LibraryItemRow {
+    pub kind: LibraryItemKind,
+    pub uri: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub composer: String,
+    pub genre: String,
+    pub date: String,
+    pub format: Option<String>,
+    pub duration: f64,
+    pub updated_at: Option<String>,
+}
+
+#[derive(FromRow)]
+pub struct LibraryItemModificationRow {
+    pub uri: String,
+    pub updated_at: Option<String>,
+}
+
+#[derive(FromRow)]
+pub struct LibraryItemTagRow {
+    pub uri: String,
+    pub key: String,
+    pub value: String,
+}
+
+pub struct CreateLibraryItemTagRow {
+    pub server: String,
+    pub uri: String,
+    pub key: String,
+    pub value: String,
+}
+
+pub struct CreateLibraryItemRow {
+    pub server: String,
+    pub kind: LibraryItemKind,
+    pub uri: String,
+    pub parent: String,
+    pub name: String,
+    pub title: String,
+    pub artist: String,
+    pub album: String,
+    pub album_artist: String,
+    pub composer: String,
+    pub genre: String,
+    pub date: String,
+    pub format: Option<String>,
+    pub duration: f64,
+    pub updated_at: Option<String>,
+}
+
+// Keeps the number of bound parameters per statement well below SQLite's limit.
+const LIBRARY_ITEMS_CHUNK_SIZE: usize = 1000;
+
+impl<'c> LibraryItemRepository<'c> {
+    pub async fn search(&mut self, server: &str, query: &str, limit: i64, offset: i64) -> Result<Vec<LibraryItemRow>> {
+        // Weights follow the column order of the index, names and titles matter the most.
+        let sql = /* language=sql */ r#"
+            SELECT "i"."kind", "i"."uri", "i"."title", "i"."artist", "i"."album", "i"."album_artist",
+                   "i"."composer", "i"."genre", "i"."date", "i"."format", "i"."duration", "i"."updated_at"
+            FROM "library_items_fts"
+            JOIN "library_items" "i" ON "i"."id" = "library_items_fts"."rowid"
+            WHERE "library_items_fts" MATCH ?1 AND "i"."server" = ?2
+            ORDER BY bm25("library_items_fts", 10.0, 8.0, 6.0, 4.0, 4.0, 2.0, 2.0, 1.0), "i"."kind", "i"."uri"
+            LIMIT ?3 OFFSET ?4
+        "#;
+
+        query_as(sql)
+            .bind(query)
+            .bind(server)
+            .bind(limit)
+            .bind(offset)
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn get_all_matching(&mut self, server: &str, query: &str) -> Result<Vec<LibraryItemRow>> {
+        let sql = /* language=sql */ r#"
+            SELECT "i"."kind", "i"."uri", "i"."title", "i"."artist", "i"."album", "i"."album_artist",
+                   "i"."composer", "i"."genre", "i"."date", "i"."format", "i"."duration", "i"."updated_at"
+            FROM "library_items_fts"
+            JOIN "library_items" "i" ON "i"."id" = "library_items_fts"."rowid"
+            WHERE "library_items_fts" MATCH ?1 AND "i"."server" = ?2
+        "#;
+
+        query_as(sql)
+            .bind(query)
+            .bind(server)
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    // The vocabulary is shared by every server.
+    pub async fn get_terms(&mut self) -> Result<Vec<String>> {
+        let sql = /* language=sql */ r#"
+            SELECT "term"
+            FROM "library_items_fts_vocab"
+        "#;
+
+        query_scalar(sql)
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn count(&mut self, server: &str, query: &str) -> Result<i64> {
+        let sql = /* language=sql */ r#"
+            SELECT COUNT(*)
+            FROM "library_items_fts"
+            JOIN "library_items" "i" ON "i"."id" = "library_items_fts"."rowid"
+            WHERE "library_items_fts" MATCH ?1 AND "i"."server" = ?2
+        "#;
+
+        query_scalar(sql)
+            .bind(query)
+            .bind(server)
+            .fetch_one(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn create_all(&mut self, create: Vec<CreateLibraryItemRow>) -> Result<()> {
+        let mut create = create.into_iter().peekable();
+
+        while create.peek().is_some() {
+            let mut builder = QueryBuilder::new(r#"
+                INSERT INTO "library_items" (
+                    "server", "kind", "uri", "parent", "name", "title", "artist", "album", "album_artist",
+                    "composer", "genre", "date", "format", "duration", "updated_at"
+                )
+            "#);
+
+            builder.push_values(create.by_ref().take(LIBRARY_ITEMS_CHUNK_SIZE), |mut builder, create| {
+                builder.push_bind(create.server)
+                    .push_bind(create.kind)
+                    .push_bind(create.uri)
+                    .push_bind(create.parent)
+                    .push_bind(create.name)
+                    .push_bind(create.title)
+                    .push_bind(create.artist)
+                    .push_bind(create.album)
+                    .push_bind(create.album_artist)
+                    .push_bind(create.composer)
+                    .push_bind(create.genre)
+                    .push_bind(create.date)
+                    .push_bind(create.format)
+                    .push_bind(create.duration)
+                    .push_bind(create.updated_at);
+            });
+
+            builder.build()
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn create_all_tags(&mut self, create: Vec<CreateLibraryItemTagRow>) -> Result<()> {
+        let mut create = create.into_iter().peekable();
+
+        while create.peek().is_some() {
+            let mut builder = QueryBuilder::new(r#"
+                INSERT INTO "library_item_tags" ("server", "uri", "key", "value")
+            "#);
+
+            builder.push_values(create.by_ref().take(LIBRARY_ITEMS_CHUNK_SIZE), |mut builder, create| {
+                builder.push_bind(create.server)
+                    .push_bind(create.uri)
+                    .push_bind(create.key)
+                    .push_bind(create.value);
+            });
+
+            builder.build()
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_all_tags(&mut self, server: &str, uris: &[&str]) -> Result<Vec<LibraryItemTagRow>> {
+        let mut result = Vec::new();
+
+        for uris in uris.chunks(LIBRARY_ITEMS_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::new(r#"
+                SELECT "uri", "key", "value"
+                FROM "library_item_tags"
+                WHERE "server" =
+            "#);
+
+            builder.push_bind(server);
+            builder.push(r#" AND "uri" IN ("#);
+
+            let mut separated = builder.separated(", ");
+
+            for uri in uris {
+                separated.push_bind(uri);
+            }
+
+            separated.push_unseparated(r#") ORDER BY "id""#);
+
+            result.append(
+                &mut builder.build_query_as()
+                    .fetch_all(&mut *self.inner)
+                    .await?
+            );
+        }
+
+        Ok(result)
+    }
+
+    pub async fn get_all_by_parent(&mut self, server: &str, parent: &str) -> Result<Vec<LibraryItemModificationRow>> {
+        let sql = /* language=sql */ r#"
+            SELECT "uri", "updated_at"
+            FROM "library_items"
+            WHERE "server" = ? AND "parent" = ?
+        "#;
+
+        query_as(sql)
+            .bind(server)
+            .bind(parent)
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn delete_all_by_uri(&mut self, server: &str, uris: &[&str]) -> Result<()> {
+        for uris in uris.chunks(LIBRARY_ITEMS_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::new(r#"
+                DELETE FROM "library_items"
+                WHERE "server" =
+            "#);
+
+            builder.push_bind(server);
+            builder.push(r#" AND "uri" IN ("#);
+
+            let mut separated = builder.separated(", ");
+
+            for uri in uris {
+                separated.push_bind(uri);
+            }
+
+            separated.push_unseparated(")");
+
+            builder.build()
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Compares prefixes as they are, LIKE would treat '%' and '_' in them as wildcards.
+    pub async fn delete_all_descendants(&mut self, server: &str, uri: &str) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            DELETE FROM "library_items"
+            WHERE "server" = ?1 AND substr("uri", 1, length(?2) + 1) = ?2 || '/'
+        "#;
+
+        query(sql)
+            .bind(server)
+            .bind(uri)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn get_synced_at(&mut self, server: &str) -> Result<Option<String>> {
+        let sql = /* language=sql */ r#"
+            SELECT "synced_at"
+            FROM "library_syncs"
+            WHERE "server" = ?
+        "#;
+
+        query_scalar(sql)
+            .bind(server)
+            .fetch_optional(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn set_synced_at(&mut self, server: &str, synced_at: String) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            INSERT INTO "library_syncs" ("server", "synced_at")
+            VALUES (?1, ?2)
+            ON CONFLICT ("server") DO UPDATE SET "synced_at" = ?2
+        "#;
+
+        query(sql)
+            .bind(server)
+            .bind(synced_at)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+// </editor-fold>
+
+// <editor-fold desc="Cover">
+
+pub struct CoverRepository<'c> {
+    inner: &'c mut SqliteConnection,
+}
+
+#[derive(Type, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CoverKind {
+    File,
+    Embedded,
+}
+
+// Where the cover of an album was found when the kind wasn't asked for.
+#[derive(Type, Debug, Copy, Clone, PartialEq, Eq)]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CoverStrategy {
+    Embedded,
+    Directory,
+    ParentDirectory,
+}
+
+#[derive(FromRow)]
+pub struct CoverRow {
+    pub last_modified: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub stale: bool,
+}
+
+#[derive(FromRow)]
+pub struct CoverPlaceholderRow {
+    pub kind: CoverKind,
+    pub key: String,
+    pub blurhash: String,
+    pub dominant_color: String,
+}
+
+#[derive(Type, Debug, Copy, Clone, PartialEq)]
+#[sqlx(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CoverThumbnailFormat {
+    Jpeg,
+    Webp,
+}
+
+pub struct CreateCoverPlaceholderRow {
+    pub server: String,
+    pub kind: CoverKind,
+    pub key: String,
+    pub blurhash: String,
+    pub dominant_color: String,
+    pub created_at: String,
+}
+
+#[derive(FromRow)]
+pub struct CoverThumbnailRow {
+    pub last_modified: String,
+    pub source_modified_at: i64,
+    pub data: Vec<u8>,
+}
+
+pub struct CreateCoverThumbnailRow {
+    pub server: String,
+    pub kind: CoverKind,
+    pub key: String,
+    pub size: u32,
+    pub format: CoverThumbnailFormat,
+    pub source_modified_at: i64,
+    pub data: Vec<u8>,
+    pub created_at: String,
+}
+
+pub struct CreateCoverRow {
+    pub server: String,
+    pub kind: CoverKind,
+    pub key: String,
+    pub last_modified: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+    pub cached_at: String,
+}
+
+// Keeps the number of bound parameters per statement well below SQLite's limit.
+const COVER_KEYS_CHUNK_SIZE: usize = 1000;
+
+impl<'c> CoverRepository<'c> {
+    pub async fn get(&mut self, server: &str, kind: CoverKind, key: &str) -> Result<Option<CoverRow>> {
+        let sql = /* language=sql */ r#"
+            SELECT "last_modified", "content_type", "data", "stale"
+            FROM "cover_cache"
+            WHERE "server" = ? AND "kind" = ? AND "key" = ?
+        "#;
+
+        query_as(sql)
+            .bind(server)
+            .bind(kind)
+            .bind(key)
+            .fetch_optional(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn create_or_replace(&mut self, create: CreateCoverRow) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            INSERT OR REPLACE INTO "cover_cache" (
+                "server", "kind", "key", "last_modified", "content_type", "data", "size", "cached_at", "accessed_at"
+            )
+            VALUES
+            (?1, ?2, ?3, ?4, ?5, ?6, length(?6), ?7, ?7)
+        "#;
+
+        query(sql)
+            .bind(create.server)
+            .bind(create.kind)
+            .bind(create.key)
+            .bind(create.last_modified)
+            .bind(create.content_type)
+            .bind(create.data)
+            .bind(create.cached_at)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn get_all_placeholders(&mut self, server: &str, keys: &[(CoverKind, &str)]) -> Result<Vec<CoverPlaceholderRow>> {
+        if keys.is_empty() {
+            return Ok(vec![]);
+        }
+
+        let mut builder = QueryBuilder::new(r#"
+            SELECT "kind", "key", "blurhash", "dominant_color"
+            FROM "cover_placeholders"
+            WHERE "server" =
+        "#);
+
+        builder.push_bind(server);
+        builder.push(r#" AND ("kind", "key") IN (VALUES "#);
+
+        let mut separated = builder.separated(", ");
+
+        for (kind, key) in keys {
+            separated.push("(")
+                .push_bind_unseparated(kind)
+                .push_unseparated(", ")
+                .push_bind_unseparated(key)
+                .push_unseparated(")");
+        }
+
+        separated.push_unseparated(")");
+
+        builder.build_query_as()
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn create_or_replace_placeholder(&mut self, create: CreateCoverPlaceholderRow) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            INSERT OR REPLACE INTO "cover_placeholders" ("server", "kind", "key", "blurhash", "dominant_color", "created_at")
+            VALUES
+            (?, ?, ?, ?, ?, ?)
+        "#;
+
+        query(sql)
+            .bind(create.server)
+            .bind(create.kind)
+            .bind(create.key)
+            .bind(create.blurhash)
+            .bind(create.dominant_color)
+            .bind(create.created_at)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn delete_all_placeholders(&mut self, server: &str, keys: &[(CoverKind, &str)]) -> Result<()> {
+        for keys in keys.chunks(COVER_KEYS_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::new(r#"
+                DELETE FROM "cover_placeholders"
+                WHERE "server" =
+            "#);
+
+            builder.push_bind(server);
+            builder.push(r#" AND ("kind", "key") IN (VALUES "#);
+
+            let mut separated = builder.separated(", ");
+
+            for (kind, key) in keys {
+                separated.push("(")
+                    .push_bind_unseparated(kind)
+                    .push_unseparated(", ")
+                    .push_bind_unseparated(key)
+                    .push_unseparated(")");
+            }
+
+            separated.push_unseparated(")");
+
+            builder.build()
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Only thumbnails of covers that are still valid are found, without loading the covers themselves.
+    pub async fn get_thumbnail(
+        &mut self,
+        server: &str,
+        kind: CoverKind,
+        key: &str,
+        size: u32,
+        format: CoverThumbnailFormat,
+    ) -> Result<Option<CoverThumbnailRow>> {
+        let sql = /* language=sql */ r#"
+            SELECT "cover_cache"."last_modified", "cover_thumbnails"."source_modified_at", "cover_thumbnails"."data"
+            FROM "cover_thumbnails"
+            JOIN "cover_cache" USING ("server", "kind", "key")
+            WHERE "server" = ? AND "kind" = ? AND "key" = ? AND "cover_thumbnails"."size" = ? AND "format" = ?
+              AND "cover_cache"."stale" = 0
+        "#;
+
+        query_as(sql)
+            .bind(server)
+            .bind(kind)
+            .bind(key)
+            .bind(size)
+            .bind(format)
+            .fetch_optional(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn create_or_replace_thumbnail(&mut self, create: CreateCoverThumbnailRow) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            INSERT OR REPLACE INTO "cover_thumbnails" (
+                "server", "kind", "key", "size", "format", "source_modified_at", "data", "created_at"
+            )
+            SELECT "server", "kind", "key", ?4, ?5, ?6, ?7, ?8
+            FROM "cover_cache"
+            WHERE "server" = ?1 AND "kind" = ?2 AND "key" = ?3
+        "#;
+
+        query(sql)
+            .bind(create.server)
+            .bind(create.kind)
+            .bind(create.key)
+            .bind(create.size)
+            .bind(create.format)
+            .bind(create.source_modified_at)
+            .bind(create.data)
+            .bind(create.created_at)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn get_strategy(&mut self, server: &str, directory: &str) -> Result<Option<CoverStrategy>> {
+        let sql = /* language=sql */ r#"
+            SELECT "strategy"
+            FROM "cover_strategies"
+            WHERE "server" = ? AND "directory" = ?
+        "#;
+
+        query_scalar(sql)
+            .bind(server)
+            .bind(directory)
+            .fetch_optional(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn create_or_replace_strategy(
+        &mut self,
+        server: &str,
+        directory: &str,
+        strategy: CoverStrategy,
+        updated_at: &str,
+    ) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            INSERT OR REPLACE INTO "cover_strategies" ("server", "directory", "strategy", "updated_at")
+            VALUES
+            (?, ?, ?, ?)
+        "#;
+
+        query(sql)
+            .bind(server)
+            .bind(directory)
+            .bind(strategy)
+            .bind(updated_at)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn delete_all_strategies(&mut self, server: &str, directories: &[&str]) -> Result<()> {
+        for directories in directories.chunks(COVER_KEYS_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::new(r#"
+                DELETE FROM "cover_strategies"
+                WHERE "server" =
+            "#);
+
+            builder.push_bind(server);
+            builder.push(r#" AND "directory" IN ("#);
+
+            let mut separated = builder.separated(", ");
+
+            for directory in directories {
+                separated.push_bind(directory);
+            }
+
+            separated.push_unseparated(")");
+
+            builder.build()
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn touch(&mut self, server: &str, kind: CoverKind, key: &str, accessed_at: &str) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            UPDATE "cover_cache"
+            SET "stale" = 0, "accessed_at" = ?
+            WHERE "server" = ? AND "kind" = ? AND "key" = ?
+        "#;
+
+        query(sql)
+            .bind(accessed_at)
+            .bind(server)
+            .bind(kind)
+            .bind(key)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn set_all_stale(&mut self, server: &str, keys: &[(CoverKind, &str)]) -> Result<()> {
+        for keys in keys.chunks(COVER_KEYS_CHUNK_SIZE) {
+            let mut builder = QueryBuilder::new(r#"
+                UPDATE "cover_cache"
+                SET "stale" = 1
+                WHERE "server" =
+            "#);
+
+            builder.push_bind(server);
+            builder.push(r#" AND ("kind", "key") IN (VALUES "#);
+
+            let mut separated = builder.separated(", ");
+
+            for (kind, key) in keys {
+                separated.push("(")
+                    .push_bind_unseparated(kind)
+                    .push_unseparated(", ")
+                    .push_bind_unseparated(key)
+                    .push_unseparated(")");
+            }
+
+            separated.push_unseparated(")");
+
+            builder.build()
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Covers are keyed by song or directory, so whatever is under a removed directory goes too.
+    pub async fn delete_all_under(&mut self, server: &str, uri: &str) -> Result<()> {
+        let covers_sql = /* language=sql */ r#"
+            DELETE FROM "cover_cache"
+            WHERE "server" = ?1 AND ("key" = ?2 OR substr("key", 1, length(?2) + 1) = ?2 || '/')
+        "#;
+
+        let placeholders_sql = /* language=sql */ r#"
+            DELETE FROM "cover_placeholders"
+            WHERE "server" = ?1 AND ("key" = ?2 OR substr("key", 1, length(?2) + 1) = ?2 || '/')
+        "#;
+
+        let strategies_sql = /* language=sql */ r#"
+            DELETE FROM "cover_strategies"
+            WHERE "server" = ?1 AND ("directory" = ?2 OR substr("directory", 1, length(?2) + 1) = ?2 || '/')
+        "#;
+
+        for sql in [covers_sql, placeholders_sql, strategies_sql] {
+            query(sql)
+                .bind(server)
+                .bind(uri)
+                .execute(&mut *self.inner)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    // Whatever was served least recently goes first, until the rest fits into the given size.
+    pub async fn delete_least_recently_accessed(&mut self, max_size: i64) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            DELETE FROM "cover_cache"
+            WHERE "id" IN (
+                SELECT "id"
+                FROM (
+                    SELECT "id", SUM("size") OVER (ORDER BY "accessed_at" DESC, "id" DESC) AS "total_size"
+                    FROM "cover_cache"
+                )
+                WHERE "total_size" > ?
+            )
+        "#;
+
+        query(sql)
+            .bind(max_size)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+// </editor-fold>
+
+// <editor-fold desc="Scrobble">
+
+pub struct ScrobbleRepository<'c> {
+    inner: &'c mut SqliteConnection,
+}
+
+pub type ScrobbleId = i64;
+
+#[derive(FromRow)]
+pub struct ScrobbleRow {
+    pub id: ScrobbleId,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub duration: f64,
+    pub listened_at: String,
+}
+
+pub struct CreateScrobbleRow {
+    pub server: String,
+    pub target: String,
+    pub play_id: PlaybackHistoryPlayId,
+    pub artist: String,
+    pub title: String,
+    pub album: Option<String>,
+    pub album_artist: Option<String>,
+    pub duration: f64,
+    pub listened_at: String,
+    pub queued_at: String,
+}
+
+impl<'c> ScrobbleRepository<'c> {
+    pub async fn get_all(&mut self, server: &str, target: &str, limit: i64) -> Result<Vec<ScrobbleRow>> {
+        let sql = /* language=sql */ r#"
+            SELECT "id", "artist", "title", "album", "album_artist", "duration", "listened_at"
+            FROM "scrobble_queue"
+            WHERE "server" = ? AND "target" = ? AND "submitted_at" IS NULL
+            ORDER BY "listened_at", "id"
+            LIMIT ?
+        "#;
+
+        query_as(sql)
+            .bind(server)
+            .bind(target)
+            .bind(limit)
+            .fetch_all(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    // A play is only ever queued once for each of the targets.
+    pub async fn create_all(&mut self, create: Vec<CreateScrobbleRow>) -> Result<()> {
+        if create.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = QueryBuilder::new(r#"
+            INSERT OR IGNORE INTO "scrobble_queue" (
+                "server", "target", "play_id", "artist", "title", "album", "album_artist", "duration", "listened_at", "queued_at"
+            )
+        "#);
+
+        builder.push_values(create, |mut builder, create| {
+            builder.push_bind(create.server)
+                .push_bind(create.target)
+                .push_bind(create.play_id)
+                .push_bind(create.artist)
+                .push_bind(create.title)
+                .push_bind(create.album)
+                .push_bind(create.album_artist)
+                .push_bind(create.duration)
+                .push_bind(create.listened_at)
+                .push_bind(create.queued_at);
+        });
+
+        builder.build()
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    pub async fn get_last_listened_at(&mut self, server: &str) -> Result<Option<String>> {
+        let sql = /* language=sql */ r#"
+            SELECT max("listened_at")
+            FROM "scrobble_queue"
+            WHERE "server" = ?
+        "#;
+
+        query_scalar(sql)
+            .bind(server)
+            .fetch_one(&mut *self.inner)
+            .await
+            .map_err(Into::into)
+    }
+
+    pub async fn set_all_submitted(&mut self, ids: &[ScrobbleId], submitted_at: &str) -> Result<()> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = QueryBuilder::new(r#"
+            UPDATE "scrobble_queue"
+            SET "submitted_at" = "#);
+
+        builder.push_bind(submitted_at);
+        builder.push(r#" WHERE "id" IN ("#);
+
+        let mut separated = builder.separated(", ");
+
+        for id in ids {
+            separated.push_bind(id);
+        }
+
+        separated.push_unseparated(")");
+
+        builder.build()
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+
+    // Only the last submitted scrobble of each target is worth keeping.
+    pub async fn delete_all_submitted_but_last(&mut self, server: &str) -> Result<()> {
+        let sql = /* language=sql */ r#"
+            DELETE FROM "scrobble_queue"
+            WHERE "server" = ?1
+              AND "submitted_at" IS NOT NULL
+              AND "id" NOT IN (
+                SELECT max("id")
+                FROM "scrobble_queue"
+                WHERE "server" = ?1 AND "submitted_at" IS NOT NULL
+                GROUP BY "target"
+              )
+        "#;
+
+        query(sql)
+            .bind(server)
+            .execute(&mut *self.inner)
+            .await
+            .map(|_| ())
+            .map_err(Into::into)
+    }
+}
+
+// </editor-fold>
+
 macro_rules! impl_repository {
     ($name:ident) => {
         impl $name {
-            pub fn playback_history_event(&mut self) -> PlaybackHistoryEventRepository {
+            pub fn playback_history_event(&mut self) -> PlaybackHistoryEventRepository<'_> {
                 PlaybackHistoryEventRepository { inner: &mut self.inner }
             }
 
-            pub fn playback_history_metadata(&mut self) -> PlaybackHistoryMetadataRepository {
+            pub fn playback_history_metadata(&mut self) -> PlaybackHistoryMetadataRepository<'_> {
                 PlaybackHistoryMetadataRepository { inner: &mut self.inner }
             }
 
-            pub fn db_item_label(&mut self) -> DbItemLabelRepository {
+            pub fn db_item_label(&mut self) -> DbItemLabelRepository<'_> {
                 DbItemLabelRepository { inner: &mut self.inner }
             }
+
+            pub fn library_item(&mut self) -> LibraryItemRepository<'_> {
+                LibraryItemRepository { inner: &mut self.inner }
+            }
+
+            pub fn cover(&mut self) -> CoverRepository<'_> {
+                CoverRepository { inner: &mut self.inner }
+            }
+
+            pub fn scrobble(&mut self) -> ScrobbleRepository<'_> {
+                ScrobbleRepository { inner: &mut self.inner }
+            }
         }
     }
 }
@@ -1,9 +1,18 @@
+mod body;
 mod error;
 mod result;
 
 pub mod assets;
 pub mod db;
+pub mod library;
 pub mod playlists;
+pub mod queue;
+pub mod playback;
+pub mod volume;
+pub mod outputs;
+pub mod partitions;
+pub mod servers;
+pub mod stickers;
 pub mod ws;
 pub mod history;
 pub mod labels;
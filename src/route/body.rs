@@ -0,0 +1,86 @@
+use axum::async_trait;
+use axum::body::Body;
+use axum::extract::FromRequest;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::de::DeserializeOwned;
+
+use crate::route::error::Error;
+
+// Like Json, except that a request without a body is None rather than rejected.
+// A body that is there still has to be valid.
+#[derive(Debug)]
+pub struct OptionalJson<T>(pub Option<T>);
+
+#[async_trait]
+impl<T, S> FromRequest<S, Body> for OptionalJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = Error;
+
+    async fn from_request(req: Request<Body>, state: &S) -> Result<Self, Self::Rejection> {
+        let (parts, body) = req.into_parts();
+
+        let bytes = hyper::body::to_bytes(body).await
+            .map_err(|e| Error::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+        if bytes.is_empty() {
+            return Ok(OptionalJson(None));
+        }
+
+        let Json(result) = Json::from_request(Request::from_parts(parts, Body::from(bytes)), state).await?;
+
+        Ok(OptionalJson(Some(result)))
+    }
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use axum::http::header;
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    struct TestBody {
+        id: i64,
+    }
+
+    async fn extract(body: &'static str) -> Result<OptionalJson<TestBody>, Error> {
+        let req = Request::builder()
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .unwrap();
+
+        OptionalJson::from_request(req, &()).await
+    }
+
+    #[tokio::test]
+    async fn should_extract_missing_body_as_none() {
+        let req = Request::builder()
+            .body(Body::empty())
+            .unwrap();
+
+        let OptionalJson(result) = OptionalJson::<TestBody>::from_request(req, &()).await.unwrap();
+
+        assert!(result.is_none());
+    }
+
+    #[tokio::test]
+    async fn should_extract_body() {
+        let OptionalJson(result) = extract(r#"{"id":1}"#).await.unwrap();
+
+        assert_eq!(result.map(|body| body.id), Some(1));
+    }
+
+    #[tokio::test]
+    async fn should_reject_malformed_body() {
+        assert_eq!(extract(r#"{"id":"#).await.unwrap_err().code(), StatusCode::BAD_REQUEST);
+        assert_eq!(extract(r#"{"id":"1"}"#).await.unwrap_err().code(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+}
@@ -1,14 +1,32 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::result;
+
+use axum::body;
+use axum::body::Body;
 use axum::Extension;
 use axum::extract::Query;
 use axum::http::header;
 use axum::http::header::HeaderName;
+use axum::http::HeaderMap;
+use axum::http::HeaderValue;
 use axum::Json;
-use bytes::Bytes;
+use axum::response::IntoResponse;
+use axum::response::Response;
 use hyper::StatusCode;
 use serde::Deserialize;
 use serde::Serialize;
+use serde::Serializer;
+use serde_json as json;
+use time::format_description;
+use time::OffsetDateTime;
+use time::UtcOffset;
 
+use crate::config;
+use crate::covers;
+use crate::index;
 use crate::mpd;
+use crate::route::body::OptionalJson;
 use crate::route::error::Error;
 use crate::route::result::Result;
 use crate::time::Duration;
@@ -37,26 +55,124 @@ impl From<mpd::DbAudioFormat> for DbAudioFormat {
     }
 }
 
+// Tags other than titles, artists and albums are only present when set.
+// Those without a field of their own are under other, by their lowercase MPD name.
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DbTags {
     pub titles: Vec<String>,
     pub artists: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub artist_sorts: Vec<String>,
     pub albums: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub album_sorts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub album_artists: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub album_artist_sorts: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tracks: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub discs: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub dates: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub original_dates: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub genres: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub composers: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub performers: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub conductors: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub works: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub groupings: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub names: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub comments: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub labels: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub musicbrainz_artist_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub musicbrainz_album_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub musicbrainz_album_artist_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub musicbrainz_track_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub musicbrainz_release_track_ids: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub musicbrainz_work_ids: Vec<String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub other: BTreeMap<String, Vec<String>>,
 }
 
 impl From<mpd::DbTags> for DbTags {
     fn from(
         mpd::DbTags {
+            titles,
             artists,
+            artist_sorts,
             albums,
-            titles,
+            album_sorts,
+            album_artists,
+            album_artist_sorts,
+            tracks,
+            discs,
+            dates,
+            original_dates,
+            genres,
+            composers,
+            performers,
+            conductors,
+            works,
+            groupings,
+            names,
+            comments,
+            labels,
+            musicbrainz_artist_ids,
+            musicbrainz_album_ids,
+            musicbrainz_album_artist_ids,
+            musicbrainz_track_ids,
+            musicbrainz_release_track_ids,
+            musicbrainz_work_ids,
+            other,
         }: mpd::DbTags,
     ) -> Self {
         DbTags {
+            titles,
             artists,
+            artist_sorts,
             albums,
-            titles,
+            album_sorts,
+            album_artists,
+            album_artist_sorts,
+            tracks,
+            discs,
+            dates,
+            original_dates,
+            genres,
+            composers,
+            performers,
+            conductors,
+            works,
+            groupings,
+            names,
+            comments,
+            labels,
+            musicbrainz_artist_ids,
+            musicbrainz_album_ids,
+            musicbrainz_album_artist_ids,
+            musicbrainz_track_ids,
+            musicbrainz_release_track_ids,
+            musicbrainz_work_ids,
+            other,
         }
     }
 }
@@ -68,12 +184,16 @@ pub enum DbItem {
     File {
         uri: String,
         duration: Duration,
-        tags: DbTags,
+        tags: Box<DbTags>,
         format: Option<DbAudioFormat>,
         updated_at: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cover: Option<DbCoverPlaceholder>,
     },
     Directory {
         uri: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        cover: Option<DbCoverPlaceholder>,
     },
     Playlist {
         uri: String,
@@ -95,37 +215,303 @@ impl From<mpd::DbItem> for DbItem {
                 uri,
                 updated_at,
                 duration: duration.into(),
-                tags: tags.into(),
+                tags: Box::new((*tags).into()),
                 format: format.map(Into::into),
+                cover: None,
             },
-            Directory { uri } => DbItem::Directory { uri },
+            Directory { uri, .. } => DbItem::Directory { uri, cover: None },
             Playlist { uri } => DbItem::Playlist { uri },
         }
     }
 }
 
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbCoverPlaceholder {
+    blurhash: String,
+    color: String,
+}
+
+impl From<covers::CoverPlaceholder> for DbCoverPlaceholder {
+    fn from(placeholder: covers::CoverPlaceholder) -> Self {
+        DbCoverPlaceholder {
+            blurhash: placeholder.blurhash,
+            color: placeholder.dominant_color,
+        }
+    }
+}
+
+impl DbItem {
+    fn with_cover(mut self, placeholder: Option<covers::CoverPlaceholder>) -> Self {
+        if let DbItem::File { cover, .. } | DbItem::Directory { cover, .. } = &mut self {
+            *cover = placeholder.map(Into::into);
+        }
+
+        self
+    }
+}
+
+// Lets clients paint something in place of covers they haven't loaded yet. Items only
+// get one once their album has been indexed or their cover has been fetched.
+async fn into_items(covers: &covers::Handle, items: Vec<mpd::DbItem>) -> Result<Vec<DbItem>> {
+    let mut placeholders = covers.placeholders(&items).await?;
+
+    let items = items.into_iter()
+        .map(|item| {
+            let placeholder = placeholders.remove(item.uri());
+
+            DbItem::from(item).with_cover(placeholder)
+        })
+        .collect();
+
+    Ok(items)
+}
+
+const DEFAULT_PAGE_LIMIT: usize = 100;
+
+#[derive(Debug)]
+struct DbPaging {
+    offset: usize,
+    limit: usize,
+}
+
+impl DbPaging {
+    // Cursors are opaque to clients, for now they carry the offset of the next page.
+    fn new(offset: Option<usize>, limit: Option<usize>, cursor: Option<String>) -> Result<Option<DbPaging>> {
+        let offset = match (offset, cursor) {
+            (Some(_), Some(_)) => {
+                return Err(Error::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Expected at most one of offset or cursor to not be null".to_owned(),
+                ))
+            },
+            (None, Some(cursor)) => {
+                let offset = cursor.parse()
+                    .map_err(|_| Error::new(StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid cursor '{cursor}'")))?;
+
+                Some(offset)
+            },
+            (offset, None) => offset,
+        };
+
+        if offset.is_none() && limit.is_none() {
+            return Ok(None);
+        }
+
+        let limit = limit.unwrap_or(DEFAULT_PAGE_LIMIT);
+
+        if limit == 0 {
+            return Err(Error::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Expected limit to be greater than zero".to_owned(),
+            ));
+        }
+
+        Ok(Some(DbPaging { offset: offset.unwrap_or_default(), limit }))
+    }
+
+    // Unpaged requests get everything there is.
+    fn bounds(paging: &Option<DbPaging>) -> (usize, usize) {
+        paging.as_ref()
+            .map_or((0, usize::MAX), |paging| (paging.offset, paging.limit))
+    }
+
+    fn window(&self) -> mpd::DbWindow {
+        mpd::DbWindow {
+            start: self.offset,
+            end: self.offset.saturating_add(self.limit),
+        }
+    }
+
+    fn slice<T>(&self, items: Vec<T>) -> Vec<T> {
+        items.into_iter()
+            .skip(self.offset)
+            .take(self.limit)
+            .collect()
+    }
+}
+
+fn serialize_cursor<S: Serializer>(offset: &Option<usize>, serializer: S) -> result::Result<S::Ok, S::Error> {
+    offset.map(|it| it.to_string()).serialize(serializer)
+}
+
+// Cursors are passed back as strings, so that's what the next one is.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbPage<T = DbItem> {
+    pub items: Vec<T>,
+    total: usize,
+    #[serde(rename = "nextCursor", serialize_with = "serialize_cursor")]
+    next_offset: Option<usize>,
+}
+
+impl<T> DbPage<T> {
+    fn new(items: Vec<T>, total: usize, offset: usize) -> Self {
+        let end = offset + items.len();
+
+        let next_offset = (!items.is_empty() && end < total).then_some(end);
+
+        DbPage { items, total, next_offset }
+    }
+}
+
+// The total and the next cursor are sent as headers so that the body stays a plain array.
+impl<T: Serialize> IntoResponse for DbPage<T> {
+    fn into_response(self) -> Response {
+        let mut headers = HeaderMap::new();
+
+        headers.insert(HeaderName::from_static("x-total-count"), self.total.into());
+
+        if let Some(offset) = self.next_offset {
+            headers.insert(HeaderName::from_static("x-next-cursor"), offset.into());
+        }
+
+        (headers, Json(self.items)).into_response()
+    }
+}
+
+const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+const STREAM_CHUNK_SIZE: usize = 1000;
+
+fn wants_ndjson(headers: &HeaderMap) -> bool {
+    headers.get(header::ACCEPT)
+        .and_then(|it| it.to_str().ok())
+        .is_some_and(|it| it.contains(NDJSON_CONTENT_TYPE))
+}
+
+// MPD answers a search in one response, so it's requested once and written out a chunk
+// at a time, with covers looked up per chunk instead of for the whole result up front.
+fn stream_items<R>(covers: covers::Handle, fetch: R) -> Response
+    where R: Future<Output=mpd::Result<Vec<mpd::DbItem>>> + Send + 'static,
+{
+    let (mut sender, body) = Body::channel();
+
+    tokio::spawn(async move {
+        let mut items = match fetch.await {
+            Ok(items) => items.into_iter(),
+            Err(e) => {
+                tracing::error!("failed to fetch items to stream: {e}");
+
+                sender.abort();
+
+                return;
+            },
+        };
+
+        loop {
+            let chunk = items.by_ref().take(STREAM_CHUNK_SIZE).collect::<Vec<_>>();
+
+            if chunk.is_empty() {
+                break;
+            }
+
+            let chunk = match into_items(&covers, chunk).await {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    tracing::error!("failed to look up cover placeholders of items to stream: {e:?}");
+
+                    sender.abort();
+
+                    break;
+                },
+            };
+
+            let mut data = Vec::new();
+
+            for item in chunk {
+                json::to_writer(&mut data, &item)
+                    .expect("expected to be able to serialize database item");
+
+                data.push(b'\n');
+            }
+
+            // The client is gone.
+            if sender.send_data(data.into()).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    ([(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)], body::boxed(body)).into_response()
+}
+
+#[derive(Debug, Default, Copy, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DbSearchMode {
+    #[default]
+    Text,
+    Fuzzy,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct DbQueryParams {
     uri: Option<String>,
     query: Option<String>,
+    // Only applies to queries.
+    mode: Option<DbSearchMode>,
+    // Comma-separated tag names, every known tag is returned if omitted.
+    tags: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    cursor: Option<String>,
 }
 
 const MIN_QUERY_LEN: usize = 3;
 
-#[tracing::instrument(ret, skip(handle), level = "debug")]
+#[tracing::instrument(ret, skip(handle, index, covers), level = "debug")]
 pub async fn database(
     Query(params): Query<DbQueryParams>,
     Extension(handle): Extension<mpd::Handle>,
-) -> Result<Json<Vec<DbItem>>> {
+    Extension(index): Extension<index::Handle>,
+    Extension(covers): Extension<covers::Handle>,
+) -> Result<DbPage> {
+    load_database(&handle, &index, &covers, params).await
+}
+
+// Neither lsinfo nor the uri search support windows, so paging happens here.
+pub async fn load_database(
+    handle: &mpd::Handle,
+    index: &index::Handle,
+    covers: &covers::Handle,
+    params: DbQueryParams,
+) -> Result<DbPage> {
+    let tags = params.tags.map(split_tags);
+
+    validate_tags(&tags)?;
+
+    let paging = DbPaging::new(params.offset, params.limit, params.cursor)?;
+
     let items = match (params.uri, params.query) {
-        (Some(uri), None) => handle.db().get(uri).await?,
+        (Some(uri), None) => handle.db().get(uri, tags).await?,
         (None, Some(query)) if query.len() < MIN_QUERY_LEN => {
             return Err(Error::new(
                 StatusCode::UNPROCESSABLE_ENTITY,
                 format!("Minimum query length is {MIN_QUERY_LEN}"),
             ))
         },
-        (None, Some(query)) => handle.db().search(query).await?,
+        (None, Some(query)) if index.is_synced().await? => {
+            let (offset, limit) = DbPaging::bounds(&paging);
+
+            let (items, total) = match params.mode.unwrap_or_default() {
+                DbSearchMode::Text => index.search(&query, offset, limit).await?,
+                DbSearchMode::Fuzzy => index.fuzzy_search(&query, offset, limit).await?,
+            };
+
+            let items = match tags {
+                Some(tags) => narrow_tags(handle, items, &tags).await?,
+                None => items,
+            };
+
+            return Ok(DbPage::new(into_items(covers, items).await?, total, offset));
+        },
+        (None, Some(_)) if matches!(params.mode, Some(DbSearchMode::Fuzzy)) => {
+            return Err(Error::new(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "Fuzzy search is unavailable until the library is indexed".to_owned(),
+            ))
+        },
+        (None, Some(query)) => handle.db().search(query, tags).await?,
         (None, None) | (Some(_), Some(_)) => {
             return Err(Error::new(
                 StatusCode::UNPROCESSABLE_ENTITY,
@@ -134,9 +520,320 @@ pub async fn database(
         },
     };
 
-    let items = items.into_iter().map(Into::into).collect();
+    let total = items.len();
+
+    let (items, offset) = match paging {
+        Some(paging) => (paging.slice(items), paging.offset),
+        None => (items, 0),
+    };
+
+    Ok(DbPage::new(into_items(covers, items).await?, total, offset))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DbFilterOperator {
+    Eq,
+    Ne,
+    Contains,
+    Regex,
+    NotRegex,
+}
+
+impl From<DbFilterOperator> for mpd::DbFilterOperator {
+    fn from(operator: DbFilterOperator) -> Self {
+        match operator {
+            DbFilterOperator::Eq => mpd::DbFilterOperator::Equal,
+            DbFilterOperator::Ne => mpd::DbFilterOperator::NotEqual,
+            DbFilterOperator::Contains => mpd::DbFilterOperator::Contains,
+            DbFilterOperator::Regex => mpd::DbFilterOperator::Regex,
+            DbFilterOperator::NotRegex => mpd::DbFilterOperator::NotRegex,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DbFilter {
+    Tag {
+        name: String,
+        #[serde(default = "default_filter_operator")]
+        op: DbFilterOperator,
+        value: String,
+    },
+    Not(Box<DbFilter>),
+    And(Vec<DbFilter>),
+    Base(String),
+    ModifiedSince(String),
+    AudioFormat {
+        value: String,
+        #[serde(default)]
+        mask: bool,
+    },
+}
+
+fn default_filter_operator() -> DbFilterOperator {
+    DbFilterOperator::Eq
+}
+
+// Tag names end up unquoted in the filter expression.
+fn validate_tag(tag: &str) -> Result<()> {
+    let is_valid = !tag.is_empty() && tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+
+    if !is_valid {
+        return Err(Error::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Invalid tag name '{tag}'"),
+        ));
+    }
+
+    Ok(())
+}
+
+impl TryFrom<DbFilter> for mpd::DbFilter {
+    type Error = Error;
+
+    fn try_from(filter: DbFilter) -> Result<Self> {
+        let result = match filter {
+            DbFilter::Tag { name, op, value } => {
+                validate_tag(&name)?;
+
+                mpd::DbFilter::Tag { tag: name, operator: op.into(), value }
+            },
+            DbFilter::Not(filter) => {
+                mpd::DbFilter::Not(Box::new((*filter).try_into()?))
+            },
+            DbFilter::And(filters) if filters.is_empty() => {
+                return Err(Error::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Expected and to contain at least one filter".to_owned(),
+                ));
+            },
+            DbFilter::And(filters) => {
+                mpd::DbFilter::And(
+                    filters.into_iter()
+                        .map(TryInto::try_into)
+                        .collect::<Result<_>>()?
+                )
+            },
+            DbFilter::Base(uri) => mpd::DbFilter::Base(uri),
+            DbFilter::ModifiedSince(since) => mpd::DbFilter::ModifiedSince(since),
+            DbFilter::AudioFormat { value, mask } => mpd::DbFilter::AudioFormat { value, mask },
+        };
+
+        Ok(result)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DbWindow {
+    start: usize,
+    end: usize,
+}
+
+impl From<DbWindow> for mpd::DbWindow {
+    fn from(DbWindow { start, end }: DbWindow) -> Self {
+        mpd::DbWindow { start, end }
+    }
+}
+
+fn split_tags(tags: String) -> Vec<String> {
+    tags.split(',')
+        .map(str::trim)
+        .filter(|it| !it.is_empty())
+        .map(ToOwned::to_owned)
+        .collect()
+}
+
+// The index holds every tag, so narrowing is up to us, with the same tags allowed as MPD would.
+async fn narrow_tags(handle: &mpd::Handle, items: Vec<mpd::DbItem>, tags: &[String]) -> Result<Vec<mpd::DbItem>> {
+    let tagtypes = handle.db().tagtypes().await?;
+
+    if let Some(tag) = tags.iter().find(|tag| !tagtypes.iter().any(|it| it.eq_ignore_ascii_case(tag))) {
+        return Err(Error::new(StatusCode::BAD_REQUEST, format!("Unknown tag '{tag}'")));
+    }
+
+    let result = items.into_iter()
+        .map(|item| match item {
+            mpd::DbItem::File { uri, duration, tags: item_tags, format, updated_at } => mpd::DbItem::File {
+                uri,
+                duration,
+                tags: Box::new(item_tags.narrow(tags)),
+                format,
+                updated_at,
+            },
+            item => item,
+        })
+        .collect();
+
+    Ok(result)
+}
+
+fn validate_tags(tags: &Option<Vec<String>>) -> Result<()> {
+    tags.iter()
+        .flatten()
+        .try_for_each(|it| validate_tag(it))
+}
+
+// Sorting is descending when the tag is prefixed with '-'.
+fn parse_sort(sort: String) -> Result<mpd::DbSort> {
+    let result = match sort.strip_prefix('-') {
+        Some(tag) => mpd::DbSort { tag: tag.to_owned(), descending: true },
+        None => mpd::DbSort { tag: sort, descending: false },
+    };
+
+    validate_tag(&result.tag)?;
+
+    Ok(result)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DbSearchBody {
+    filter: DbFilter,
+    sort: Option<String>,
+    window: Option<DbWindow>,
+    tags: Option<Vec<String>>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DbSearchQueryParams {
+    // JSON-encoded filter tree, same as in the body of the POST variant.
+    filter: String,
+    sort: Option<String>,
+    start: Option<usize>,
+    end: Option<usize>,
+    tags: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+impl TryFrom<DbSearchQueryParams> for DbSearchBody {
+    type Error = Error;
+
+    fn try_from(params: DbSearchQueryParams) -> Result<Self> {
+        let filter = json::from_str(&params.filter)
+            .map_err(|e| Error::new(StatusCode::UNPROCESSABLE_ENTITY, format!("Invalid filter: {e}")))?;
+
+        let window = match (params.start, params.end) {
+            (Some(start), Some(end)) => Some(DbWindow { start, end }),
+            (None, None) => None,
+            _ => {
+                return Err(Error::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Expected either both or neither of start and end".to_owned(),
+                ))
+            },
+        };
+
+        Ok(DbSearchBody {
+            filter,
+            sort: params.sort,
+            window,
+            tags: params.tags.map(split_tags),
+            offset: params.offset,
+            limit: params.limit,
+            cursor: params.cursor,
+        })
+    }
+}
+
+struct DbSearch {
+    filter: mpd::DbFilter,
+    sort: Option<mpd::DbSort>,
+    window: Option<mpd::DbWindow>,
+    tags: Option<Vec<String>>,
+}
+
+impl TryFrom<DbSearchBody> for DbSearch {
+    type Error = Error;
+
+    fn try_from(body: DbSearchBody) -> Result<Self> {
+        validate_tags(&body.tags)?;
+
+        let paging = DbPaging::new(body.offset, body.limit, body.cursor)?;
+
+        let window = match (body.window, paging) {
+            (Some(_), Some(_)) => {
+                return Err(Error::new(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "Expected either a window or paging parameters but not both".to_owned(),
+                ))
+            },
+            (Some(window), None) => Some(window.into()),
+            (None, Some(paging)) => Some(paging.window()),
+            (None, None) => None,
+        };
+
+        Ok(DbSearch {
+            filter: body.filter.try_into()?,
+            sort: body.sort.map(parse_sort).transpose()?,
+            window,
+            tags: body.tags,
+        })
+    }
+}
+
+#[tracing::instrument(skip(handle, covers, headers), level = "debug")]
+pub async fn search(
+    Query(params): Query<DbSearchQueryParams>,
+    headers: HeaderMap,
+    Extension(handle): Extension<mpd::Handle>,
+    Extension(covers): Extension<covers::Handle>,
+) -> Result<Response> {
+    respond_search(handle, covers, params.try_into()?, &headers).await
+}
+
+#[tracing::instrument(skip(handle, covers, headers), level = "debug")]
+pub async fn search_body(
+    headers: HeaderMap,
+    Extension(handle): Extension<mpd::Handle>,
+    Extension(covers): Extension<covers::Handle>,
+    Json(body): Json<DbSearchBody>,
+) -> Result<Response> {
+    respond_search(handle, covers, body, &headers).await
+}
+
+async fn respond_search(
+    handle: mpd::Handle,
+    covers: covers::Handle,
+    body: DbSearchBody,
+    headers: &HeaderMap,
+) -> Result<Response> {
+    if !wants_ndjson(headers) {
+        return Ok(load_search(&handle, &covers, body).await?.into_response());
+    }
+
+    let DbSearch { filter, sort, window, tags } = body.try_into()?;
+
+    let response = stream_items(covers, async move {
+        handle.db().filter(filter, sort, window, tags).await
+    });
+
+    Ok(response)
+}
+
+pub async fn load_search(handle: &mpd::Handle, covers: &covers::Handle, body: DbSearchBody) -> Result<DbPage> {
+    let DbSearch { filter, sort, window, tags } = body.try_into()?;
+
+    let offset = window.as_ref().map_or(0, |it| it.start);
+
+    // Counting is only worth it when the result is a part of the whole.
+    let total = match window {
+        Some(_) => Some(handle.db().count_filter(filter.clone()).await?.nsongs),
+        None => None,
+    };
+
+    let items = handle.db()
+        .filter(filter, sort, window, tags)
+        .await?;
+
+    let total = total.map_or(items.len(), |it| it as usize);
 
-    Ok(Json(items))
+    Ok(DbPage::new(into_items(covers, items).await?, total, offset))
 }
 
 #[derive(Debug, Deserialize)]
@@ -144,13 +841,31 @@ pub async fn database(
 pub enum CoverArtKind {
     File,
     Embedded,
+    Auto,
 }
 
-impl From<CoverArtKind> for mpd::CoverArtKind {
+impl From<CoverArtKind> for covers::CoverArtKind {
     fn from(kind: CoverArtKind) -> Self {
         match kind {
-            CoverArtKind::File => mpd::CoverArtKind::File,
-            CoverArtKind::Embedded => mpd::CoverArtKind::Embedded,
+            CoverArtKind::File => covers::CoverArtKind::File,
+            CoverArtKind::Embedded => covers::CoverArtKind::Embedded,
+            CoverArtKind::Auto => covers::CoverArtKind::Auto,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CoverThumbnailFormat {
+    Jpeg,
+    Webp,
+}
+
+impl From<CoverThumbnailFormat> for covers::CoverThumbnailFormat {
+    fn from(format: CoverThumbnailFormat) -> Self {
+        match format {
+            CoverThumbnailFormat::Jpeg => covers::CoverThumbnailFormat::Jpeg,
+            CoverThumbnailFormat::Webp => covers::CoverThumbnailFormat::Webp,
         }
     }
 }
@@ -159,19 +874,91 @@ impl From<CoverArtKind> for mpd::CoverArtKind {
 pub struct DbCoverQueryParams {
     uri: String,
     kind: CoverArtKind,
+    // Longest side of the thumbnail, the cover is returned as is if omitted
+    // or if it's in a format that thumbnails can't be made from.
+    size: Option<u32>,
+    format: Option<CoverThumbnailFormat>,
 }
 
-#[tracing::instrument(skip(handle), level = "debug")]
+const MIN_THUMBNAIL_SIZE: u32 = 16;
+const MAX_THUMBNAIL_SIZE: u32 = 1024;
+
+// Covers may change with any database update, so clients have to revalidate.
+const COVER_CACHE_CONTROL: &str = "no-cache";
+
+fn cover_etag(cover: &covers::Cover) -> Option<String> {
+    let last_modified = cover.last_modified?;
+
+    Some(format!(r#""{:x}-{:x}""#, last_modified.unix_timestamp(), cover.data.len()))
+}
+
+fn http_date(datetime: OffsetDateTime) -> Option<String> {
+    let format = format_description::parse(
+        "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+    ).ok()?;
+
+    datetime.to_offset(UtcOffset::UTC)
+        .format(&format)
+        .ok()
+}
+
+fn matches_etag(if_none_match: &str, etag: &str) -> bool {
+    if_none_match.split(',')
+        .map(str::trim)
+        .any(|tag| tag == "*" || tag.trim_start_matches("W/") == etag)
+}
+
+#[tracing::instrument(skip(headers, handle, covers), level = "debug")]
 pub async fn cover(
     Query(params): Query<DbCoverQueryParams>,
+    headers: HeaderMap,
     Extension(handle): Extension<mpd::Handle>,
-) -> Result<([(HeaderName, &'static str); 1], Bytes)> {
-    let result = handle
-        .db()
-        .cover_art(params.uri, params.kind.into())
-        .await?;
+    Extension(covers): Extension<covers::Handle>,
+) -> Result<Response> {
+    let cover = match (params.size, params.format) {
+        (Some(size), _) if !(MIN_THUMBNAIL_SIZE..=MAX_THUMBNAIL_SIZE).contains(&size) => {
+            return Err(Error::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Expected size to be between {MIN_THUMBNAIL_SIZE} and {MAX_THUMBNAIL_SIZE}"),
+            ))
+        },
+        (Some(size), format) => {
+            let format = format.unwrap_or(CoverThumbnailFormat::Jpeg);
+
+            covers.get_thumbnail(&handle, params.uri, params.kind.into(), size, format.into()).await?
+        },
+        (None, Some(_)) => {
+            return Err(Error::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                "Expected format to be accompanied by size".to_owned(),
+            ))
+        },
+        (None, None) => covers.get(&handle, params.uri, params.kind.into()).await?,
+    };
+
+    let mut response_headers = HeaderMap::new();
+
+    response_headers.insert(header::CACHE_CONTROL, HeaderValue::from_static(COVER_CACHE_CONTROL));
+
+    if let Some(last_modified) = cover.last_modified.and_then(http_date) {
+        response_headers.insert(header::LAST_MODIFIED, HeaderValue::from_str(&last_modified).map_err(|err| err.to_string())?);
+    }
+
+    if let Some(etag) = cover_etag(&cover) {
+        let is_not_modified = headers.get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| matches_etag(value, &etag));
+
+        response_headers.insert(header::ETAG, HeaderValue::from_str(&etag).map_err(|err| err.to_string())?);
 
-    Ok(([(header::CONTENT_TYPE, "image/png")], result))
+        if is_not_modified {
+            return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+        }
+    }
+
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_str(&cover.content_type).map_err(|err| err.to_string())?);
+
+    Ok((response_headers, cover.data).into_response())
 }
 
 #[derive(Serialize)]
@@ -199,17 +986,237 @@ pub async fn count(
     Query(params): Query<DbCountQueryParams>,
     Extension(handle): Extension<mpd::Handle>,
 ) -> Result<Json<DbCount>> {
-    let result = handle.db().count(params.uri).await?.into();
+    let result = load_count(&handle, params).await?;
 
     Ok(Json(result))
 }
 
+pub async fn load_count(handle: &mpd::Handle, params: DbCountQueryParams) -> Result<DbCount> {
+    Ok(handle.db().count(params.uri).await?.into())
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum DbGroupKind {
+    Album,
+    Directory,
+}
+
+impl From<DbGroupKind> for mpd::DbGroupKind {
+    fn from(kind: DbGroupKind) -> Self {
+        match kind {
+            DbGroupKind::Album => mpd::DbGroupKind::Album,
+            DbGroupKind::Directory => mpd::DbGroupKind::Directory,
+        }
+    }
+}
+
+impl From<mpd::DbGroupKind> for DbGroupKind {
+    fn from(kind: mpd::DbGroupKind) -> Self {
+        match kind {
+            mpd::DbGroupKind::Album => DbGroupKind::Album,
+            mpd::DbGroupKind::Directory => DbGroupKind::Directory,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbGroup {
+    #[serde(rename = "type")]
+    kind: DbGroupKind,
+    name: String,
+    artist: Option<String>,
+    uri: String,
+    song_count: usize,
+    updated_at: String,
+}
+
+impl From<mpd::DbGroup> for DbGroup {
+    fn from(group: mpd::DbGroup) -> Self {
+        DbGroup {
+            kind: group.kind.into(),
+            name: group.name,
+            artist: group.artist,
+            uri: group.uri,
+            song_count: group.count,
+            updated_at: group.updated_at,
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DbRecentsQueryParams {
+    #[serde(default, with = "time::serde::iso8601::option")]
+    since: Option<OffsetDateTime>,
+    group_by: Option<DbGroupKind>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    cursor: Option<String>,
+}
+
+impl DbRecentsQueryParams {
+    fn since(&self, defaults: &config::Recents) -> OffsetDateTime {
+        self.since.unwrap_or_else(|| {
+            OffsetDateTime::now_utc() - time::Duration::days(defaults.days.into())
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum DbRecents {
+    Files(DbPage),
+    Groups(DbPage<DbGroup>),
+}
+
+impl IntoResponse for DbRecents {
+    fn into_response(self) -> Response {
+        match self {
+            DbRecents::Files(page) => page.into_response(),
+            DbRecents::Groups(page) => page.into_response(),
+        }
+    }
+}
+
 pub async fn recents(
+    Query(params): Query<DbRecentsQueryParams>,
+    headers: HeaderMap,
     Extension(handle): Extension<mpd::Handle>,
-) -> Result<Json<Vec<DbItem>>> {
-    let items = handle.db().recents().await?;
+    Extension(covers): Extension<covers::Handle>,
+    Extension(defaults): Extension<config::Recents>,
+) -> Result<Response> {
+    if !wants_ndjson(&headers) {
+        return Ok(load_recents(&handle, &covers, &defaults, params).await?.into_response());
+    }
+
+    if params.group_by.is_some() {
+        return Err(Error::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Grouped recents cannot be streamed".to_owned(),
+        ));
+    }
+
+    let since = params.since(&defaults);
+
+    let paging = DbPaging::new(params.offset, params.limit, params.cursor)?;
+
+    let window = paging.map(|it| it.window());
+
+    let response = stream_items(covers, async move {
+        handle.db().recents(since, window).await
+    });
+
+    Ok(response)
+}
+
+pub async fn load_recents(
+    handle: &mpd::Handle,
+    covers: &covers::Handle,
+    defaults: &config::Recents,
+    params: DbRecentsQueryParams,
+) -> Result<DbRecents> {
+    let since = params.since(defaults);
+
+    let paging = DbPaging::new(params.offset, params.limit, params.cursor)?;
+
+    // Groups are built from every recent file so they can only be paged here.
+    if let Some(kind) = params.group_by {
+        let groups = handle.db().recents_groups(since, kind.into()).await?;
 
-    let items = items.into_iter().map(Into::into).collect();
+        let total = groups.len();
 
-    Ok(Json(items))
+        let (groups, offset) = match paging {
+            Some(paging) => (paging.slice(groups), paging.offset),
+            None => (groups, 0),
+        };
+
+        return Ok(DbRecents::Groups(DbPage::new(groups.into_iter().map(Into::into).collect(), total, offset)));
+    }
+
+    let Some(paging) = paging else {
+        let items = handle.db().recents(since, None).await?;
+
+        let total = items.len();
+
+        return Ok(DbRecents::Files(DbPage::new(into_items(covers, items).await?, total, 0)));
+    };
+
+    let total = handle.db().recents_count(since).await?.nsongs as usize;
+
+    let items = handle.db().recents(since, Some(paging.window())).await?;
+
+    Ok(DbRecents::Files(DbPage::new(into_items(covers, items).await?, total, paging.offset)))
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn tagtypes(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<String>>> {
+    let result = handle.db().tagtypes().await?;
+
+    Ok(Json(result))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DbUpdateBody {
+    uri: Option<String>,
+}
+
+// The body is optional so that updating the whole database is a bare POST.
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn update(
+    Extension(handle): Extension<mpd::Handle>,
+    OptionalJson(body): OptionalJson<DbUpdateBody>,
+) -> Result<()> {
+    let uri = body.and_then(|body| body.uri);
+
+    handle.db().update(uri).await?;
+
+    Ok(())
+}
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_page_from_cursor() {
+        let paging = DbPaging::new(None, Some(2), Some("10".to_owned())).unwrap().unwrap();
+
+        assert_eq!(paging.window().to_window_string(), "10:12");
+        assert_eq!(paging.slice((0..20).collect()), vec![10, 11]);
+    }
+
+    #[test]
+    fn should_not_page_without_parameters() {
+        assert!(DbPaging::new(None, None, None).unwrap().is_none());
+    }
+
+    #[test]
+    fn should_point_to_next_page_only_if_there_is_one() {
+        let item = || DbItem::Directory { uri: "alfa".to_owned(), cover: None };
+
+        assert_eq!(DbPage::new(vec![item(), item()], 5, 2).next_offset, Some(4));
+        assert_eq!(DbPage::new(vec![item()], 5, 4).next_offset, None);
+    }
+
+    #[test]
+    fn should_serialize_page_with_total_and_cursor() {
+        let page = DbPage::new(vec!["alfa", "bravo"], 5, 2);
+
+        assert_eq!(
+            json::to_value(&page).unwrap(),
+            json::json!({"items": ["alfa", "bravo"], "total": 5, "nextCursor": "4"}),
+        );
+    }
+
+    #[test]
+    fn should_match_etag_in_if_none_match() {
+        assert!(matches_etag(r#""a-1", W/"b-2""#, r#""b-2""#));
+        assert!(matches_etag("*", r#""b-2""#));
+        assert!(!matches_etag(r#""a-1""#, r#""b-2""#));
+    }
 }
@@ -1,9 +1,11 @@
+use axum::extract::rejection::JsonRejection;
 use axum::http::StatusCode;
 use axum::Json;
 use axum::response::IntoResponse;
 use axum::response::Response;
 use serde::Serialize;
 
+use crate::covers;
 use crate::mpd;
 
 #[derive(Debug)]
@@ -16,6 +18,14 @@ impl Error {
     pub fn new(code: StatusCode, message: String) -> Self {
         Error { code, message }
     }
+
+    pub fn code(&self) -> StatusCode {
+        self.code
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
 }
 
 impl From<String> for Error {
@@ -39,6 +49,9 @@ impl From<mpd::Error> for Error {
             mpd::Error::AlreadyExists(msg) => {
                 (StatusCode::CONFLICT, msg)
             },
+            mpd::Error::Invalid(msg) => {
+                (StatusCode::BAD_REQUEST, msg)
+            },
             mpd::Error::Disconnected(msg) | mpd::Error::Unavailable(msg) => {
                 (StatusCode::SERVICE_UNAVAILABLE, msg)
             },
@@ -48,6 +61,23 @@ impl From<mpd::Error> for Error {
     }
 }
 
+impl From<JsonRejection> for Error {
+    fn from(rejection: JsonRejection) -> Self {
+        let message = rejection.to_string();
+
+        Error::new(rejection.into_response().status(), message)
+    }
+}
+
+impl From<covers::Error> for Error {
+    fn from(err: covers::Error) -> Self {
+        match err {
+            covers::Error::Mpd(err) => err.into(),
+            covers::Error::Persistence(msg) => msg.into(),
+        }
+    }
+}
+
 #[derive(Serialize)]
 struct ErrorResponse {
     message: String,
@@ -1,15 +1,20 @@
 use axum::Extension;
 use axum::extract::Query;
+use axum::http::StatusCode;
 use axum::Json;
 use serde::Deserialize;
 use serde::Serialize;
 use time::OffsetDateTime;
+use time::UtcOffset;
+use time::Weekday;
 
 use crate::convert::IntoOption;
 use crate::convert::MapInto;
 use crate::history;
 use crate::route::db::DbTags;
+use crate::route::error::Error;
 use crate::route::result::Result;
+use crate::time::Duration;
 
 #[derive(Debug, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -17,36 +22,348 @@ pub struct HistoryEntry {
     id: i64,
     uri: String,
     tags: DbTags,
+    duration: Duration,
+    listened: Duration,
+    completion: Option<f64>,
+    skipped: bool,
     #[serde(with = "time::serde::iso8601")]
     recorded_at: OffsetDateTime,
 }
 
 impl From<history::HistoryEntry> for HistoryEntry {
-    fn from(history::HistoryEntry { id, uri, tags, recorded_at, .. }: history::HistoryEntry) -> Self {
+    fn from(history::HistoryEntry { id, uri, tags, duration, session, recorded_at }: history::HistoryEntry) -> Self {
         HistoryEntry {
             id,
             uri,
             tags: tags.into(),
+            duration: duration.into(),
+            listened: session.listened.into(),
+            completion: session.completion,
+            skipped: session.skipped,
             recorded_at,
         }
     }
 }
 
 #[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct HistoryQueryParams {
     #[serde(with = "time::serde::iso8601")]
     from: OffsetDateTime,
     #[serde(default, with = "time::serde::iso8601::option")]
     to: Option<OffsetDateTime>,
+    min_completion: Option<f64>,
+    skipped: Option<bool>,
+}
+
+// Plays that can't be completed have no completion to compare against.
+fn matches_filters(entry: &history::HistoryEntry, params: &HistoryQueryParams) -> bool {
+    let matches_completion = params.min_completion.is_none_or(|min_completion| {
+        entry.session.completion.is_some_and(|completion| completion >= min_completion)
+    });
+
+    let matches_skipped = params.skipped.is_none_or(|skipped| entry.session.skipped == skipped);
+
+    matches_completion && matches_skipped
 }
 
 pub async fn history(
     Query(params): Query<HistoryQueryParams>,
     Extension(handle): Extension<history::Handle>,
 ) -> Result<Json<Vec<HistoryEntry>>> {
+    let result = load_history(&handle, params).await?;
+
+    Ok(Json(result))
+}
+
+pub async fn load_history(handle: &history::Handle, params: HistoryQueryParams) -> Result<Vec<HistoryEntry>> {
+    if params.min_completion.is_some_and(|min_completion| !(0.0..=1.0).contains(&min_completion)) {
+        return Err(Error::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "Expected minCompletion to be between 0 and 1".to_owned(),
+        ));
+    }
+
     let result = handle.get(params.from.into_some(), params.to)
         .await?
-        .map_into();
+        .into_iter()
+        .filter(|entry| matches_filters(entry, &params))
+        .map(Into::into)
+        .collect();
+
+    Ok(result)
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryTally {
+    plays: i64,
+    listened: Duration,
+}
+
+impl From<history::PlaybackHistoryTally> for HistoryTally {
+    fn from(tally: history::PlaybackHistoryTally) -> Self {
+        HistoryTally {
+            plays: tally.plays,
+            listened: tally.listened.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryTotals {
+    #[serde(flatten)]
+    tally: HistoryTally,
+    tracks: i64,
+    artists: i64,
+    albums: i64,
+}
+
+impl From<history::PlaybackHistoryTotals> for HistoryTotals {
+    fn from(totals: history::PlaybackHistoryTotals) -> Self {
+        HistoryTotals {
+            tally: totals.tally.into(),
+            tracks: totals.tracks,
+            artists: totals.artists,
+            albums: totals.albums,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryArtist {
+    artist: String,
+    #[serde(flatten)]
+    tally: HistoryTally,
+}
+
+impl From<history::PlaybackHistoryArtist> for HistoryArtist {
+    fn from(artist: history::PlaybackHistoryArtist) -> Self {
+        HistoryArtist {
+            artist: artist.artist,
+            tally: artist.tally.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryAlbum {
+    album: String,
+    album_artist: Option<String>,
+    #[serde(flatten)]
+    tally: HistoryTally,
+}
+
+impl From<history::PlaybackHistoryAlbum> for HistoryAlbum {
+    fn from(album: history::PlaybackHistoryAlbum) -> Self {
+        HistoryAlbum {
+            album: album.album,
+            album_artist: album.album_artist,
+            tally: album.tally.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryTrack {
+    uri: String,
+    title: Option<String>,
+    artist: Option<String>,
+    #[serde(flatten)]
+    tally: HistoryTally,
+}
+
+impl From<history::PlaybackHistoryTrack> for HistoryTrack {
+    fn from(track: history::PlaybackHistoryTrack) -> Self {
+        HistoryTrack {
+            uri: track.uri,
+            title: track.title,
+            artist: track.artist,
+            tally: track.tally.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryHour {
+    hour: u8,
+    #[serde(flatten)]
+    tally: HistoryTally,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryDay {
+    day: String,
+    #[serde(flatten)]
+    tally: HistoryTally,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryWeekday {
+    weekday: String,
+    #[serde(flatten)]
+    tally: HistoryTally,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryComparison {
+    #[serde(with = "time::serde::iso8601")]
+    from: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    to: OffsetDateTime,
+    totals: HistoryTotals,
+    plays_change: Option<f64>,
+    listened_change: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    #[serde(with = "time::serde::iso8601")]
+    from: OffsetDateTime,
+    #[serde(with = "time::serde::iso8601")]
+    to: OffsetDateTime,
+    totals: HistoryTotals,
+    artists: Vec<HistoryArtist>,
+    albums: Vec<HistoryAlbum>,
+    tracks: Vec<HistoryTrack>,
+    hours: Vec<HistoryHour>,
+    days: Vec<HistoryDay>,
+    weekdays: Vec<HistoryWeekday>,
+    previous: HistoryComparison,
+}
+
+// Relative to the previous value, which makes it undefined when that's zero.
+fn change(current: f64, previous: f64) -> Option<f64> {
+    if previous == 0.0 {
+        return None;
+    }
+
+    Some((current - previous) / previous)
+}
+
+fn format_weekday(weekday: Weekday) -> String {
+    weekday.to_string().to_lowercase()
+}
+
+impl From<history::HistoryStats> for HistoryStats {
+    fn from(history::HistoryStats { from, to, current, previous }: history::HistoryStats) -> Self {
+        let plays_change = change(current.totals.tally.plays as f64, previous.tally.plays as f64);
+
+        let listened_change = change(
+            current.totals.tally.listened.as_seconds_f64(),
+            previous.tally.listened.as_seconds_f64(),
+        );
+
+        HistoryStats {
+            from,
+            to,
+            totals: current.totals.into(),
+            artists: current.artists.map_into(),
+            albums: current.albums.map_into(),
+            tracks: current.tracks.map_into(),
+            hours: current.hours.into_iter()
+                .map(|(hour, tally)| HistoryHour { hour, tally: tally.into() })
+                .collect(),
+            days: current.days.into_iter()
+                .map(|(day, tally)| HistoryDay { day, tally: tally.into() })
+                .collect(),
+            weekdays: current.weekdays.into_iter()
+                .map(|(weekday, tally)| HistoryWeekday { weekday: format_weekday(weekday), tally: tally.into() })
+                .collect(),
+            previous: HistoryComparison {
+                from: from - (to - from),
+                to: from,
+                totals: previous.into(),
+                plays_change,
+                listened_change,
+            },
+        }
+    }
+}
+
+const DEFAULT_HISTORY_STATS_LIMIT: usize = 10;
+const MAX_HISTORY_STATS_LIMIT: usize = 100;
+
+// UTC offsets range from -12:00 to +14:00, with some room to spare.
+const MAX_UTC_OFFSET_MINUTES: i16 = 18 * 60;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStatsQueryParams {
+    #[serde(with = "time::serde::iso8601")]
+    from: OffsetDateTime,
+    #[serde(default, with = "time::serde::iso8601::option")]
+    to: Option<OffsetDateTime>,
+    limit: Option<usize>,
+    // Offset in minutes of the time zone that plays are grouped by hour and day in.
+    utc_offset: Option<i16>,
+}
+
+pub async fn stats(
+    Query(params): Query<HistoryStatsQueryParams>,
+    Extension(handle): Extension<history::Handle>,
+) -> Result<Json<HistoryStats>> {
+    let result = load_history_stats(&handle, params).await?;
 
     Ok(Json(result))
 }
+
+pub async fn load_history_stats(handle: &history::Handle, params: HistoryStatsQueryParams) -> Result<HistoryStats> {
+    let to = params.to.unwrap_or_else(OffsetDateTime::now_utc);
+
+    if params.from >= to {
+        return Err(Error::new(StatusCode::UNPROCESSABLE_ENTITY, "Expected from to be before to".to_owned()));
+    }
+
+    let limit = params.limit.unwrap_or(DEFAULT_HISTORY_STATS_LIMIT);
+
+    if limit > MAX_HISTORY_STATS_LIMIT {
+        return Err(Error::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            format!("Expected limit to be at most {MAX_HISTORY_STATS_LIMIT}"),
+        ));
+    }
+
+    let utc_offset = match params.utc_offset {
+        Some(minutes) if minutes.abs() > MAX_UTC_OFFSET_MINUTES => {
+            return Err(Error::new(
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("Expected utcOffset to be between -{MAX_UTC_OFFSET_MINUTES} and {MAX_UTC_OFFSET_MINUTES}"),
+            ));
+        },
+        Some(minutes) => {
+            UtcOffset::from_whole_seconds(i32::from(minutes) * 60)
+                .map_err(|err| err.to_string())?
+        },
+        None => UtcOffset::UTC,
+    };
+
+    let result = handle.stats(params.from, to, limit, utc_offset)
+        .await?
+        .into();
+
+    Ok(result)
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_compute_relative_change() {
+        assert_eq!(change(15.0, 10.0), Some(0.5));
+        assert_eq!(change(5.0, 10.0), Some(-0.5));
+        assert_eq!(change(5.0, 0.0), None);
+    }
+}
@@ -0,0 +1,116 @@
+use axum::Extension;
+use axum::extract::Query;
+use axum::Json;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::mpd;
+use crate::route::result::Result;
+use crate::time::Duration;
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    name: String,
+    song_count: i64,
+    playtime: Duration,
+}
+
+impl From<mpd::TagCount> for TagCount {
+    fn from(mpd::TagCount { value, count }: mpd::TagCount) -> Self {
+        TagCount {
+            name: value,
+            song_count: count.nsongs,
+            playtime: count.playtime.into(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Album {
+    artist: String,
+    name: String,
+    date: Option<String>,
+    song_count: i64,
+    playtime: Duration,
+}
+
+impl From<mpd::Album> for Album {
+    fn from(mpd::Album { artist, name, date, count }: mpd::Album) -> Self {
+        Album {
+            artist,
+            name,
+            date: Some(date).filter(|it| !it.is_empty()),
+            song_count: count.nsongs,
+            playtime: count.playtime.into(),
+        }
+    }
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn artists(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<TagCount>>> {
+    let items = load_artists(&handle).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_artists(handle: &mpd::Handle) -> Result<Vec<TagCount>> {
+    let items = handle.library().artists().await?;
+
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LibraryAlbumsQueryParams {
+    artist: Option<String>,
+    genre: Option<String>,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn albums(
+    Query(params): Query<LibraryAlbumsQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<Album>>> {
+    let items = load_albums(&handle, params).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_albums(handle: &mpd::Handle, params: LibraryAlbumsQueryParams) -> Result<Vec<Album>> {
+    let items = handle.library().albums(params.artist, params.genre).await?;
+
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn genres(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<TagCount>>> {
+    let items = load_genres(&handle).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_genres(handle: &mpd::Handle) -> Result<Vec<TagCount>> {
+    let items = handle.library().genres().await?;
+
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn years(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<TagCount>>> {
+    let items = load_years(&handle).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_years(handle: &mpd::Handle) -> Result<Vec<TagCount>> {
+    let items = handle.library().years().await?;
+
+    Ok(items.into_iter().map(Into::into).collect())
+}
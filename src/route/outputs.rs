@@ -0,0 +1,109 @@
+use std::collections::BTreeMap;
+
+use axum::Extension;
+use axum::extract::Path;
+use axum::Json;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::mpd;
+use crate::route::result::Result;
+
+#[derive(Debug, Serialize)]
+pub struct Output {
+    id: i64,
+    name: String,
+    plugin: Option<String>,
+    enabled: bool,
+    attributes: BTreeMap<String, String>,
+}
+
+impl From<mpd::Output> for Output {
+    fn from(
+        mpd::Output {
+            id,
+            name,
+            plugin,
+            enabled,
+            attributes,
+        }: mpd::Output,
+    ) -> Self {
+        Output {
+            id,
+            name,
+            plugin,
+            enabled,
+            attributes,
+        }
+    }
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn outputs(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<Output>>> {
+    let items = load_outputs(&handle).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_outputs(handle: &mpd::Handle) -> Result<Vec<Output>> {
+    let items = handle.outputs().list().await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(items)
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputPathParams {
+    id: i64,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn enable(
+    Path(params): Path<OutputPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.outputs().enable(params.id).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn disable(
+    Path(params): Path<OutputPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.outputs().disable(params.id).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn toggle(
+    Path(params): Path<OutputPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.outputs().toggle(params.id).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct OutputSetBody {
+    name: String,
+    value: String,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn set(
+    Path(params): Path<OutputPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<OutputSetBody>,
+) -> Result<()> {
+    handle.outputs().set(params.id, body.name, body.value).await?;
+
+    Ok(())
+}
@@ -0,0 +1,69 @@
+use axum::Extension;
+use axum::extract::Path;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::mpd;
+use crate::route::result::Result;
+
+#[tracing::instrument(ret, skip(partitions), level = "debug")]
+pub async fn partitions(
+    Extension(partitions): Extension<mpd::PartitionRegistry>,
+) -> Result<Json<Vec<String>>> {
+    let items = partitions.list().await?;
+
+    Ok(Json(items))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartitionCreateBody {
+    name: String,
+}
+
+#[tracing::instrument(ret, skip(partitions), level = "debug")]
+pub async fn create(
+    Extension(partitions): Extension<mpd::PartitionRegistry>,
+    Json(body): Json<PartitionCreateBody>,
+) -> Result<()> {
+    partitions.create(body.name).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartitionDeletePathParams {
+    name: String,
+}
+
+#[tracing::instrument(ret, skip(partitions), level = "debug")]
+pub async fn delete(
+    Path(params): Path<PartitionDeletePathParams>,
+    Extension(partitions): Extension<mpd::PartitionRegistry>,
+) -> Result<()> {
+    partitions.delete(params.name).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartitionMoveOutputPathParams {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PartitionMoveOutputBody {
+    output: String,
+}
+
+#[tracing::instrument(ret, skip(partitions), level = "debug")]
+pub async fn move_output(
+    Path(params): Path<PartitionMoveOutputPathParams>,
+    Extension(partitions): Extension<mpd::PartitionRegistry>,
+    Json(body): Json<PartitionMoveOutputBody>,
+) -> Result<()> {
+    let partition = partitions.get(&params.name).await?;
+
+    partition.handle.partitions().move_output(body.output).await?;
+
+    Ok(())
+}
@@ -0,0 +1,58 @@
+use axum::Extension;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::mpd;
+use crate::route::body::OptionalJson;
+use crate::route::result::Result;
+
+#[derive(Debug, Deserialize)]
+pub struct PlaybackPlayBody {
+    id: Option<i64>,
+}
+
+// The body is optional so that resuming playback is a bare POST.
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn play(
+    Extension(handle): Extension<mpd::Handle>,
+    OptionalJson(body): OptionalJson<PlaybackPlayBody>,
+) -> Result<()> {
+    let id = body.and_then(|body| body.id);
+
+    handle.playback().play(id).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn toggle(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.playback().toggle().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn stop(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.playback().stop().await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaybackSeekBody {
+    time: f64,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn seek(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<PlaybackSeekBody>,
+) -> Result<()> {
+    handle.playback().seek(body.time).await?;
+
+    Ok(())
+}
@@ -6,6 +6,7 @@ use serde::Serialize;
 
 use crate::mpd;
 use crate::route::db::DbItem;
+use crate::route::body::OptionalJson;
 use crate::route::result::Result;
 
 #[derive(Debug, Deserialize)]
@@ -18,12 +19,18 @@ pub async fn playlist(
     Path(params): Path<PlaylistPathParams>,
     Extension(handle): Extension<mpd::Handle>,
 ) -> Result<Json<Vec<DbItem>>> {
+    let items = load_playlist(&handle, params).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_playlist(handle: &mpd::Handle, params: PlaylistPathParams) -> Result<Vec<DbItem>> {
     let items = handle.playlists().get(params.name).await?
         .into_iter()
         .map(Into::into)
         .collect();
 
-    Ok(Json(items))
+    Ok(items)
 }
 
 #[derive(Debug, Serialize)]
@@ -43,12 +50,18 @@ impl From<mpd::Playlist> for Playlist {
 pub async fn playlists(
     Extension(handle): Extension<mpd::Handle>,
 ) -> Result<Json<Vec<Playlist>>> {
+    let items = load_playlists(&handle).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_playlists(handle: &mpd::Handle) -> Result<Vec<Playlist>> {
     let items = handle.playlists().list().await?
         .into_iter()
         .map(Into::into)
         .collect();
 
-    Ok(Json(items))
+    Ok(items)
 }
 
 #[derive(Debug, Deserialize)]
@@ -86,3 +99,126 @@ pub async fn delete_songs(
 
     Ok(())
 }
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum PlaylistSaveMode {
+    Create,
+    Append,
+    Replace,
+}
+
+impl From<PlaylistSaveMode> for mpd::PlaylistSaveMode {
+    fn from(mode: PlaylistSaveMode) -> Self {
+        match mode {
+            PlaylistSaveMode::Create => mpd::PlaylistSaveMode::Create,
+            PlaylistSaveMode::Append => mpd::PlaylistSaveMode::Append,
+            PlaylistSaveMode::Replace => mpd::PlaylistSaveMode::Replace,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistSavePathParams {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistSaveBody {
+    mode: Option<PlaylistSaveMode>,
+}
+
+// The body is optional so that creating a playlist from the queue is a bare POST.
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn save(
+    Path(params): Path<PlaylistSavePathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    OptionalJson(body): OptionalJson<PlaylistSaveBody>,
+) -> Result<()> {
+    let mode = body.and_then(|body| body.mode)
+        .unwrap_or(PlaylistSaveMode::Create);
+
+    handle.playlists().save(params.name, mode.into()).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistAddSongsPathParams {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistAddSongsBody {
+    uris: Vec<String>,
+    position: Option<usize>,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn add_songs(
+    Path(params): Path<PlaylistAddSongsPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<PlaylistAddSongsBody>,
+) -> Result<()> {
+    handle.playlists().add(params.name, body.uris, body.position).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistRenamePathParams {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistRenameBody {
+    name: String,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn rename(
+    Path(params): Path<PlaylistRenamePathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<PlaylistRenameBody>,
+) -> Result<()> {
+    handle.playlists().rename(params.name, body.name).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistMoveSongPathParams {
+    name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistMoveSongBody {
+    from: usize,
+    to: usize,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn move_song(
+    Path(params): Path<PlaylistMoveSongPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<PlaylistMoveSongBody>,
+) -> Result<()> {
+    handle.playlists().move_song(params.name, body.from, body.to).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistClearPathParams {
+    name: String,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn clear(
+    Path(params): Path<PlaylistClearPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.playlists().clear(params.name).await?;
+
+    Ok(())
+}
@@ -0,0 +1,315 @@
+use axum::Extension;
+use axum::extract::Path;
+use axum::Json;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::convert::MapInto;
+use crate::mpd;
+use crate::route::body::OptionalJson;
+use crate::route::result::Result;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum QueueSource {
+    File { uri: String },
+    Playlist { name: String },
+}
+
+impl From<QueueSource> for mpd::QueueSource {
+    fn from(source: QueueSource) -> Self {
+        match source {
+            QueueSource::File { uri } => mpd::QueueSource::File { uri },
+            QueueSource::Playlist { name } => mpd::QueueSource::Playlist { name },
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueRange {
+    start: usize,
+    end: Option<usize>,
+}
+
+impl From<QueueRange> for mpd::QueueRange {
+    fn from(QueueRange { start, end }: QueueRange) -> Self {
+        mpd::QueueRange { start, end }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OneshotState {
+    On,
+    Off,
+    Oneshot,
+}
+
+impl From<mpd::OneshotState> for OneshotState {
+    fn from(state: mpd::OneshotState) -> Self {
+        use mpd::OneshotState::*;
+
+        match state {
+            On => OneshotState::On,
+            Off => OneshotState::Off,
+            Oneshot => OneshotState::Oneshot,
+        }
+    }
+}
+
+impl From<OneshotState> for mpd::OneshotState {
+    fn from(state: OneshotState) -> Self {
+        use OneshotState::*;
+
+        match state {
+            On => mpd::OneshotState::On,
+            Off => mpd::OneshotState::Off,
+            Oneshot => mpd::OneshotState::Oneshot,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueSourcesBody {
+    sources: Vec<QueueSource>,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn add(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueSourcesBody>,
+) -> Result<()> {
+    handle.queue().add(body.sources.map_into()).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn replace(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueSourcesBody>,
+) -> Result<()> {
+    handle.queue().replace(body.sources.map_into()).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn clear(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.queue().clear().await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueRemovePathParams {
+    id: i64,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn remove(
+    Path(params): Path<QueueRemovePathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.queue().remove(params.id).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueRangeBody {
+    range: QueueRange,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn remove_range(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueRangeBody>,
+) -> Result<()> {
+    handle.queue().remove_range(body.range.into()).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueMoveBody {
+    range: QueueRange,
+    to: usize,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn move_range(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueMoveBody>,
+) -> Result<()> {
+    handle.queue().move_range(body.range.into(), body.to).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueItemPathParams {
+    id: i64,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueMoveIdBody {
+    to: usize,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn move_id(
+    Path(params): Path<QueueItemPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueMoveIdBody>,
+) -> Result<()> {
+    handle.queue().move_id(params.id, body.to).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn play_next(
+    Path(params): Path<QueueItemPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.queue().play_next(params.id).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueSwapBody {
+    with: i64,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn swap(
+    Path(params): Path<QueueItemPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueSwapBody>,
+) -> Result<()> {
+    handle.queue().swap(params.id, body.with).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueuePrioBody {
+    range: QueueRange,
+    priority: u8,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn prio(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueuePrioBody>,
+) -> Result<()> {
+    handle.queue().prio(body.range.into(), body.priority).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueuePrioIdBody {
+    priority: u8,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn prio_id(
+    Path(params): Path<QueueItemPathParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueuePrioIdBody>,
+) -> Result<()> {
+    handle.queue().prio_ids(vec![params.id], body.priority).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueShuffleBody {
+    range: Option<QueueRange>,
+}
+
+// The body is optional so that shuffling the whole queue is a bare POST.
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn shuffle(
+    Extension(handle): Extension<mpd::Handle>,
+    OptionalJson(body): OptionalJson<QueueShuffleBody>,
+) -> Result<()> {
+    let range = body.and_then(|body| body.range);
+
+    handle.queue().shuffle(range.map(Into::into)).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn next(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.queue().next().await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn prev(
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.queue().prev().await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueStateBody {
+    state: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueueOneshotStateBody {
+    state: OneshotState,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn repeat(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueStateBody>,
+) -> Result<()> {
+    handle.queue().repeat(body.state).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn consume(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueOneshotStateBody>,
+) -> Result<()> {
+    handle.queue().consume(body.state.into()).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn random(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueStateBody>,
+) -> Result<()> {
+    handle.queue().random(body.state).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn single(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<QueueOneshotStateBody>,
+) -> Result<()> {
+    handle.queue().single(body.state.into()).await?;
+
+    Ok(())
+}
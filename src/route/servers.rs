@@ -0,0 +1,53 @@
+use std::collections::HashMap;
+
+use axum::Extension;
+use axum::extract::Path;
+use axum::http::Request;
+use axum::http::StatusCode;
+use axum::Json;
+use axum::middleware::Next;
+use axum::response::IntoResponse;
+use axum::response::Response;
+
+use crate::route::error::Error;
+use crate::route::result::Result;
+use crate::servers;
+
+#[tracing::instrument(ret, skip(registry), level = "debug")]
+pub async fn servers(
+    Extension(registry): Extension<servers::Registry>,
+) -> Result<Json<Vec<String>>> {
+    Ok(Json(registry.names().to_vec()))
+}
+
+// Swaps the default server's handles for the ones of the server named in the
+// path so that the routes nested under it need not know about servers at all.
+pub async fn select<B>(
+    Extension(registry): Extension<servers::Registry>,
+    Path(params): Path<HashMap<String, String>>,
+    mut request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let Some(name) = params.get("server") else {
+        return Error::new(StatusCode::INTERNAL_SERVER_ERROR, "server is missing from path".to_owned())
+            .into_response();
+    };
+
+    let Some(server) = registry.get(name) else {
+        return Error::new(StatusCode::NOT_FOUND, format!("server '{name}' does not exist"))
+            .into_response();
+    };
+
+    let server = server.clone();
+    let partition = server.partitions.default_partition();
+
+    let extensions = request.extensions_mut();
+
+    extensions.insert(partition.handle);
+    extensions.insert(server.partitions);
+    extensions.insert(server.history);
+    extensions.insert(server.index);
+    extensions.insert(server.covers);
+
+    next.run(request).await
+}
@@ -0,0 +1,149 @@
+use axum::Extension;
+use axum::extract::Path;
+use axum::extract::Query;
+use axum::Json;
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::mpd;
+use crate::route::result::Result;
+
+#[derive(Debug, Serialize)]
+pub struct Sticker {
+    name: String,
+    value: String,
+}
+
+impl From<mpd::Sticker> for Sticker {
+    fn from(mpd::Sticker { name, value }: mpd::Sticker) -> Self {
+        Sticker { name, value }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct StickerMatch {
+    uri: String,
+    #[serde(flatten)]
+    sticker: Sticker,
+}
+
+impl From<mpd::StickerMatch> for StickerMatch {
+    fn from(mpd::StickerMatch { uri, sticker }: mpd::StickerMatch) -> Self {
+        StickerMatch {
+            uri,
+            sticker: sticker.into(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StickerQueryParams {
+    uri: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StickerPathParams {
+    name: String,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn stickers(
+    Query(params): Query<StickerQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<Sticker>>> {
+    let items = load_stickers(&handle, params).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_stickers(handle: &mpd::Handle, params: StickerQueryParams) -> Result<Vec<Sticker>> {
+    let items = handle.stickers().list(params.uri).await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(items)
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn sticker(
+    Path(path_params): Path<StickerPathParams>,
+    Query(params): Query<StickerQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Sticker>> {
+    let item = load_sticker(&handle, params.uri, path_params.name).await?;
+
+    Ok(Json(item))
+}
+
+pub async fn load_sticker(handle: &mpd::Handle, uri: String, name: String) -> Result<Sticker> {
+    let item = handle.stickers().get(uri, name).await?;
+
+    Ok(item.into())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StickerSetBody {
+    value: String,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn set(
+    Path(path_params): Path<StickerPathParams>,
+    Query(params): Query<StickerQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<StickerSetBody>,
+) -> Result<()> {
+    handle.stickers().set(params.uri, path_params.name, body.value).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn delete(
+    Path(path_params): Path<StickerPathParams>,
+    Query(params): Query<StickerQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.stickers().delete(params.uri, Some(path_params.name)).await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn clear(
+    Query(params): Query<StickerQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<()> {
+    handle.stickers().delete(params.uri, None).await?;
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StickerFindQueryParams {
+    // Searches the whole database by default.
+    #[serde(default)]
+    uri: String,
+    name: String,
+    value: Option<String>,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn find(
+    Query(params): Query<StickerFindQueryParams>,
+    Extension(handle): Extension<mpd::Handle>,
+) -> Result<Json<Vec<StickerMatch>>> {
+    let items = load_matches(&handle, params).await?;
+
+    Ok(Json(items))
+}
+
+pub async fn load_matches(handle: &mpd::Handle, params: StickerFindQueryParams) -> Result<Vec<StickerMatch>> {
+    let items = handle.stickers().find(params.uri, params.name, params.value).await?
+        .into_iter()
+        .map(Into::into)
+        .collect();
+
+    Ok(items)
+}
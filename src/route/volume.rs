@@ -0,0 +1,21 @@
+use axum::Extension;
+use axum::Json;
+use serde::Deserialize;
+
+use crate::mpd;
+use crate::route::result::Result;
+
+#[derive(Debug, Deserialize)]
+pub struct VolumeSetBody {
+    value: u8,
+}
+
+#[tracing::instrument(ret, skip(handle), level = "debug")]
+pub async fn set(
+    Extension(handle): Extension<mpd::Handle>,
+    Json(body): Json<VolumeSetBody>,
+) -> Result<()> {
+    handle.volume().set(body.value).await?;
+
+    Ok(())
+}
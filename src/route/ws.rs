@@ -6,11 +6,23 @@ use axum::extract::ws::Message;
 use axum::extract::ws::WebSocket;
 use axum::response::IntoResponse;
 
+use crate::config;
+use crate::covers;
 use crate::convert::MapInto;
+use crate::history;
+use crate::index;
 use crate::mpd;
+use crate::route::db;
+use crate::route::history::load_history;
+use crate::route::history::load_history_stats;
+use crate::route::library;
+use crate::route::outputs;
+use crate::route::playlists;
+use crate::route::stickers;
 use crate::route::ws::action::Action;
 use crate::route::ws::proto::Out;
 use crate::route::ws::proto::Request;
+use crate::route::ws::proto::ResponseContent;
 use crate::route::ws::proto::Status;
 use crate::route::ws::proto::Update;
 use crate::route::ws::proto::UpdateKind;
@@ -65,87 +77,246 @@ impl Socket {
 
 struct Handle {
     inner: mpd::Handle,
+    sub_handle: mpd::SubscriptionHandle,
+    partition: String,
+    partitions: mpd::PartitionRegistry,
+    history: history::Handle,
+    index: index::Handle,
+    covers: covers::Handle,
+    recents: config::Recents,
 }
 
 impl Handle {
-    fn new(handle: mpd::Handle) -> Self {
-        Handle { inner: handle }
+    fn new(
+        partitions: mpd::PartitionRegistry,
+        history_handle: history::Handle,
+        index_handle: index::Handle,
+        covers_handle: covers::Handle,
+        recents: config::Recents,
+    ) -> Self {
+        let mpd::Partition { handle, sub_handle } = partitions.default_partition();
+
+        Handle {
+            inner: handle,
+            sub_handle,
+            partition: mpd::DEFAULT_PARTITION.to_owned(),
+            partitions,
+            history: history_handle,
+            index: index_handle,
+            covers: covers_handle,
+            recents,
+        }
+    }
+}
+
+impl Handle {
+    fn select(&mut self, name: String, mpd::Partition { handle, sub_handle }: mpd::Partition) {
+        self.inner = handle;
+        self.sub_handle = sub_handle;
+        self.partition = name;
+    }
+
+    fn select_default(&mut self) {
+        let partition = self.partitions.default_partition();
+
+        self.select(mpd::DEFAULT_PARTITION.to_owned(), partition);
     }
 }
 
 impl Handle {
-    async fn process(&self, action: Action) -> Status {
-        let result = match action {
+    async fn process(&mut self, action: Action) -> ResponseContent {
+        match action {
             // Database actions.
+            Action::DbGet(params) => {
+                db::load_database(&self.inner, &self.index, &self.covers, params).await.into()
+            },
+            Action::DbCount(params) => {
+                db::load_count(&self.inner, params).await.into()
+            },
+            Action::DbRecents(params) => {
+                db::load_recents(&self.inner, &self.covers, &self.recents, params).await.into()
+            },
+            Action::DbSearch(body) => {
+                db::load_search(&self.inner, &self.covers, body).await.into()
+            },
             Action::DbUpdate { uri } => {
-                self.inner.db().update(uri).await
+                self.inner.db().update(uri).await.into()
+            },
+            // Library actions.
+            Action::LibraryArtists => {
+                library::load_artists(&self.inner).await.into()
+            },
+            Action::LibraryAlbums(params) => {
+                library::load_albums(&self.inner, params).await.into()
+            },
+            Action::LibraryGenres => {
+                library::load_genres(&self.inner).await.into()
+            },
+            Action::LibraryYears => {
+                library::load_years(&self.inner).await.into()
             },
             // Queue actions.
             Action::QueueAdd { sources } => {
-                self.inner.queue().add(sources.map_into()).await
+                self.inner.queue().add(sources.map_into()).await.into()
             },
             Action::QueueReplace { sources } => {
-                self.inner.queue().replace(sources.map_into()).await
+                self.inner.queue().replace(sources.map_into()).await.into()
             },
             Action::QueueClear => {
-                self.inner.queue().clear().await
+                self.inner.queue().clear().await.into()
             },
             Action::QueueRemove { id } => {
-                self.inner.queue().remove(id).await
+                self.inner.queue().remove(id).await.into()
+            },
+            Action::QueueRemoveRange { range } => {
+                self.inner.queue().remove_range(range.into()).await.into()
+            },
+            Action::QueueMove { range, to } => {
+                self.inner.queue().move_range(range.into(), to).await.into()
+            },
+            Action::QueueMoveId { id, to } => {
+                self.inner.queue().move_id(id, to).await.into()
+            },
+            Action::QueuePlayNext { id } => {
+                self.inner.queue().play_next(id).await.into()
+            },
+            Action::QueueSwap { id, with } => {
+                self.inner.queue().swap(id, with).await.into()
+            },
+            Action::QueuePrio { range, priority } => {
+                self.inner.queue().prio(range.into(), priority).await.into()
+            },
+            Action::QueuePrioId { ids, priority } => {
+                self.inner.queue().prio_ids(ids, priority).await.into()
+            },
+            Action::QueueShuffle { range } => {
+                self.inner.queue().shuffle(range.map(Into::into)).await.into()
             },
             Action::QueueNext => {
-                self.inner.queue().next().await
+                self.inner.queue().next().await.into()
             },
             Action::QueuePrev => {
-                self.inner.queue().prev().await
+                self.inner.queue().prev().await.into()
             },
             Action::QueueRepeat { state } => {
-                self.inner.queue().repeat(state).await
+                self.inner.queue().repeat(state).await.into()
             },
             Action::QueueConsume { state } => {
-                self.inner.queue().consume(state.into()).await
+                self.inner.queue().consume(state.into()).await.into()
             },
             Action::QueueRandom { state } => {
-                self.inner.queue().random(state).await
+                self.inner.queue().random(state).await.into()
             },
             Action::QueueSingle { state } => {
-                self.inner.queue().single(state.into()).await
+                self.inner.queue().single(state.into()).await.into()
             },
             // Playback actions.
             Action::PlaybackPlay { id } => {
-                self.inner.playback().play(id).await
+                self.inner.playback().play(id).await.into()
             },
             Action::PlaybackToggle => {
-                self.inner.playback().toggle().await
+                self.inner.playback().toggle().await.into()
             },
             Action::PlaybackStop => {
-                self.inner.playback().stop().await
+                self.inner.playback().stop().await.into()
             },
             Action::PlaybackSeek { time } => {
-                self.inner.playback().seek(time).await
+                self.inner.playback().seek(time).await.into()
             },
             // Volume actions.
             Action::VolumeSet { value } => {
-                self.inner.volume().set(value).await
+                self.inner.volume().set(value).await.into()
             },
-        };
-
-        result.map_or_else(Into::into, |_| Status::success())
+            // Playlist actions.
+            Action::PlaylistsList => {
+                playlists::load_playlists(&self.inner).await.into()
+            },
+            Action::PlaylistsGet(params) => {
+                playlists::load_playlist(&self.inner, params).await.into()
+            },
+            // History actions.
+            Action::HistoryGet(params) => {
+                load_history(&self.history, params).await.into()
+            },
+            Action::HistoryStats(params) => {
+                load_history_stats(&self.history, params).await.into()
+            },
+            // Output actions.
+            Action::OutputsList => {
+                outputs::load_outputs(&self.inner).await.into()
+            },
+            Action::OutputsEnable { id } => {
+                self.inner.outputs().enable(id).await.into()
+            },
+            Action::OutputsDisable { id } => {
+                self.inner.outputs().disable(id).await.into()
+            },
+            Action::OutputsToggle { id } => {
+                self.inner.outputs().toggle(id).await.into()
+            },
+            Action::OutputsSet { id, name, value } => {
+                self.inner.outputs().set(id, name, value).await.into()
+            },
+            // Partition actions.
+            Action::PartitionSelect { name } => {
+                self.partitions.get(&name).await
+                    .map(|partition| self.select(name, partition))
+                    .into()
+            },
+            Action::PartitionsList => {
+                self.partitions.list().await
+                    .map_err(Into::into)
+                    .into()
+            },
+            Action::PartitionsCreate { name } => {
+                self.partitions.create(name).await.into()
+            },
+            Action::PartitionsDelete { name } => {
+                self.partitions.delete(name).await.into()
+            },
+            Action::PartitionsMoveOutput { name } => {
+                self.inner.partitions().move_output(name).await.into()
+            },
+            // Sticker actions.
+            Action::StickersList(params) => {
+                stickers::load_stickers(&self.inner, params).await.into()
+            },
+            Action::StickersGet { uri, name } => {
+                stickers::load_sticker(&self.inner, uri, name).await.into()
+            },
+            Action::StickersSet { uri, name, value } => {
+                self.inner.stickers().set(uri, name, value).await.into()
+            },
+            Action::StickersDelete { uri, name } => {
+                self.inner.stickers().delete(uri, name).await.into()
+            },
+            Action::StickersFind(params) => {
+                stickers::load_matches(&self.inner, params).await.into()
+            },
+        }
     }
 
     async fn initial_update(&self) -> Update {
-        match (self.inner.status().get().await, self.inner.queue().get().await) {
-            (Ok(status), Ok(queue)) => {
+        let result = (
+            self.inner.status().get().await,
+            self.inner.queue().get().await,
+            self.inner.outputs().list().await,
+        );
+
+        match result {
+            (Ok(status), Ok(queue), Ok(outputs)) => {
                 Update::from_data(
                     vec![
                         UpdateKind::Db,
                         UpdateKind::Playlists,
                         UpdateKind::Status(status.into()),
                         UpdateKind::Queue(queue.into_iter().map(Into::into).collect()),
+                        UpdateKind::Outputs(outputs.into_iter().map(Into::into).collect()),
+                        UpdateKind::Stickers,
                     ]
                 )
             },
-            (Err(err), _) | (_, Err(err)) => {
+            (Err(err), _, _) | (_, Err(err), _) | (_, _, Err(err)) => {
                 Update::from_err(err)
             },
         }
@@ -167,19 +338,32 @@ impl From<mpd::Result<Vec<mpd::Update>>> for Update {
 
 async fn handle_upgrade(
     socket: WebSocket,
-    handle: mpd::Handle,
-    mut sub_handle: mpd::SubscriptionHandle,
+    partitions: mpd::PartitionRegistry,
+    history_handle: history::Handle,
+    index_handle: index::Handle,
+    covers_handle: covers::Handle,
+    recents: config::Recents,
 ) -> Result<()> {
     let mut socket = Socket::new(socket);
 
-    let handle = Handle::new(handle);
+    let mut handle = Handle::new(partitions, history_handle, index_handle, covers_handle, recents);
 
     socket.send(Out::update(handle.initial_update().await)).await?;
 
     loop {
         tokio::select! {
-            updates = sub_handle.updates() => {
-                socket.send(Out::update(updates.into())).await?;
+            updates = handle.sub_handle.updates() => {
+                // The selected partition is gone, fall back to the default one.
+                if handle.sub_handle.is_closed() {
+                    let err = mpd::Error::NotFound(format!("partition '{}' is gone", handle.partition));
+
+                    handle.select_default();
+
+                    socket.send(Out::update(Update::from_err(err))).await?;
+                    socket.send(Out::update(handle.initial_update().await)).await?;
+                } else {
+                    socket.send(Out::update(updates.into())).await?;
+                }
             },
             msg = socket.recv() => {
                 let Ok(Some(msg)) = msg else {
@@ -188,9 +372,16 @@ async fn handle_upgrade(
 
                 match msg.parse() {
                     Ok(Request { id, content: action }) => {
-                        let status = handle.process(action).await;
+                        let partition = handle.partition.clone();
+
+                        let content = handle.process(action).await;
+
+                        socket.send(Out::response(id, content)).await?;
 
-                        socket.send(Out::response(id, status)).await?;
+                        // Everything the client knows belongs to the previous partition.
+                        if handle.partition != partition {
+                            socket.send(Out::update(handle.initial_update().await)).await?;
+                        }
                     },
                     Err(err) => {
                         socket.send(
@@ -199,7 +390,7 @@ async fn handle_upgrade(
                                 Status::new(
                                     Status::PARSE_ERR_CODE,
                                     Some(format!("failed to parse request: {err}")),
-                                ),
+                                ).into(),
                             )
                         ).await?;
                     },
@@ -209,14 +400,17 @@ async fn handle_upgrade(
     }
 }
 
-#[tracing::instrument(skip(ws, handle, sub_handle), level = "debug")]
+#[tracing::instrument(skip(ws, partitions, history_handle, index_handle, covers_handle, recents), level = "debug")]
 pub async fn websocket(
     ws: WebSocketUpgrade,
-    Extension(handle): Extension<mpd::Handle>,
-    Extension(sub_handle): Extension<mpd::SubscriptionHandle>,
+    Extension(partitions): Extension<mpd::PartitionRegistry>,
+    Extension(history_handle): Extension<history::Handle>,
+    Extension(index_handle): Extension<index::Handle>,
+    Extension(covers_handle): Extension<covers::Handle>,
+    Extension(recents): Extension<config::Recents>,
 ) -> impl IntoResponse {
     ws.on_upgrade(|socket| async {
-        match handle_upgrade(socket, handle, sub_handle).await {
+        match handle_upgrade(socket, partitions, history_handle, index_handle, covers_handle, recents).await {
             Ok(_) => tracing::debug!("connection closed"),
             Err(err) => tracing::debug!("connection closed with error: {err}"),
         };
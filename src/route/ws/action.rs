@@ -1,16 +1,43 @@
 use serde::Deserialize;
 
-use crate::mpd;
-use crate::route::ws::data::OneshotState;
+use crate::route::db::DbCountQueryParams;
+use crate::route::db::DbQueryParams;
+use crate::route::db::DbRecentsQueryParams;
+use crate::route::db::DbSearchBody;
+use crate::route::history::HistoryQueryParams;
+use crate::route::history::HistoryStatsQueryParams;
+use crate::route::library::LibraryAlbumsQueryParams;
+use crate::route::playlists::PlaylistPathParams;
+use crate::route::queue::OneshotState;
+use crate::route::queue::QueueRange;
+use crate::route::queue::QueueSource;
+use crate::route::stickers::StickerFindQueryParams;
+use crate::route::stickers::StickerQueryParams;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub enum Action {
+    DbGet(DbQueryParams),
+    DbCount(DbCountQueryParams),
+    DbRecents(DbRecentsQueryParams),
+    DbSearch(DbSearchBody),
     DbUpdate { uri: Option<String> },
+    LibraryArtists,
+    LibraryAlbums(LibraryAlbumsQueryParams),
+    LibraryGenres,
+    LibraryYears,
     QueueAdd { sources: Vec<QueueSource> },
     QueueReplace { sources: Vec<QueueSource> },
     QueueClear,
     QueueRemove { id: i64 },
+    QueueRemoveRange { range: QueueRange },
+    QueueMove { range: QueueRange, to: usize },
+    QueueMoveId { id: i64, to: usize },
+    QueuePlayNext { id: i64 },
+    QueueSwap { id: i64, with: i64 },
+    QueuePrio { range: QueueRange, priority: u8 },
+    QueuePrioId { ids: Vec<i64>, priority: u8 },
+    QueueShuffle { range: Option<QueueRange> },
     QueueNext,
     QueuePrev,
     QueueRepeat { state: bool },
@@ -22,20 +49,23 @@ pub enum Action {
     PlaybackStop,
     PlaybackSeek { time: f64 },
     VolumeSet { value: u8 },
-}
-
-#[derive(Deserialize)]
-#[serde(rename_all = "camelCase")]
-pub enum QueueSource {
-    File { uri: String },
-    Playlist { name: String },
-}
-
-impl From<QueueSource> for mpd::QueueSource {
-    fn from(source: QueueSource) -> Self {
-        match source {
-            QueueSource::File { uri } => mpd::QueueSource::File { uri },
-            QueueSource::Playlist { name } => mpd::QueueSource::Playlist { name },
-        }
-    }
+    PlaylistsList,
+    PlaylistsGet(PlaylistPathParams),
+    HistoryGet(HistoryQueryParams),
+    HistoryStats(HistoryStatsQueryParams),
+    OutputsList,
+    OutputsEnable { id: i64 },
+    OutputsDisable { id: i64 },
+    OutputsToggle { id: i64 },
+    OutputsSet { id: i64, name: String, value: String },
+    PartitionSelect { name: String },
+    PartitionsList,
+    PartitionsCreate { name: String },
+    PartitionsDelete { name: String },
+    PartitionsMoveOutput { name: String },
+    StickersList(StickerQueryParams),
+    StickersGet { uri: String, name: String },
+    StickersSet { uri: String, name: String, value: String },
+    StickersDelete { uri: String, name: Option<String> },
+    StickersFind(StickerFindQueryParams),
 }
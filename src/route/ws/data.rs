@@ -1,4 +1,3 @@
-use serde::Deserialize;
 use serde::ser::SerializeStruct;
 use serde::Serialize;
 use serde::Serializer;
@@ -6,6 +5,7 @@ use serde::Serializer;
 use crate::mpd;
 use crate::route::db::DbAudioFormat;
 use crate::route::db::DbTags;
+use crate::route::queue::OneshotState;
 use crate::time::Duration;
 
 #[derive(Debug, Serialize)]
@@ -28,38 +28,6 @@ impl From<mpd::PlaybackState> for PlaybackState {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
-pub enum OneshotState {
-    On,
-    Off,
-    Oneshot,
-}
-
-impl From<mpd::OneshotState> for OneshotState {
-    fn from(state: mpd::OneshotState) -> Self {
-        use mpd::OneshotState::*;
-
-        match state {
-            On => OneshotState::On,
-            Off => OneshotState::Off,
-            Oneshot => OneshotState::Oneshot,
-        }
-    }
-}
-
-impl From<OneshotState> for mpd::OneshotState {
-    fn from(state: OneshotState) -> Self {
-        use OneshotState::*;
-
-        match state {
-            On => mpd::OneshotState::On,
-            Off => mpd::OneshotState::Off,
-            Oneshot => mpd::OneshotState::Oneshot,
-        }
-    }
-}
-
 #[derive(Debug, Serialize)]
 pub struct SongStatus {
     id: i64,
@@ -102,6 +70,7 @@ pub enum Status {
         consume: OneshotState,
         song: Option<SongStatus>,
         queue: QueueStatus,
+        partition: Option<String>,
     },
 }
 
@@ -122,8 +91,9 @@ impl Serialize for Status {
                 consume,
                 song,
                 queue,
+                partition,
             } => {
-                let mut s = serializer.serialize_struct("Connected", 7)?;
+                let mut s = serializer.serialize_struct("Connected", 9)?;
 
                 s.serialize_field("volume", volume)?;
                 s.serialize_field("repeat", repeat)?;
@@ -133,6 +103,7 @@ impl Serialize for Status {
                 s.serialize_field("consume", consume)?;
                 s.serialize_field("song", song)?;
                 s.serialize_field("queue", queue)?;
+                s.serialize_field("partition", partition)?;
 
                 s.end()
             }
@@ -149,7 +120,8 @@ impl From<mpd::Status> for Status {
         single,
         consume,
         song,
-        queue
+        queue,
+        partition,
     }: mpd::Status) -> Self {
         Status::Connected {
             volume,
@@ -160,6 +132,7 @@ impl From<mpd::Status> for Status {
             consume: consume.into(),
             song: song.map(Into::into),
             queue: queue.into(),
+            partition,
         }
     }
 }
@@ -172,6 +145,7 @@ pub struct QueueItem {
     duration: Duration,
     tags: DbTags,
     format: Option<DbAudioFormat>,
+    priority: u8,
 }
 
 impl From<mpd::QueueItem> for QueueItem {
@@ -182,6 +156,7 @@ impl From<mpd::QueueItem> for QueueItem {
         duration,
         tags,
         format,
+        priority,
     }: mpd::QueueItem) -> Self {
         QueueItem {
             id,
@@ -190,6 +165,7 @@ impl From<mpd::QueueItem> for QueueItem {
             tags: tags.into(),
             duration: duration.into(),
             format: format.map(Into::into),
+            priority,
         }
     }
 }
@@ -1,11 +1,26 @@
 use std::str::FromStr;
 
 use axum::extract::ws::Message;
+use axum::http::StatusCode;
 use serde::Deserialize;
 use serde::Serialize;
 use serde_json as json;
 
 use crate::mpd;
+use crate::route::db::DbCount;
+use crate::route::db::DbItem;
+use crate::route::db::DbGroup;
+use crate::route::db::DbPage;
+use crate::route::db::DbRecents;
+use crate::route::error::Error;
+use crate::route::history::HistoryEntry;
+use crate::route::history::HistoryStats;
+use crate::route::library::Album;
+use crate::route::library::TagCount;
+use crate::route::outputs::Output;
+use crate::route::playlists::Playlist;
+use crate::route::stickers::Sticker;
+use crate::route::stickers::StickerMatch;
 use crate::route::ws::action::Action;
 use crate::route::ws::data;
 
@@ -44,6 +59,7 @@ impl Status {
     pub const FORBIDDEN_ERR_CODE: i16 = 1;
     pub const NOT_FOUND_ERR_CODE: i16 = 2;
     pub const CONFLICT_ERR_CODE: i16 = 3;
+    pub const INVALID_ERR_CODE: i16 = 4;
 }
 
 #[derive(Serialize)]
@@ -59,12 +75,44 @@ impl Response {
     }
 }
 
+// The payload sits next to the status, so that status-only responses carry
+// neither the type nor the data, e.g. {"type": "dbItems", "data": [...], "code": 0}.
 #[derive(Serialize)]
 pub struct ResponseContent {
+    #[serde(flatten, skip_serializing_if = "Option::is_none")]
+    pub data: Option<ResponseData>,
     #[serde(flatten)]
     pub status: Status,
 }
 
+impl ResponseContent {
+    pub fn from_data(data: ResponseData) -> Self {
+        ResponseContent {
+            data: Some(data),
+            status: Status::success(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", content = "data", rename_all = "camelCase")]
+pub enum ResponseData {
+    DbItems(Vec<DbItem>),
+    DbPage(DbPage),
+    DbGroupPage(DbPage<DbGroup>),
+    DbCount(DbCount),
+    TagCounts(Vec<TagCount>),
+    Albums(Vec<Album>),
+    Playlists(Vec<Playlist>),
+    History(Vec<HistoryEntry>),
+    HistoryStats(Box<HistoryStats>),
+    Outputs(Vec<Output>),
+    Partitions(Vec<String>),
+    Stickers(Vec<Sticker>),
+    Sticker(Sticker),
+    StickerMatches(Vec<StickerMatch>),
+}
+
 #[derive(Serialize)]
 pub struct Content<T> {
     pub content: T,
@@ -111,10 +159,10 @@ impl Out {
         Out::Update(Content::new(update))
     }
 
-    pub fn response(id: String, status: Status) -> Self {
+    pub fn response(id: String, content: ResponseContent) -> Self {
         let response = Response {
             id,
-            content: Content::new(ResponseContent { status })
+            content: Content::new(content),
         };
 
         Out::Response(response)
@@ -128,6 +176,8 @@ pub enum UpdateKind {
     Playlists,
     Status(data::Status),
     Queue(Vec<data::QueueItem>),
+    Outputs(Vec<Output>),
+    Stickers,
 }
 
 impl From<mpd::Update> for UpdateKind {
@@ -145,6 +195,12 @@ impl From<mpd::Update> for UpdateKind {
             mpd::Update::Queue(queue) => {
                 UpdateKind::Queue(queue.into_iter().map(Into::into).collect())
             },
+            mpd::Update::Outputs(outputs) => {
+                UpdateKind::Outputs(outputs.into_iter().map(Into::into).collect())
+            },
+            mpd::Update::Stickers => {
+                UpdateKind::Stickers
+            },
         }
     }
 }
@@ -156,6 +212,7 @@ impl From<mpd::Error> for Status {
             mpd::Error::Forbidden(_) => Status::FORBIDDEN_ERR_CODE,
             mpd::Error::NotFound(_) => Status::NOT_FOUND_ERR_CODE,
             mpd::Error::AlreadyExists(_) => Status::CONFLICT_ERR_CODE,
+            mpd::Error::Invalid(_) => Status::INVALID_ERR_CODE,
             mpd::Error::Disconnected(_) | mpd::Error::Unavailable(_) => Status::DISCONNECTED_ERR_CODE,
         };
 
@@ -166,6 +223,132 @@ impl From<mpd::Error> for Status {
     }
 }
 
+impl From<Error> for Status {
+    fn from(err: Error) -> Self {
+        let code = match err.code() {
+            StatusCode::FORBIDDEN => Status::FORBIDDEN_ERR_CODE,
+            StatusCode::NOT_FOUND => Status::NOT_FOUND_ERR_CODE,
+            StatusCode::CONFLICT => Status::CONFLICT_ERR_CODE,
+            StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => Status::INVALID_ERR_CODE,
+            StatusCode::SERVICE_UNAVAILABLE => Status::DISCONNECTED_ERR_CODE,
+            _ => Status::INTERNAL_ERR_CODE,
+        };
+
+        Status {
+            code,
+            message: Some(err.message().to_owned()),
+        }
+    }
+}
+
+impl From<Status> for ResponseContent {
+    fn from(status: Status) -> Self {
+        ResponseContent {
+            data: None,
+            status,
+        }
+    }
+}
+
+impl From<mpd::Result<()>> for ResponseContent {
+    fn from(result: mpd::Result<()>) -> Self {
+        result.map_or_else(|err| Status::from(err).into(), |_| Status::success().into())
+    }
+}
+
+impl<T: Into<ResponseData>> From<Result<T, Error>> for ResponseContent {
+    fn from(result: Result<T, Error>) -> Self {
+        result.map_or_else(|err| Status::from(err).into(), |data| ResponseContent::from_data(data.into()))
+    }
+}
+
+impl From<Vec<DbItem>> for ResponseData {
+    fn from(items: Vec<DbItem>) -> Self {
+        ResponseData::DbItems(items)
+    }
+}
+
+impl From<DbPage> for ResponseData {
+    fn from(page: DbPage) -> Self {
+        ResponseData::DbPage(page)
+    }
+}
+
+impl From<DbRecents> for ResponseData {
+    fn from(recents: DbRecents) -> Self {
+        match recents {
+            DbRecents::Files(page) => ResponseData::DbPage(page),
+            DbRecents::Groups(page) => ResponseData::DbGroupPage(page),
+        }
+    }
+}
+
+impl From<DbCount> for ResponseData {
+    fn from(count: DbCount) -> Self {
+        ResponseData::DbCount(count)
+    }
+}
+
+impl From<Vec<TagCount>> for ResponseData {
+    fn from(counts: Vec<TagCount>) -> Self {
+        ResponseData::TagCounts(counts)
+    }
+}
+
+impl From<Vec<Album>> for ResponseData {
+    fn from(albums: Vec<Album>) -> Self {
+        ResponseData::Albums(albums)
+    }
+}
+
+impl From<Vec<Playlist>> for ResponseData {
+    fn from(playlists: Vec<Playlist>) -> Self {
+        ResponseData::Playlists(playlists)
+    }
+}
+
+impl From<Vec<HistoryEntry>> for ResponseData {
+    fn from(entries: Vec<HistoryEntry>) -> Self {
+        ResponseData::History(entries)
+    }
+}
+
+impl From<HistoryStats> for ResponseData {
+    fn from(stats: HistoryStats) -> Self {
+        ResponseData::HistoryStats(Box::new(stats))
+    }
+}
+
+impl From<Vec<Output>> for ResponseData {
+    fn from(outputs: Vec<Output>) -> Self {
+        ResponseData::Outputs(outputs)
+    }
+}
+
+impl From<Vec<String>> for ResponseData {
+    fn from(partitions: Vec<String>) -> Self {
+        ResponseData::Partitions(partitions)
+    }
+}
+
+impl From<Vec<Sticker>> for ResponseData {
+    fn from(stickers: Vec<Sticker>) -> Self {
+        ResponseData::Stickers(stickers)
+    }
+}
+
+impl From<Sticker> for ResponseData {
+    fn from(sticker: Sticker) -> Self {
+        ResponseData::Sticker(sticker)
+    }
+}
+
+impl From<Vec<StickerMatch>> for ResponseData {
+    fn from(matches: Vec<StickerMatch>) -> Self {
+        ResponseData::StickerMatches(matches)
+    }
+}
+
 impl From<mpd::Error> for Update {
     fn from(err: mpd::Error) -> Self {
         Update {
@@ -191,3 +374,34 @@ impl From<Out> for Message {
         )
     }
 }
+
+///////////////////////////////////////////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_omit_data_from_status_only_response() {
+        let out = Out::response("1".to_owned(), Status::success().into());
+
+        assert_eq!(
+            json::to_value(&out).unwrap(),
+            json::json!({"type": "response", "id": "1", "content": {"code": 0, "message": null}}),
+        );
+    }
+
+    #[test]
+    fn should_put_data_next_to_status() {
+        let out = Out::response("1".to_owned(), ResponseContent::from_data(vec!["default".to_owned()].into()));
+
+        assert_eq!(
+            json::to_value(&out).unwrap(),
+            json::json!({
+                "type": "response",
+                "id": "1",
+                "content": {"type": "partitions", "data": ["default"], "code": 0, "message": null},
+            }),
+        );
+    }
+}
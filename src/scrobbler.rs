@@ -0,0 +1,459 @@
+use std::cmp;
+
+use time::Duration;
+use time::OffsetDateTime;
+use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config;
+use crate::convert::IntoOption;
+use crate::history::session;
+use crate::persist;
+use crate::persist::PlaybackHistoryEvent;
+use crate::persist::PlaybackHistoryEventKind;
+use crate::persist::PlaybackHistoryMetadata;
+use crate::persist::QueuedScrobble;
+use crate::persist::Scrobble;
+use crate::scrobbler::error::Error;
+use crate::scrobbler::lastfm::LastFm;
+use crate::scrobbler::listenbrainz::ListenBrainz;
+use crate::scrobbler::result::Result;
+
+mod error;
+mod result;
+mod http;
+mod lastfm;
+mod listenbrainz;
+
+// Scrobbles that couldn't be submitted are kept around until then.
+const RETRY_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5 * 60);
+
+const MIN_DURATION: Duration = Duration::seconds(30);
+const MAX_REQUIRED_LISTENED: Duration = Duration::minutes(4);
+
+enum Service {
+    ListenBrainz(ListenBrainz),
+    LastFm(LastFm),
+}
+
+struct Target {
+    name: String,
+    service: Service,
+}
+
+impl TryFrom<config::Scrobbler> for Target {
+    type Error = String;
+
+    fn try_from(scrobbler: config::Scrobbler) -> std::result::Result<Self, Self::Error> {
+        let target = match scrobbler {
+            config::Scrobbler::ListenBrainz { name, url, token } => Target {
+                name,
+                service: Service::ListenBrainz(ListenBrainz::new(&url, token)?),
+            },
+            config::Scrobbler::LastFm { name, url, api_key, api_secret, session_key } => Target {
+                name,
+                service: Service::LastFm(LastFm::new(&url, api_key, api_secret, session_key)?),
+            },
+        };
+
+        Ok(target)
+    }
+}
+
+impl Target {
+    fn max_batch_size(&self) -> usize {
+        match self.service {
+            Service::ListenBrainz(_) => listenbrainz::MAX_BATCH_SIZE,
+            Service::LastFm(_) => lastfm::MAX_BATCH_SIZE,
+        }
+    }
+
+    async fn now_playing(&self, client: &http::Client, scrobble: &Scrobble) -> Result<()> {
+        match &self.service {
+            Service::ListenBrainz(service) => service.now_playing(client, scrobble).await,
+            Service::LastFm(service) => service.now_playing(client, scrobble).await,
+        }
+    }
+
+    async fn scrobble(&self, client: &http::Client, scrobbles: &[&Scrobble]) -> Result<()> {
+        match &self.service {
+            Service::ListenBrainz(service) => service.scrobble(client, scrobbles).await,
+            Service::LastFm(service) => service.scrobble(client, scrobbles).await,
+        }
+    }
+}
+
+// Both services go by the same rule: the song has to be longer than
+// 30 seconds and played for at least half of it, or for 4 minutes.
+fn is_scrobbleable(duration: Duration, listened: Duration) -> bool {
+    duration > MIN_DURATION && listened >= cmp::min(duration / 2, MAX_REQUIRED_LISTENED)
+}
+
+fn is_end(event: &PlaybackHistoryEvent) -> bool {
+    matches!(event.kind, PlaybackHistoryEventKind::Stop | PlaybackHistoryEventKind::Interrupt)
+}
+
+// Neither service accepts songs without an artist or a title.
+fn into_scrobble(metadata: PlaybackHistoryMetadata, listened_at: OffsetDateTime) -> Option<Scrobble> {
+    let tags = metadata.tags;
+
+    Scrobble {
+        artist: tags.artists.into_iter().next()?,
+        title: tags.titles.into_iter().next()?,
+        album: tags.albums.into_iter().next(),
+        album_artist: tags.album_artists.into_iter().next(),
+        duration: metadata.duration,
+        listened_at,
+    }.into_some()
+}
+
+struct Scrobbler<'a> {
+    client: http::Client,
+    persistence_handle: &'a persist::Handle,
+    server: &'a str,
+    targets: &'a [Target],
+}
+
+impl<'a> Scrobbler<'a> {
+    // Now playing is not worth retrying, the song is likely over by then.
+    async fn now_playing(&self, event: &PlaybackHistoryEvent) -> Result<()> {
+        let metadata = self.persistence_handle.playback_history_metadata(self.server)
+            .get_by_play_id(event.play_id)
+            .await?;
+
+        let Some(scrobble) = into_scrobble(metadata, event.recorded_at) else {
+            return Ok(());
+        };
+
+        for target in self.targets {
+            if let Err(err) = target.now_playing(&self.client, &scrobble).await {
+                tracing::warn!(server = self.server, target = target.name, "failed to submit now playing: {err}");
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn scrobble(&self, event: &PlaybackHistoryEvent) -> Result<()> {
+        let events = self.persistence_handle.playback_history_event(self.server)
+            .get_all_by_play_id(&[event.play_id])
+            .await?;
+
+        let Some(start) = events.first() else {
+            return Ok(());
+        };
+
+        let Some(end) = events.iter().position(|it| it.recorded_at == event.recorded_at && it.kind == event.kind) else {
+            return Ok(());
+        };
+
+        let metadata = self.persistence_handle.playback_history_metadata(self.server)
+            .get_by_play_id(event.play_id)
+            .await?;
+
+        let now = OffsetDateTime::now_utc();
+
+        let is_scrobbleable_until = |end: usize| {
+            is_scrobbleable(metadata.duration, session::reduce(&events[..=end], metadata.duration, now).listened)
+        };
+
+        // Interrupted plays may go on after reconnecting, they're only scrobbled the first time they qualify.
+        let previous_end = events[..end].iter().rposition(is_end);
+
+        if !is_scrobbleable_until(end) || previous_end.is_some_and(is_scrobbleable_until) {
+            tracing::debug!(server = self.server, play_id = event.play_id, "not scrobbleable");
+
+            return Ok(());
+        }
+
+        let Some(scrobble) = into_scrobble(metadata, start.recorded_at) else {
+            tracing::debug!(server = self.server, play_id = event.play_id, "missing artist or title");
+
+            return Ok(());
+        };
+
+        let targets = self.targets.iter()
+            .map(|target| target.name.as_str())
+            .collect::<Vec<_>>();
+
+        self.persistence_handle.scrobble(self.server)
+            .enqueue(&targets, event.play_id, scrobble)
+            .await?;
+
+        self.flush().await
+    }
+
+    // Returns how many of the scrobbles, oldest first, are done with, either because they were
+    // accepted or because they were rejected. One rejected scrobble shouldn't take the rest of
+    // its batch down with it, so a rejected batch is submitted again one scrobble at a time.
+    async fn submit(&self, target: &Target, queued: &[QueuedScrobble]) -> usize {
+        let scrobbles = queued.iter()
+            .map(|it| &it.scrobble)
+            .collect::<Vec<_>>();
+
+        match target.scrobble(&self.client, &scrobbles).await {
+            Ok(()) => {
+                tracing::debug!(server = self.server, target = target.name, count = queued.len(), "scrobbled");
+            },
+            Err(Error::Rejected(err)) if queued.len() > 1 => {
+                tracing::warn!(server = self.server, target = target.name, count = queued.len(), "batch rejected, retrying one by one: {err}");
+
+                for (i, it) in queued.iter().enumerate() {
+                    match target.scrobble(&self.client, &[&it.scrobble]).await {
+                        Ok(()) => {
+                            // Nothing else to do.
+                        },
+                        Err(err @ Error::Rejected(_)) => {
+                            tracing::warn!(server = self.server, target = target.name, id = it.id, "dropping scrobble: {err}");
+                        },
+                        Err(err) => {
+                            tracing::warn!(server = self.server, target = target.name, "failed to scrobble, will retry: {err}");
+
+                            return i;
+                        },
+                    }
+                }
+            },
+            Err(err @ Error::Rejected(_)) => {
+                tracing::warn!(server = self.server, target = target.name, count = queued.len(), "dropping scrobbles: {err}");
+            },
+            Err(err) => {
+                tracing::warn!(server = self.server, target = target.name, "failed to scrobble, will retry: {err}");
+
+                return 0;
+            },
+        }
+
+        queued.len()
+    }
+
+    // Submits queued scrobbles oldest first, stopping at the first
+    // failure that is worth retrying so that the order is kept.
+    async fn flush(&self) -> Result<()> {
+        for target in self.targets {
+            loop {
+                let queued = self.persistence_handle.scrobble(self.server)
+                    .get_queued(&target.name, target.max_batch_size())
+                    .await?;
+
+                if queued.is_empty() {
+                    break;
+                }
+
+                let done = self.submit(target, &queued).await;
+
+                let ids = queued[..done].iter()
+                    .map(|it| it.id)
+                    .collect::<Vec<_>>();
+
+                self.persistence_handle.scrobble(self.server)
+                    .set_submitted(&ids)
+                    .await?;
+
+                if done < queued.len() {
+                    break;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    // Plays that ended while events were being missed are looked up in the history instead.
+    // Returns when the last of the events that have been caught up on was recorded.
+    async fn catch_up(&self, since: OffsetDateTime) -> Result<OffsetDateTime> {
+        let mut events = self.persistence_handle.playback_history_event(self.server)
+            .get_all(Some(since), None)
+            .await?;
+
+        events.reverse();
+
+        let mut last_seen = since;
+
+        for event in events.iter().filter(|it| it.recorded_at > since) {
+            if is_end(event) {
+                if let Err(err) = self.scrobble(event).await {
+                    tracing::error!(server = self.server, play_id = event.play_id, "failed to scrobble missed play: {err}");
+                }
+            }
+
+            last_seen = event.recorded_at;
+        }
+
+        Ok(last_seen)
+    }
+
+    async fn process(&self, event: &PlaybackHistoryEvent) -> Result<()> {
+        match event.kind {
+            PlaybackHistoryEventKind::Start => {
+                self.now_playing(event).await
+            },
+            PlaybackHistoryEventKind::Stop | PlaybackHistoryEventKind::Interrupt => {
+                self.scrobble(event).await
+            },
+            PlaybackHistoryEventKind::Pause | PlaybackHistoryEventKind::Resume | PlaybackHistoryEventKind::Seek => {
+                Ok(())
+            },
+        }
+    }
+}
+
+// Scrobbles are queued persistently, so failing to deal with one
+// event doesn't keep the ones that come after it from being scrobbled.
+async fn inner(
+    events: &mut broadcast::Receiver<PlaybackHistoryEvent>,
+    persistence_handle: &persist::Handle,
+    server: &str,
+    targets: &[Target],
+    started_at: OffsetDateTime,
+) {
+    let scrobbler = Scrobbler { client: http::client(), persistence_handle, server, targets };
+
+    // Plays that ended while not running, interrupted ones included, are caught up on from the last one that was
+    // queued. Without any, there's nothing to go by and the history from before is left alone.
+    let since = match persistence_handle.scrobble(server).get_last_listened_at().await {
+        Ok(it) => it.unwrap_or(started_at),
+        Err(err) => {
+            tracing::error!(server, "failed to get last scrobble: {err}");
+
+            started_at
+        },
+    };
+
+    let mut last_seen = match scrobbler.catch_up(since).await {
+        Ok(it) => it,
+        Err(err) => {
+            tracing::error!(server, "failed to catch up on playback history: {err}");
+
+            since
+        },
+    };
+
+    // The first tick is immediate, which submits whatever was left over from before.
+    let mut retry = tokio::time::interval(RETRY_INTERVAL);
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                match event {
+                    // Events that have been caught up on after lagging behind come through again.
+                    Ok(event) if event.recorded_at <= last_seen => {
+                        // Nothing to do.
+                    },
+                    Ok(event) => {
+                        last_seen = event.recorded_at;
+
+                        if let Err(err) = scrobbler.process(&event).await {
+                            tracing::error!(server, play_id = event.play_id, "failed to process playback history event: {err}");
+                        }
+                    },
+                    Err(RecvError::Lagged(count)) => {
+                        tracing::warn!(server, count, "missed playback history events, catching up");
+
+                        match scrobbler.catch_up(last_seen).await {
+                            Ok(it) => {
+                                last_seen = it;
+                            },
+                            Err(err) => {
+                                tracing::error!(server, "failed to catch up on playback history: {err}");
+                            },
+                        }
+                    },
+                    Err(RecvError::Closed) => {
+                        return;
+                    },
+                }
+            },
+            _ = retry.tick() => {
+                if let Err(err) = scrobbler.flush().await {
+                    tracing::error!(server, "failed to flush scrobbles: {err}");
+                }
+            }
+        }
+    }
+}
+
+pub fn run(
+    mut events: broadcast::Receiver<PlaybackHistoryEvent>,
+    persistence_handle: persist::Handle,
+    server: String,
+    scrobblers: Vec<config::Scrobbler>,
+) {
+    let targets = scrobblers.into_iter()
+        .filter_map(|scrobbler| {
+            let name = scrobbler.name().to_owned();
+
+            Target::try_from(scrobbler)
+                .map_err(|err| tracing::error!(server, target = name, "skipping scrobbler: {err}"))
+                .ok()
+        })
+        .collect::<Vec<_>>();
+
+    if targets.is_empty() {
+        return;
+    }
+
+    // Anything recorded from here on comes through the receiver.
+    let started_at = OffsetDateTime::now_utc();
+
+    tokio::spawn(async move {
+        inner(&mut events, &persistence_handle, &server, &targets, started_at).await;
+
+        tracing::debug!("inner exited");
+    });
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_scrobble_after_half_of_song() {
+        assert!(is_scrobbleable(Duration::seconds(200), Duration::seconds(100)));
+        assert!(!is_scrobbleable(Duration::seconds(200), Duration::seconds(99)));
+    }
+
+    #[test]
+    fn should_scrobble_long_songs_after_four_minutes() {
+        assert!(is_scrobbleable(Duration::minutes(20), Duration::minutes(4)));
+        assert!(!is_scrobbleable(Duration::minutes(20), Duration::seconds(239)));
+    }
+
+    #[test]
+    fn should_not_scrobble_short_songs() {
+        assert!(!is_scrobbleable(Duration::seconds(30), Duration::seconds(30)));
+    }
+
+    #[tokio::test]
+    async fn should_remember_last_submitted_scrobble() {
+        let persistence_handle = persist::init_in_memory().await.unwrap();
+        let scrobbles = persistence_handle.scrobble("test");
+
+        let scrobble = |seconds| Scrobble {
+            artist: "Artist".to_owned(),
+            title: "Title".to_owned(),
+            album: None,
+            album_artist: None,
+            duration: Duration::minutes(3),
+            listened_at: OffsetDateTime::UNIX_EPOCH + Duration::seconds(seconds),
+        };
+
+        assert_eq!(scrobbles.get_last_listened_at().await.unwrap(), None);
+
+        scrobbles.enqueue(&["a", "b"], 1, scrobble(0)).await.unwrap();
+        scrobbles.enqueue(&["a", "b"], 2, scrobble(200)).await.unwrap();
+
+        let queued = scrobbles.get_queued("a", 10).await.unwrap();
+
+        scrobbles.set_submitted(&queued.iter().map(|it| it.id).collect::<Vec<_>>()).await.unwrap();
+
+        // Submitted plays aren't queued again when caught up on once more.
+        scrobbles.enqueue(&["a", "b"], 2, scrobble(200)).await.unwrap();
+
+        assert!(scrobbles.get_queued("a", 10).await.unwrap().is_empty());
+        assert_eq!(scrobbles.get_queued("b", 10).await.unwrap().len(), 2);
+        assert_eq!(scrobbles.get_last_listened_at().await.unwrap(), Some(OffsetDateTime::UNIX_EPOCH + Duration::seconds(200)));
+    }
+}
@@ -0,0 +1,41 @@
+use std::error;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+use crate::persist;
+
+#[derive(Debug)]
+pub enum Error {
+    Persistence,
+    // Worth trying again later, e.g. the service is unreachable or rate limiting.
+    Unavailable(String),
+    // Trying again won't change anything, e.g. the service refused the submission.
+    Rejected(String),
+}
+
+impl error::Error for Error {
+    // default
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Persistence => write!(f, "persistence error"),
+            Error::Unavailable(message) => write!(f, "service unavailable: {message}"),
+            Error::Rejected(message) => write!(f, "submission rejected: {message}"),
+        }
+    }
+}
+
+impl From<persist::Error> for Error {
+    fn from(_: persist::Error) -> Self {
+        Error::Persistence
+    }
+}
+
+impl From<hyper::Error> for Error {
+    fn from(err: hyper::Error) -> Self {
+        Error::Unavailable(err.to_string())
+    }
+}
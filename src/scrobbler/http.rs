@@ -0,0 +1,45 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use hyper::Body;
+use hyper::client::HttpConnector;
+use hyper::Request;
+use hyper::StatusCode;
+use hyper_tls::HttpsConnector;
+
+use crate::scrobbler::error::Error;
+use crate::scrobbler::result::Result;
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+pub type Client = hyper::Client<HttpsConnector<HttpConnector>>;
+
+// Plain http is allowed as well, so that a local stand-in can be used.
+pub fn client() -> Client {
+    hyper::Client::builder().build(HttpsConnector::new())
+}
+
+pub async fn send(client: &Client, request: Request<Body>) -> Result<(StatusCode, Bytes)> {
+    let response = async {
+        let response = client.request(request).await?;
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await?;
+
+        Ok((status, body))
+    };
+
+    tokio::time::timeout(TIMEOUT, response)
+        .await
+        .map_err(|_| Error::Unavailable("request timed out".to_owned()))?
+}
+
+// Credentials are worth retrying with as well, they may get fixed in the meantime.
+pub fn into_error(status: StatusCode, body: &[u8]) -> Error {
+    let message = format!("{status}: {}", String::from_utf8_lossy(body));
+
+    if status.is_server_error() || matches!(status, StatusCode::TOO_MANY_REQUESTS | StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) {
+        Error::Unavailable(message)
+    } else {
+        Error::Rejected(message)
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::BTreeMap;
+
+use hyper::Body;
+use hyper::header;
+use hyper::Request;
+use hyper::Uri;
+use md5::Digest;
+use md5::Md5;
+use serde::Deserialize;
+use serde_json as json;
+
+use crate::persist::Scrobble;
+use crate::scrobbler::error::Error;
+use crate::scrobbler::http;
+use crate::scrobbler::result::Result;
+
+pub const MAX_BATCH_SIZE: usize = 50;
+
+// Service offline, temporarily unavailable and rate limit exceeded,
+// as well as an invalid session key, which may get fixed in the meantime.
+const RETRYABLE_ERRORS: &[i64] = &[9, 11, 16, 29];
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: i64,
+    message: String,
+}
+
+fn track_params(scrobble: &Scrobble, suffix: &str) -> Vec<(String, String)> {
+    let mut params = vec![
+        (format!("artist{suffix}"), scrobble.artist.clone()),
+        (format!("track{suffix}"), scrobble.title.clone()),
+    ];
+
+    if let Some(album) = &scrobble.album {
+        params.push((format!("album{suffix}"), album.clone()));
+    }
+
+    if let Some(album_artist) = &scrobble.album_artist {
+        params.push((format!("albumArtist{suffix}"), album_artist.clone()));
+    }
+
+    if scrobble.duration.is_positive() {
+        params.push((format!("duration{suffix}"), scrobble.duration.whole_seconds().to_string()));
+    }
+
+    params
+}
+
+// Every parameter except for the format goes into the signature, ordered by name.
+fn signature(params: &BTreeMap<String, String>, secret: &str) -> String {
+    let mut hasher = Md5::new();
+
+    for (key, value) in params {
+        hasher.update(key);
+        hasher.update(value);
+    }
+
+    hasher.update(secret);
+
+    format!("{:x}", hasher.finalize())
+}
+
+pub struct LastFm {
+    uri: Uri,
+    api_key: String,
+    api_secret: String,
+    session_key: String,
+}
+
+impl LastFm {
+    pub fn new(url: &str, api_key: String, api_secret: String, session_key: String) -> std::result::Result<Self, String> {
+        let uri = url.parse()
+            .map_err(|err| format!("invalid url '{url}': {err}"))?;
+
+        Ok(LastFm { uri, api_key, api_secret, session_key })
+    }
+
+    fn body(&self, method: &str, params: Vec<(String, String)>) -> String {
+        let mut params = params.into_iter().collect::<BTreeMap<_, _>>();
+
+        params.insert("method".to_owned(), method.to_owned());
+        params.insert("api_key".to_owned(), self.api_key.clone());
+        params.insert("sk".to_owned(), self.session_key.clone());
+
+        let signature = signature(&params, &self.api_secret);
+
+        form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&params)
+            .append_pair("api_sig", &signature)
+            .append_pair("format", "json")
+            .finish()
+    }
+
+    async fn call(&self, client: &http::Client, method: &str, params: Vec<(String, String)>) -> Result<()> {
+        let request = Request::post(&self.uri)
+            .header(header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(Body::from(self.body(method, params)))
+            .map_err(|err| Error::Rejected(format!("failed to build request: {err}")))?;
+
+        let (status, body) = http::send(client, request).await?;
+
+        // Errors are reported in the body, regardless of the status.
+        if let Ok(ErrorResponse { error, message }) = json::from_slice(&body) {
+            let message = format!("error {error}: {message}");
+
+            return if RETRYABLE_ERRORS.contains(&error) {
+                Err(Error::Unavailable(message))
+            } else {
+                Err(Error::Rejected(message))
+            };
+        }
+
+        if !status.is_success() {
+            return Err(http::into_error(status, &body));
+        }
+
+        Ok(())
+    }
+
+    pub async fn now_playing(&self, client: &http::Client, scrobble: &Scrobble) -> Result<()> {
+        self.call(client, "track.updateNowPlaying", track_params(scrobble, "")).await
+    }
+
+    pub async fn scrobble(&self, client: &http::Client, scrobbles: &[&Scrobble]) -> Result<()> {
+        let params = scrobbles.iter()
+            .enumerate()
+            .flat_map(|(i, scrobble)| {
+                let suffix = format!("[{i}]");
+
+                let mut params = track_params(scrobble, &suffix);
+
+                params.push((format!("timestamp{suffix}"), scrobble.listened_at.unix_timestamp().to_string()));
+
+                params
+            })
+            .collect();
+
+        self.call(client, "track.scrobble", params).await
+    }
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn should_sign_params_in_order() {
+        let params = BTreeMap::from([
+            ("track".to_owned(), "Come Together".to_owned()),
+            ("artist".to_owned(), "The Beatles".to_owned()),
+            ("method".to_owned(), "track.updateNowPlaying".to_owned()),
+        ]);
+
+        // md5("artistThe Beatlesmethodtrack.updateNowPlayingtrackCome Togethersecret")
+        assert_eq!(signature(&params, "secret"), "8fcce717d0a2934ca39f915b0697c2a1");
+    }
+}
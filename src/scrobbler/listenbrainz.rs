@@ -0,0 +1,175 @@
+use hyper::Body;
+use hyper::header;
+use hyper::Request;
+use hyper::Uri;
+use serde::Serialize;
+use serde_json as json;
+
+use crate::persist::Scrobble;
+use crate::scrobbler::error::Error;
+use crate::scrobbler::http;
+use crate::scrobbler::result::Result;
+
+pub const MAX_BATCH_SIZE: usize = 100;
+
+#[derive(Serialize)]
+#[serde(rename_all = "snake_case")]
+enum ListenType {
+    PlayingNow,
+    Single,
+    Import,
+}
+
+#[derive(Serialize)]
+struct AdditionalInfo<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration_ms: Option<i64>,
+    submission_client: &'a str,
+    submission_client_version: &'a str,
+}
+
+#[derive(Serialize)]
+struct TrackMetadata<'a> {
+    artist_name: &'a str,
+    track_name: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    release_name: Option<&'a str>,
+    additional_info: AdditionalInfo<'a>,
+}
+
+#[derive(Serialize)]
+struct Listen<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    listened_at: Option<i64>,
+    track_metadata: TrackMetadata<'a>,
+}
+
+#[derive(Serialize)]
+struct Submission<'a> {
+    listen_type: ListenType,
+    payload: Vec<Listen<'a>>,
+}
+
+impl<'a> Listen<'a> {
+    fn new(scrobble: &'a Scrobble, is_playing_now: bool) -> Self {
+        Listen {
+            listened_at: (!is_playing_now).then(|| scrobble.listened_at.unix_timestamp()),
+            track_metadata: TrackMetadata {
+                artist_name: &scrobble.artist,
+                track_name: &scrobble.title,
+                release_name: scrobble.album.as_deref(),
+                additional_info: AdditionalInfo {
+                    duration_ms: scrobble.duration.is_positive()
+                        .then(|| scrobble.duration.whole_milliseconds() as i64),
+                    submission_client: env!("CARGO_PKG_NAME"),
+                    submission_client_version: env!("CARGO_PKG_VERSION"),
+                },
+            },
+        }
+    }
+}
+
+fn playing_now(scrobble: &Scrobble) -> Submission<'_> {
+    Submission {
+        listen_type: ListenType::PlayingNow,
+        payload: vec![Listen::new(scrobble, true)],
+    }
+}
+
+fn listens<'a>(scrobbles: &[&'a Scrobble]) -> Submission<'a> {
+    let listen_type = if scrobbles.len() == 1 {
+        ListenType::Single
+    } else {
+        ListenType::Import
+    };
+
+    Submission {
+        listen_type,
+        payload: scrobbles.iter()
+            .map(|scrobble| Listen::new(scrobble, false))
+            .collect(),
+    }
+}
+
+pub struct ListenBrainz {
+    uri: Uri,
+    token: String,
+}
+
+impl ListenBrainz {
+    pub fn new(url: &str, token: String) -> std::result::Result<Self, String> {
+        let uri = format!("{}/1/submit-listens", url.trim_end_matches('/'))
+            .parse()
+            .map_err(|err| format!("invalid url '{url}': {err}"))?;
+
+        Ok(ListenBrainz { uri, token })
+    }
+
+    async fn submit(&self, client: &http::Client, submission: Submission<'_>) -> Result<()> {
+        let body = json::to_vec(&submission)
+            .map_err(|err| Error::Rejected(format!("failed to serialize listens: {err}")))?;
+
+        let request = Request::post(&self.uri)
+            .header(header::AUTHORIZATION, format!("Token {}", self.token))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body))
+            .map_err(|err| Error::Rejected(format!("failed to build request: {err}")))?;
+
+        let (status, body) = http::send(client, request).await?;
+
+        if !status.is_success() {
+            return Err(http::into_error(status, &body));
+        }
+
+        Ok(())
+    }
+
+    pub async fn now_playing(&self, client: &http::Client, scrobble: &Scrobble) -> Result<()> {
+        self.submit(client, playing_now(scrobble)).await
+    }
+
+    pub async fn scrobble(&self, client: &http::Client, scrobbles: &[&Scrobble]) -> Result<()> {
+        self.submit(client, listens(scrobbles)).await
+    }
+}
+
+//////////////////////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use time::Duration;
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    #[test]
+    fn should_serialize_listens() {
+        let scrobble = Scrobble {
+            artist: "The Beatles".to_owned(),
+            title: "Come Together".to_owned(),
+            album: Some("Abbey Road".to_owned()),
+            album_artist: None,
+            duration: Duration::seconds(259),
+            listened_at: OffsetDateTime::UNIX_EPOCH + Duration::seconds(1_700_000_000),
+        };
+
+        let result = json::to_value(listens(&[&scrobble])).unwrap();
+
+        assert_eq!(result, json::json!({
+            "listen_type": "single",
+            "payload": [{
+                "listened_at": 1_700_000_000,
+                "track_metadata": {
+                    "artist_name": "The Beatles",
+                    "track_name": "Come Together",
+                    "release_name": "Abbey Road",
+                    "additional_info": {
+                        "duration_ms": 259_000,
+                        "submission_client": "mpdweb",
+                        "submission_client_version": env!("CARGO_PKG_VERSION"),
+                    },
+                },
+            }],
+        }));
+    }
+}
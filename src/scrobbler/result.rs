@@ -0,0 +1,3 @@
+use crate::scrobbler::error;
+
+pub type Result<T> = std::result::Result<T, error::Error>;
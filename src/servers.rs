@@ -0,0 +1,67 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::covers;
+use crate::history;
+use crate::index;
+use crate::mpd;
+
+#[derive(Clone)]
+pub struct Server {
+    pub partitions: mpd::PartitionRegistry,
+    pub history: history::Handle,
+    pub index: index::Handle,
+    pub covers: covers::Handle,
+}
+
+impl Server {
+    pub fn new(
+        partitions: mpd::PartitionRegistry,
+        history: history::Handle,
+        index: index::Handle,
+        covers: covers::Handle,
+    ) -> Self {
+        Server { partitions, history, index, covers }
+    }
+}
+
+// Servers are keyed by the name given in the config, the first
+// one is used for requests made without specifying a server.
+#[derive(Clone)]
+pub struct Registry {
+    default: String,
+    names: Arc<Vec<String>>,
+    servers: Arc<HashMap<String, Server>>,
+}
+
+impl Registry {
+    pub fn new(servers: Vec<(String, Server)>) -> Self {
+        let names = servers.iter()
+            .map(|(name, _)| name.clone())
+            .collect::<Vec<_>>();
+
+        let default = names.first()
+            .cloned()
+            .expect("expected at least one server");
+
+        Registry {
+            default,
+            names: Arc::new(names),
+            servers: Arc::new(servers.into_iter().collect()),
+        }
+    }
+}
+
+impl Registry {
+    pub fn get(&self, name: &str) -> Option<&Server> {
+        self.servers.get(name)
+    }
+
+    pub fn default_server(&self) -> &Server {
+        &self.servers[&self.default]
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+}